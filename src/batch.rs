@@ -0,0 +1,113 @@
+use crate::{Error, Request, Response};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+    thread,
+};
+
+/// Send many requests in parallel, with at most `max_concurrency` of them in flight at once.
+///
+/// The requests are sent by a pool of threads which is as large as `max_concurrency`, or as the number of requests if there are fewer,
+/// each taking the next request which has not been sent yet. A `max_concurrency` of 0 is treated as 1.
+/// The results are returned in the order of the requests, whether or not they succeeded.
+///
+/// # Panics
+///
+/// Panics if sending one of the requests panics, e.g. in one of its hooks.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let urls = ["example.org/a", "example.org/b", "example.org/c"];
+/// let results = send_all(urls.map(Request::get), 2);
+/// for (url, result) in urls.iter().zip(results) {
+///     match result {
+///         Ok(response) => println!("{url}: {}", response.status),
+///         Err(e) => println!("{url}: {e}"),
+///     }
+/// }
+/// ```
+pub fn send_all<'a>(
+    requests: impl IntoIterator<Item = Request<'a>>,
+    max_concurrency: usize,
+) -> Vec<Result<Response, Error>> {
+    let requests: Vec<_> = requests.into_iter().collect();
+    let results: Vec<_> = requests.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let workers = max_concurrency.clamp(1, requests.len().max(1));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(request) = requests.get(index) else {
+                    break;
+                };
+                let result = request.send();
+                *results[index]
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(result);
+            });
+        }
+    });
+    (results.into_iter())
+        .map(|result| result.into_inner().unwrap_or_else(PoisonError::into_inner))
+        .map(|result| result.expect("every request is sent"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::send_all;
+    use crate::Request;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+        sync::Arc,
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn batch() {
+        // a server which answers with the path, and tracks how many connections are open at once
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let (open, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        {
+            let (open, peak) = (Arc::clone(&open), Arc::clone(&peak));
+            thread::spawn(move || {
+                for stream in listener.incoming().take(6) {
+                    let (open, peak) = (Arc::clone(&open), Arc::clone(&peak));
+                    thread::spawn(move || {
+                        let mut stream = stream.unwrap();
+                        peak.fetch_max(open.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                        let mut buffer = [0; 4096];
+                        let n = stream.read(&mut buffer).unwrap();
+                        let request = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                        let path = request.split(' ').nth(1).unwrap().to_string();
+                        thread::sleep(Duration::from_millis(50));
+                        open.fetch_sub(1, Ordering::SeqCst);
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{path}",
+                            path.len()
+                        );
+                        stream.write_all(response.as_bytes()).unwrap();
+                    });
+                }
+            });
+        }
+
+        let urls: Vec<_> = (0..6).map(|i| format!("{url}/{i}")).collect();
+        let results = send_all(urls.iter().map(Request::get), 2);
+        let bodies: Vec<_> = (results.into_iter())
+            .map(|r| r.unwrap().text().into_owned())
+            .collect();
+        assert_eq!(bodies, ["/0", "/1", "/2", "/3", "/4", "/5"]);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert!(send_all(Vec::new(), 0).is_empty());
+    }
+}
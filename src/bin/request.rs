@@ -0,0 +1,223 @@
+//! A minimal curl-like command line client, built on the `request` crate.
+
+#![warn(clippy::all, clippy::pedantic)]
+
+use request::{Method, RedirectPolicy, Request};
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+    time::Duration,
+};
+
+/// Usage of the command, printed for `--help` and after invalid arguments.
+const USAGE: &str = "\
+usage: request [options] <url>
+
+options:
+  -X, --request <method>      method of the request, GET by default
+  -H, --header <name: value>  add a header, may be repeated
+  -d, --data <body>           send a body, with POST by default
+  -T, --upload-file <path>    upload a file, with PUT by default
+  -o, --output <path>         write the body to a file instead of stdout
+  -v, --verbose               print the sent and received heads to stderr
+  -f, --fail                  exit with an error if the response is not successful
+      --max-redirs <count>    follow at most this many redirects, 0 to not follow any
+  -m, --max-time <seconds>    abort the request after this many seconds
+      --connect-timeout <seconds>
+                              give up connecting after this many seconds
+  -h, --help                  print this message";
+
+/// The options given on the command line.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    url: String,
+    method: Option<Method>,
+    headers: Vec<(String, String)>,
+    data: Option<String>,
+    upload: Option<String>,
+    output: Option<String>,
+    verbose: bool,
+    fail: bool,
+    max_redirects: Option<usize>,
+    max_time: Option<Duration>,
+    connect_timeout: Option<Duration>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("request: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(22),
+        Err(e) => {
+            eprintln!("request: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse the arguments, returning `None` if help was asked for.
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-X" | "--request" => {
+                let name = value()?;
+                options.method = Some(
+                    (name.to_ascii_uppercase().parse())
+                        .map_err(|_| format!("unsupported method {name}"))?,
+                );
+            }
+            "-H" | "--header" => {
+                let header = value()?;
+                let (name, value) = header
+                    .split_once(':')
+                    .ok_or(format!("invalid header {header}"))?;
+                (options.headers).push((name.trim().to_string(), value.trim().to_string()));
+            }
+            "-d" | "--data" => options.data = Some(value()?),
+            "-T" | "--upload-file" => options.upload = Some(value()?),
+            "-o" | "--output" => options.output = Some(value()?),
+            "-v" | "--verbose" => options.verbose = true,
+            "-f" | "--fail" => options.fail = true,
+            "--max-redirs" => {
+                let count = value()?;
+                options.max_redirects = Some(
+                    count
+                        .parse()
+                        .map_err(|_| format!("invalid count {count}"))?,
+                );
+            }
+            "-m" | "--max-time" => options.max_time = Some(seconds(&value()?)?),
+            "--connect-timeout" => options.connect_timeout = Some(seconds(&value()?)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ if options.url.is_empty() => options.url = arg,
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+    if options.url.is_empty() {
+        return Err("missing url".to_string());
+    }
+    Ok(Some(options))
+}
+
+/// A duration in seconds, which may be fractional.
+fn seconds(value: &str) -> Result<Duration, String> {
+    (value.parse().ok())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or(format!("invalid number of seconds {value}"))
+}
+
+/// Send the request, returning whether the response was acceptable.
+fn run(options: &Options) -> Result<bool, request::Error> {
+    let default = match (&options.data, &options.upload) {
+        (_, Some(_)) => Method::PUT,
+        (Some(_), None) => Method::POST,
+        (None, None) => Method::GET,
+    };
+    let length = options.data.as_ref().map(|data| data.len().to_string());
+    let mut request = Request::new(options.url.as_str(), options.method.unwrap_or(default));
+    for (name, value) in &options.headers {
+        request = request.header(name, value);
+    }
+    if let (Some(data), Some(length)) = (&options.data, &length) {
+        request = request.body(data);
+        if !(options.headers.iter()).any(|(name, _)| name.eq_ignore_ascii_case("Content-Length")) {
+            request = request.header("Content-Length", length);
+        }
+    }
+    if let Some(path) = &options.upload {
+        request = request.upload_file(Path::new(path));
+    }
+    if let Some(max) = options.max_redirects {
+        request = match max {
+            0 => request.redirect(RedirectPolicy::None),
+            max => request.redirects(max),
+        };
+    }
+    if let Some(timeout) = options.max_time {
+        request = request.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        request = request.connect_timeout(timeout);
+    }
+
+    if options.verbose {
+        let message = request.to_bytes()?;
+        let end = (message.windows(4).position(|w| w == b"\r\n\r\n")).unwrap_or(message.len());
+        for line in String::from_utf8_lossy(&message[..end]).lines() {
+            eprintln!("> {line}");
+        }
+    }
+    let (response, mut body) = request.send_streaming()?;
+    if options.verbose {
+        let reason = &response.reason;
+        eprintln!(
+            "< {} {} {reason}",
+            response.version,
+            response.status.as_u16()
+        );
+        for (name, value) in response.headers.iter() {
+            eprintln!("< {name}: {value}");
+        }
+    }
+    if let Some(path) = &options.output {
+        body.save_to(path)?;
+    } else {
+        let mut stdout = io::stdout().lock();
+        io::copy(&mut body, &mut stdout)?;
+        stdout.flush()?;
+    }
+    Ok(!options.fail || response.status.as_u16() < 400)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Options};
+    use request::Method;
+    use std::time::Duration;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn arguments() {
+        let options = parse(args("-X put -H Accept:*/* -d hi -m 1.5 -v example.org")).unwrap();
+        assert_eq!(
+            options,
+            Some(Options {
+                url: "example.org".to_string(),
+                method: Some(Method::PUT),
+                headers: vec![("Accept".to_string(), "*/*".to_string())],
+                data: Some("hi".to_string()),
+                verbose: true,
+                max_time: Some(Duration::from_millis(1500)),
+                ..Options::default()
+            })
+        );
+        assert_eq!(parse(args("--help")), Ok(None));
+        assert_eq!(parse(args("-H")), Err("missing value for -H".to_string()));
+        assert_eq!(
+            parse(args("-X BREW pot")),
+            Err("unsupported method BREW".to_string())
+        );
+        assert_eq!(parse(args("-o")), Err("missing value for -o".to_string()));
+        assert_eq!(parse(args("a b")), Err("unexpected argument b".to_string()));
+        assert!(parse(Vec::new()).is_err());
+    }
+}
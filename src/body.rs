@@ -0,0 +1,425 @@
+use crate::{
+    encoding::Decoder, pool::Lease, request::timed_out, response::has_body, Headers, Method,
+    ReadWrite, Response,
+};
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    io::{self, prelude::*, BufReader, Chain, Cursor, Take},
+    mem,
+    path::Path,
+    time::Duration,
+};
+
+/// The body of a response, which is read from the connection as it is consumed.
+///
+/// It can be read with [`Read`], or iterated over in chunks.
+/// The body ends after `Content-Length` bytes, at the end of a chunked body, or when the server closes the connection.
+/// Chunked bodies are decoded, so only the content is read, and so are compressed bodies if decompression is enabled.
+/// If the connection was taken from a [`Pool`](crate::Pool), it is returned when the body is dropped after it was read to the end.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let (response, mut body) = Request::get("localhost:8000/large.iso").send_streaming().unwrap();
+/// let mut file = std::fs::File::create("large.iso").unwrap();
+/// std::io::copy(&mut body, &mut file).unwrap();
+/// ```
+pub struct Body {
+    /// The rest of the body.
+    reader: Decoder<Framed<Box<dyn ReadWrite>>>,
+    /// Size of the body as it is read, if it is known in advance.
+    length: Option<u64>,
+    /// Number of bytes read so far.
+    read: u64,
+    /// Maximum number of bytes which may be read.
+    limit: Option<u64>,
+    /// The lease on the connection, if it was taken from a pool.
+    lease: Option<Lease>,
+}
+impl Body {
+    /// The body of a response, starting with the bytes which were received along with the head.
+    ///
+    /// If the body is decoded, the headers which describe the encoded body are removed from the response.
+    pub(crate) fn new(
+        response: &mut Response,
+        method: Method,
+        received: Vec<u8>,
+        stream: Box<dyn ReadWrite>,
+        decompress: bool,
+    ) -> Result<Self, io::Error> {
+        let framed = Framed::new(response, method, received, stream);
+        let length = framed.length();
+        // the headers of a response without a body describe the body it would have had, so they are kept
+        let decompress = decompress && !matches!(framed, Framed::Empty(_));
+        let reader = Decoder::new(response, framed, decompress)?;
+        Ok(Self {
+            length: length.filter(|_| reader.is_identity()),
+            reader,
+            read: 0,
+            limit: None,
+            lease: None,
+        })
+    }
+
+    /// Return the connection to the pool it was taken from when the body is dropped, if the lease allows it.
+    pub(crate) fn set_lease(&mut self, lease: Lease) {
+        self.lease = Some(lease);
+    }
+
+    /// Limit the size of the body as it is read, erroring if the body is known to be larger.
+    pub(crate) fn set_limit(&mut self, limit: Option<u64>) -> Result<(), io::Error> {
+        self.limit = limit;
+        match (self.length, limit) {
+            (Some(length), Some(limit)) if length > limit => Err(too_large()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Size of the body as it is read, if it is known in advance.
+    pub(crate) fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    /// Trailer fields which were sent after a chunked body, once the body has been read to the end.
+    ///
+    /// Fields which describe how the message is framed, such as `Content-Length`, are left out.
+    #[must_use]
+    pub fn trailers(&self) -> Option<&Headers> {
+        self.reader.get_ref().trailers()
+    }
+
+    /// Write the rest of the body to a file, returning the number of bytes written.
+    ///
+    /// The body is written to a temporary file next to it, named after the file with a `.part` suffix,
+    /// which replaces the file once the whole body has been written.
+    /// The file is left untouched if receiving or writing the body fails.
+    ///
+    /// # Errors
+    ///
+    /// May error if the path has no file name, if the body can not be received, or if the file can not be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// let (response, body) = Request::get("localhost:8000/large.iso").send_streaming().unwrap();
+    /// body.save_to("large.iso").unwrap();
+    /// ```
+    pub fn save_to(self, path: impl AsRef<Path>) -> Result<u64, io::Error> {
+        let path = path.as_ref();
+        let Some(name) = path.file_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path has no file name",
+            ));
+        };
+        let mut partial = OsString::from(name);
+        partial.push(".part");
+        let partial = path.with_file_name(partial);
+
+        let mut body = self;
+        let result = fs::File::create(&partial).and_then(|mut file| {
+            let length = io::copy(&mut body, &mut file)?;
+            file.sync_all()?;
+            Ok(length)
+        });
+        match result.and_then(|length| fs::rename(&partial, path).map(|()| length)) {
+            Ok(length) => Ok(length),
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
+
+    /// Set the timeout for each read from the connection.
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        (self.reader.get_ref().stream()).map_or(Ok(()), |stream| stream.set_read_timeout(timeout))
+    }
+}
+impl Read for Body {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.reader.read(buf).map_err(timed_out)?;
+        self.read += length as u64;
+        if self.limit.is_some_and(|limit| self.read > limit) {
+            return Err(too_large());
+        }
+        Ok(length)
+    }
+}
+impl Iterator for Body {
+    type Item = Result<Vec<u8>, io::Error>;
+
+    /// Read the next chunk of the body, of at most 8 KiB.
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0u8; CHUNK];
+        match self.read(&mut chunk) {
+            Ok(0) => None,
+            Ok(length) => {
+                chunk.truncate(length);
+                Some(Ok(chunk))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+impl Drop for Body {
+    /// Return the connection to its pool, if the body was read to the end and nothing was received after it.
+    fn drop(&mut self) {
+        let Some(lease) = self.lease.take().filter(Lease::is_reusable) else {
+            return;
+        };
+        let reader = mem::replace(&mut self.reader, Decoder::Identity(Framed::Empty(None)));
+        if let Some(stream) = reader.into_inner().into_stream() {
+            lease.release(stream);
+        }
+    }
+}
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Body").finish_non_exhaustive()
+    }
+}
+
+/// Maximum size of the chunks yielded when iterating over a body.
+const CHUNK: usize = 8 * 1024;
+
+/// The error for a body which is larger than allowed.
+pub(crate) fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "response body too large")
+}
+
+/// A reader which ends where the body of a response ends, depending on how the length of the body is determined.
+pub(crate) enum Framed<R> {
+    /// The response has no body, and the connection is kept until the reader is dropped.
+    Empty(Option<Chain<Cursor<Vec<u8>>, R>>),
+    /// The body is sent in chunks, and ends with an empty chunk.
+    Chunked(Chunked<BufReader<Chain<Cursor<Vec<u8>>, R>>>),
+    /// The body ends after the number of bytes given by the `Content-Length` header.
+    Length(Take<Chain<Cursor<Vec<u8>>, R>>),
+    /// The body ends when the server closes the connection.
+    Close(Chain<Cursor<Vec<u8>>, R>),
+}
+impl<R: Read> Framed<R> {
+    /// The body of a response, starting with the bytes which were received along with the head.
+    pub(crate) fn new(response: &Response, method: Method, received: Vec<u8>, stream: R) -> Self {
+        // responses to HEAD requests never have a body, even if they have a `Content-Length` header
+        let reader = Cursor::new(received).chain(stream);
+        if method == Method::HEAD || !has_body(response.status) {
+            return Self::Empty(Some(reader));
+        }
+        if is_chunked(response) {
+            Self::Chunked(Chunked::new(BufReader::new(reader)))
+        } else if let Some(length) = response.headers.content_length() {
+            Self::Length(reader.take(length))
+        } else {
+            Self::Close(reader)
+        }
+    }
+
+    /// Size of the body, if it is known in advance.
+    fn length(&self) -> Option<u64> {
+        match self {
+            Self::Empty(_) => Some(0),
+            Self::Length(reader) => Some(reader.limit()),
+            Self::Chunked(_) | Self::Close(_) => None,
+        }
+    }
+
+    /// Trailer fields which were sent after a chunked body, once the body has been read to the end.
+    pub(crate) fn trailers(&self) -> Option<&Headers> {
+        match self {
+            Self::Chunked(reader) => reader.trailers(),
+            Self::Empty(_) | Self::Length(_) | Self::Close(_) => None,
+        }
+    }
+
+    /// The connection the body is read from.
+    fn stream(&self) -> Option<&R> {
+        match self {
+            Self::Empty(reader) => reader.as_ref().map(|reader| reader.get_ref().1),
+            Self::Chunked(reader) => Some(reader.inner.get_ref().get_ref().1),
+            Self::Length(reader) => Some(reader.get_ref().get_ref().1),
+            Self::Close(reader) => Some(reader.get_ref().1),
+        }
+    }
+
+    /// The connection, if the body was read to the end and nothing else was received, so it can be used for another request.
+    pub(crate) fn into_stream(self) -> Option<R> {
+        let reader = match self {
+            Self::Empty(reader) => reader?,
+            Self::Length(reader) if reader.limit() == 0 => reader.into_inner(),
+            Self::Chunked(reader)
+                if reader.trailers.is_some() && reader.inner.buffer().is_empty() =>
+            {
+                reader.inner.into_inner()
+            }
+            Self::Length(_) | Self::Chunked(_) | Self::Close(_) => return None,
+        };
+        let (received, stream) = reader.into_inner();
+        (received.position() == received.get_ref().len() as u64).then_some(stream)
+    }
+}
+impl<R: Read> Read for Framed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Empty(_) => Ok(0),
+            Self::Chunked(reader) => reader.read(buf),
+            Self::Length(reader) => match reader.read(buf)? {
+                0 if reader.limit() > 0 && !buf.is_empty() => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "body ended early",
+                )),
+                length => Ok(length),
+            },
+            Self::Close(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Whether the body of a response is sent with the chunked transfer coding.
+pub(crate) fn is_chunked(response: &Response) -> bool {
+    is_chunked_message(&response.version, &response.headers)
+}
+
+/// Whether the body of a message with a version and headers is sent with the chunked transfer coding.
+pub(crate) fn is_chunked_message(version: &str, headers: &Headers) -> bool {
+    // the chunked coding is always applied last, and did not exist before HTTP/1.1
+    version != "HTTP/1.0"
+        && (headers.get_all("Transfer-Encoding"))
+            .flat_map(|codings| codings.split(','))
+            .last()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+}
+
+/// A reader which decodes a body sent with the chunked transfer coding, stripping the chunk framing.
+///
+/// Trailer fields after the last chunk are kept, except for those which describe how the message is framed.
+pub(crate) struct Chunked<R> {
+    /// The encoded body.
+    inner: R,
+    /// Bytes left in the current chunk.
+    remaining: u64,
+    /// Trailer fields, once the last chunk has been read.
+    trailers: Option<Headers>,
+}
+impl<R: BufRead> Chunked<R> {
+    /// Decode a chunked body.
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            trailers: None,
+        }
+    }
+
+    /// Trailer fields, once the last chunk has been read.
+    pub(crate) fn trailers(&self) -> Option<&Headers> {
+        self.trailers.as_ref()
+    }
+
+    /// Read a line, without the line break.
+    fn line(&mut self) -> Result<Vec<u8>, io::Error> {
+        let mut line = Vec::new();
+        self.inner.read_until(b'\n', &mut line)?;
+        if !line.ends_with(b"\n") {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        while line.last().is_some_and(|b| matches!(b, b'\r' | b'\n')) {
+            line.pop();
+        }
+        Ok(line)
+    }
+}
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.trailers.is_some() || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            // chunk size in hex, optionally followed by extensions
+            let line = self.line()?;
+            let size = String::from_utf8_lossy(&line);
+            let size = size.split(';').next().unwrap_or_default().trim();
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+            if self.remaining == 0 {
+                let mut trailers = Headers::new();
+                loop {
+                    let line = self.line()?;
+                    if line.is_empty() {
+                        break;
+                    }
+                    let line = String::from_utf8_lossy(&line);
+                    let Some((name, value)) = line.split_once(':') else {
+                        continue;
+                    };
+                    if !FRAMING_FIELDS.iter().any(|f| f.eq_ignore_ascii_case(name)) {
+                        trailers.append(name, value.trim());
+                    }
+                }
+                self.trailers = Some(trailers);
+                return Ok(0);
+            }
+        }
+
+        let limit = usize::try_from(self.remaining)
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+        let length = self.inner.read(&mut buf[..limit])?;
+        if length == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        self.remaining -= length as u64;
+        if self.remaining == 0 && !self.line()?.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk is longer than its size",
+            ));
+        }
+        Ok(length)
+    }
+}
+
+/// Fields which describe how a message is framed, and which are ignored in trailers.
+const FRAMING_FIELDS: [&str; 4] = [
+    "Content-Length",
+    "Transfer-Encoding",
+    "Content-Encoding",
+    "Trailer",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::Chunked;
+    use std::io::{self, Read};
+
+    #[test]
+    fn chunked() {
+        let encoded = b"4\r\nWiki\r\n7;ext=1\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\nExpires: never\r\nContent-Length: 1\r\n\r\nrest";
+        let mut decoded = String::new();
+        let mut reader = Chunked::new(&encoded[..]);
+        reader.read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "Wikipedia in \r\nchunks.");
+        assert_eq!(reader.inner, b"rest");
+        let trailers = reader.trailers().unwrap();
+        assert_eq!(trailers.iter().collect::<Vec<_>>(), [("Expires", "never")]);
+
+        let error = Chunked::new(&b"5\r\nhel"[..])
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = Chunked::new(&b"zz\r\n"[..])
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
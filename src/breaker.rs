@@ -0,0 +1,150 @@
+use crate::{uri, Error, ErrorKind, Middleware, Next, Request, Response};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A circuit breaker, which stops sending requests to a host which keeps failing, and is used as a middleware of a [`Client`](crate::Client).
+///
+/// After a number of consecutive failures of requests to a host, the circuit for the host is opened,
+/// and requests to it fail right away, without being sent, until the cool-down has passed.
+/// Then a single request is sent to probe the host: if it succeeds the circuit is closed again, and otherwise it stays open for another cool-down.
+/// Requests fail if they return an error or a server error (5xx) response.
+///
+/// Requests which are not sent fail with [`ErrorKind::Connect`], and are not retryable, see [`Error::is_retryable`].
+/// Hosts are told apart by their name, ignoring the port. Clones of a circuit breaker share the state of the hosts.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// // stop sending requests for 30 seconds after 5 failures in a row
+/// let breaker = CircuitBreaker::new(5, std::time::Duration::from_secs(30));
+/// let client = Client::new().with(breaker);
+/// ```
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Consecutive failures after which the circuit of a host is opened.
+    threshold: u32,
+    /// How long the circuit of a host stays open before the host is probed.
+    cool_down: Duration,
+    /// Circuits of the hosts which requests have been sent to.
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+/// The state of the circuit of a host.
+#[derive(Debug, Clone, Copy, Default)]
+struct Circuit {
+    /// Failures since the last successful request.
+    failures: u32,
+    /// When the circuit may be probed again, if it is open.
+    open_until: Option<Instant>,
+    /// Whether a request is probing the host.
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    /// Create a circuit breaker which opens the circuit of a host after a number of consecutive failures,
+    /// and probes the host again after the cool-down.
+    ///
+    /// A number of 0 is treated as 1.
+    pub fn new(failures: u32, cool_down: Duration) -> Self {
+        Self {
+            threshold: failures.max(1),
+            cool_down,
+            circuits: Arc::default(),
+        }
+    }
+
+    /// Whether the circuit of a host is open, so requests to it fail without being sent.
+    ///
+    /// A circuit which may be probed is not open.
+    #[must_use]
+    pub fn is_open(&self, host: &str) -> bool {
+        let circuits = self.lock();
+        (circuits.get(&host.to_ascii_lowercase()))
+            .and_then(|circuit| circuit.open_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Lock the circuits of the hosts.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Circuit>> {
+        self.circuits.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Middleware for CircuitBreaker {
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error> {
+        let url = request.absolute_url();
+        let host = uri::host(&url).unwrap_or_default().to_ascii_lowercase();
+
+        // requests to a host with an open circuit fail, except for one probe after the cool-down
+        let mut circuits = self.lock();
+        let circuit = circuits.entry(host.clone()).or_default();
+        if let Some(until) = circuit.open_until {
+            if Instant::now() < until || circuit.probing {
+                drop(circuits);
+                debug!("not sending a request to {host}, as its circuit is open");
+                let e = io::Error::other(format!("circuit breaker is open for {host}"));
+                return Err(Error::new(ErrorKind::Connect, e));
+            }
+            circuit.probing = true;
+        }
+        drop(circuits);
+
+        let result = next.run(request);
+        let failed = (result.as_ref()).map_or(true, Response::is_server_error);
+        let mut circuits = self.lock();
+        let circuit = circuits.entry(host).or_default();
+        if failed {
+            circuit.failures = circuit.failures.saturating_add(1);
+            if circuit.probing || circuit.failures >= self.threshold {
+                circuit.open_until = Some(Instant::now() + self.cool_down);
+            }
+        } else {
+            *circuit = Circuit::default();
+        }
+        circuit.probing = false;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CircuitBreaker, Client, ErrorKind, Method, Mock, MockTransport};
+    use std::{io, thread, time::Duration};
+
+    #[test]
+    fn circuit_breaker() {
+        let transport = MockTransport::new()
+            .mock(Mock::new(Method::GET, "example.org").fail(io::ErrorKind::ConnectionRefused))
+            .mock(
+                Mock::new(Method::GET, "example.com")
+                    .respond("HTTP/1.1 503 Service Unavailable\r\n\r\n"),
+            )
+            .mock(Mock::new(Method::GET, "example.net"));
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        let client = Client::new().with(breaker.clone()).with(transport.clone());
+
+        // the circuit opens after two failures, and other hosts are unaffected
+        assert!(client.get("example.org").is_err());
+        assert!(!breaker.is_open("example.org"));
+        assert!(client.get("example.org").is_err());
+        assert!(breaker.is_open("example.org"));
+        let error = client.get("example.org").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Connect);
+        assert!(!error.is_retryable());
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(client.get("example.com").unwrap().status, 503);
+        assert_eq!(client.get("example.net").unwrap().status, 200);
+
+        // after the cool-down a failed probe opens the circuit again right away
+        thread::sleep(Duration::from_millis(60));
+        assert!(!breaker.is_open("example.org"));
+        assert!(client.get("example.org").is_err());
+        assert!(breaker.is_open("example.org"));
+        assert_eq!(transport.requests().len(), 5);
+    }
+}
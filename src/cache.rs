@@ -0,0 +1,644 @@
+use crate::{date::DateTime, random, Error, Headers, Method, Middleware, Next, Request, Response};
+use std::{
+    fmt::{self, Write},
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// An HTTP cache (RFC 9111), which is used as a middleware of a [`Client`](crate::Client).
+///
+/// Responses to GET requests are stored if they are cacheable, and served without sending the request while they are fresh.
+/// Stale responses are revalidated with `If-None-Match` or `If-Modified-Since`,
+/// and a `304 Not Modified` response is answered with the stored response, with its headers updated.
+/// Successful requests with other methods remove the stored response for their URL.
+/// Responses are kept in memory by default, or in another [`CacheStore`]. Clones of a cache share the stored responses.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let transport = MockTransport::new().mock(
+///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nCache-Control: max-age=600\r\n\r\nhello"),
+/// );
+/// let client = Client::new().with(Cache::new()).with(transport.clone());
+/// assert_eq!(client.get("example.org").unwrap().body, b"hello");
+/// assert_eq!(client.get("example.org").unwrap().body, b"hello");
+/// assert_eq!(transport.requests().len(), 1);
+/// ```
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct Cache {
+    /// Where the responses are stored.
+    store: Arc<dyn CacheStore>,
+}
+impl Cache {
+    /// Create an empty cache, which keeps up to 256 responses in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache which keeps responses in a store, e.g. a [`FileStore`] so they persist across runs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let cache = Cache::with_store(FileStore::new(std::env::temp_dir().join("my-tool-cache")));
+    /// let client = Client::new().with(cache);
+    /// ```
+    pub fn with_store(store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    /// Remove all stored responses.
+    pub fn clear(&self) {
+        self.store.clear();
+    }
+
+    /// The stored response for a URL, if the headers the response varies by match the request.
+    fn lookup(&self, url: &str, headers: &Headers) -> Option<Entry> {
+        let entry = Entry::decode(&self.store.get(url)?)?;
+        let matches =
+            (entry.vary.iter()).all(|(name, value)| headers.get(name) == value.as_deref());
+        (entry.url == url && matches).then_some(entry)
+    }
+
+    /// Store a response, replacing the stored response for the URL.
+    fn store(&self, entry: &Entry) {
+        self.store.put(&entry.url, entry.encode());
+    }
+
+    /// Remove the stored response for a URL.
+    fn remove(&self, url: &str) {
+        self.store.remove(url);
+    }
+}
+impl Default for Cache {
+    fn default() -> Self {
+        Self::with_store(MemoryStore::new())
+    }
+}
+impl Middleware for Cache {
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error> {
+        let url = request.absolute_url();
+        let directives =
+            CacheControl::parse(request.headers.get("Cache-Control").unwrap_or_default());
+        if request.method != Method::GET {
+            let method = request.method;
+            let response = next.run(request)?;
+            if !matches!(method, Method::HEAD | Method::OPTIONS | Method::TRACE)
+                && (response.is_success() || response.is_redirect())
+            {
+                self.remove(&url);
+            }
+            return Ok(response);
+        }
+        // requests which are already conditional expect the 304 response themselves, and partial responses are not stored
+        let conditional = request.headers.contains("If-None-Match")
+            || request.headers.contains("If-Modified-Since")
+            || request.if_modified_since.is_some();
+        let partial = request.headers.contains("Range") || request.range.is_some();
+        if directives.no_store || conditional || partial {
+            return next.run(request);
+        }
+
+        // serve the stored response while it is fresh, and revalidate it otherwise
+        let mut request = request;
+        let cached = self.lookup(&url, &request.headers);
+        if let Some(entry) = &cached {
+            let age = entry.age(SystemTime::now());
+            if !directives.no_cache && age < entry.lifetime {
+                debug!("serving {url} from the cache");
+                let mut response = entry.response.clone();
+                response.headers.insert("Age", age.as_secs().to_string());
+                return Ok(response);
+            }
+            if let Some(etag) = entry.response.etag() {
+                request.headers.insert("If-None-Match", etag);
+            }
+            if let Some(modified) = entry.response.headers.get("Last-Modified") {
+                request.headers.insert("If-Modified-Since", modified);
+            }
+        }
+        let headers = request.headers.clone();
+        let authorized = headers.contains("Authorization") || request.url_authorization().is_some();
+        let response = next.run(request)?;
+
+        match cached {
+            Some(mut entry) if response.status == 304 => {
+                debug!("revalidated {url} in the cache");
+                // the 304 response updates the stored headers, except for those describing the stored body
+                let mut names = response
+                    .headers
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>();
+                names.retain(|name| !name.eq_ignore_ascii_case("Content-Length"));
+                names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+                for name in names {
+                    entry.response.headers.remove(name);
+                    let values = response.headers.get_all(name).map(str::to_string);
+                    entry
+                        .response
+                        .headers
+                        .extend(values.map(|value| (name, value)));
+                }
+                entry.response.connection = response.connection;
+                entry.response.timings = response.timings;
+                match Entry::new(url.clone(), entry.response.clone(), &headers) {
+                    Some(entry) => self.store(&entry),
+                    None => self.remove(&url),
+                }
+                Ok(entry.response)
+            }
+            // responses to requests with credentials are only shared with other requests if they are public
+            _ if authorized && !response.cache_control().public => Ok(response),
+            _ => {
+                match Entry::new(url.clone(), response.clone(), &headers) {
+                    Some(entry) => self.store(&entry),
+                    None => self.remove(&url),
+                }
+                Ok(response)
+            }
+        }
+    }
+}
+
+/// A stored response.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// URL of the request.
+    url: String,
+    /// Values of the request headers the response varies by.
+    vary: Vec<(String, Option<String>)>,
+    /// The stored response.
+    response: Response,
+    /// When the response was stored.
+    stored: SystemTime,
+    /// Age of the response when it was received.
+    initial_age: Duration,
+    /// How long the response is fresh for after it was generated.
+    lifetime: Duration,
+}
+impl Entry {
+    /// Prepare a response to a request with the given headers for storage, if it is cacheable.
+    fn new(url: String, response: Response, headers: &Headers) -> Option<Self> {
+        let directives = response.cache_control();
+        let varies = (response.headers.get_all("Vary"))
+            .flat_map(|names| names.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        let status = response.status;
+        if directives.no_store
+            || varies.contains(&"*")
+            || status.is_informational()
+            || status == 206
+            || status == 304
+        {
+            return None;
+        }
+
+        // the freshness lifetime is given explicitly, or guessed from how long ago the resource was last modified
+        let stored = SystemTime::now();
+        let date = (response.headers.get("Date"))
+            .and_then(DateTime::parse)
+            .map_or(stored, SystemTime::from);
+        let explicit = directives.max_age.or_else(|| {
+            let expires = response.expires()?;
+            Some(expires.duration_since(date).unwrap_or_default())
+        });
+        let heuristic = (response.last_modified())
+            .filter(|_| HEURISTIC.contains(&response.status.as_u16()))
+            .map(|modified| date.duration_since(modified).unwrap_or_default() / 10);
+        let has_validator = response.etag().is_some() || response.last_modified().is_some();
+        let cacheable = explicit.is_some() || HEURISTIC.contains(&response.status.as_u16());
+        if !cacheable || explicit.or(heuristic).is_none() && !has_validator {
+            return None;
+        }
+        let lifetime = if directives.no_cache {
+            Duration::ZERO
+        } else {
+            explicit.or(heuristic).unwrap_or_default()
+        };
+
+        Some(Self {
+            url,
+            vary: (varies.iter())
+                .map(|name| (name.to_string(), headers.get(name).map(str::to_string)))
+                .collect(),
+            initial_age: response.age().unwrap_or_default(),
+            response,
+            stored,
+            lifetime,
+        })
+    }
+
+    /// Age of the stored response at a point in time.
+    fn age(&self, now: SystemTime) -> Duration {
+        self.initial_age + now.duration_since(self.stored).unwrap_or_default()
+    }
+
+    /// Serialize the entry for a store, as lines of metadata followed by an empty line and the response.
+    fn encode(&self) -> Vec<u8> {
+        let stored = (self.stored.duration_since(UNIX_EPOCH)).unwrap_or_default();
+        let (age, lifetime) = (self.initial_age.as_secs(), self.lifetime.as_secs());
+        let mut head = format!("{}\n{} {age} {lifetime}\n", self.url, stored.as_secs());
+        for (name, value) in &self.vary {
+            let _ = match value {
+                Some(value) => writeln!(head, "{name}: {value}"),
+                None => writeln!(head, "{name}"),
+            };
+        }
+        let response = &self.response;
+        let _ = write!(
+            head,
+            "\n{} {} {}\r\n",
+            response.version, response.status, response.reason
+        );
+        for (name, value) in response.headers.iter() {
+            let _ = write!(head, "{name}: {value}\r\n");
+        }
+        head.push_str("\r\n");
+        let mut bytes = head.into_bytes();
+        bytes.extend(&response.body);
+        bytes
+    }
+
+    /// Deserialize an entry which was serialized with [`Entry::encode`].
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let split = bytes.windows(2).position(|w| w == b"\n\n")?;
+        let metadata = std::str::from_utf8(&bytes[..split]).ok()?;
+        let mut lines = metadata.lines();
+        let url = lines.next()?.to_string();
+        let times = (lines.next()?.split(' '))
+            .map(|n| n.parse().ok().map(Duration::from_secs))
+            .collect::<Option<Vec<_>>>()?;
+        let [stored, initial_age, lifetime] = times[..] else {
+            return None;
+        };
+        let vary = lines
+            .map(|line| match line.split_once(": ") {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (line.to_string(), None),
+            })
+            .collect();
+        Some(Self {
+            url,
+            vary,
+            response: Response::parse(&bytes[split + 2..]).ok()?,
+            stored: UNIX_EPOCH + stored,
+            initial_age,
+            lifetime,
+        })
+    }
+}
+
+/// A place where a [`Cache`] keeps the stored responses, such as memory or a directory.
+///
+/// Responses are stored as opaque bytes under the URL of the request.
+/// Failing to store a response is not an error, as the response is then requested again the next time.
+pub trait CacheStore: Send + Sync {
+    /// The bytes stored under a key, if there are any.
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Store bytes under a key, replacing what was stored under it before.
+    fn put(&self, key: &str, value: Vec<u8>);
+
+    /// Remove what is stored under a key.
+    fn remove(&self, key: &str);
+
+    /// Remove everything which is stored.
+    fn clear(&self);
+}
+impl fmt::Debug for dyn CacheStore + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CacheStore")
+    }
+}
+
+/// A cache store which keeps responses in memory, up to a maximum number of them.
+///
+/// Clones of a store share the stored responses.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    /// Keys and stored bytes, from the least recently stored ones.
+    entries: Arc<Mutex<Vec<Stored>>>,
+    /// Maximum number of stored responses.
+    capacity: usize,
+}
+impl MemoryStore {
+    /// Create an empty store, which keeps up to 256 responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of stored responses, after which the least recently stored ones are removed.
+    pub fn capacity(self, entries: usize) -> Self {
+        let mut store = self;
+        store.capacity = entries;
+        store
+    }
+
+    /// Number of stored responses, including stale ones.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether no responses are stored.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lock the stored responses.
+    fn entries(&self) -> MutexGuard<'_, Vec<Stored>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            capacity: 256,
+        }
+    }
+}
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let entries = self.entries();
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn put(&self, key: &str, value: Vec<u8>) {
+        let mut entries = self.entries();
+        entries.retain(|(k, _)| k != key);
+        entries.push((key.to_string(), value));
+        let excess = entries.len().saturating_sub(self.capacity);
+        entries.drain(..excess);
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries().retain(|(k, _)| k != key);
+    }
+
+    fn clear(&self) {
+        self.entries().clear();
+    }
+}
+
+/// A key and the bytes stored under it.
+type Stored = (String, Vec<u8>);
+
+/// A cache store which keeps each response in a file in a directory, so the cache persists across runs and can be shared between programs.
+///
+/// The directory is created when the first response is stored.
+/// Files are named after a hash of the key, and are replaced atomically so concurrent readers never see a partial response.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    /// Directory the responses are stored in.
+    directory: PathBuf,
+}
+impl FileStore {
+    /// Create a store which keeps responses in a directory.
+    #[must_use]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Path of the file a key is stored in.
+    fn path(&self, key: &str) -> PathBuf {
+        // 64-bit FNV-1a, which is stable across runs and platforms, unlike the hasher of the standard library
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        self.directory.join(format!("{hash:016x}.{FILE_EXTENSION}"))
+    }
+}
+impl CacheStore for FileStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(key)).ok()
+    }
+
+    fn put(&self, key: &str, value: Vec<u8>) {
+        let path = self.path(key);
+        let partial = path.with_extension(format!("{}.{}", FILE_EXTENSION, random::u64()));
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|()| fs::write(&partial, value))
+            .and_then(|()| fs::rename(&partial, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    fn clear(&self) {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        // only remove the files of the store, in case the directory is shared
+        for path in entries.filter_map(|e| Some(e.ok()?.path())) {
+            if path.extension().is_some_and(|e| e == FILE_EXTENSION) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Extension of the files of a [`FileStore`].
+const FILE_EXTENSION: &str = "http-cache";
+
+/// Statuses of responses which may be stored without explicit freshness information.
+const HEURISTIC: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Directives of a `Cache-Control` header, which describe how a response may be cached.
+///
+/// Directives which are not present are `false` or `None`, and unknown directives are ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::time::Duration;
+/// let cache = CacheControl::parse("public, max-age=3600, must-revalidate");
+/// assert_eq!(cache.max_age, Some(Duration::from_secs(3600)));
+/// assert!(cache.public && cache.must_revalidate && !cache.no_store);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CacheControl {
+    /// How long the response stays fresh after it was generated (`max-age`).
+    pub max_age: Option<Duration>,
+    /// How long the response stays fresh in shared caches, overriding `max-age` for them (`s-maxage`).
+    pub s_maxage: Option<Duration>,
+    /// For how long after it becomes stale the response may be used while it is revalidated (`stale-while-revalidate`).
+    pub stale_while_revalidate: Option<Duration>,
+    /// For how long after it becomes stale the response may be used if revalidating it fails (`stale-if-error`).
+    pub stale_if_error: Option<Duration>,
+    /// The response must not be stored (`no-store`).
+    pub no_store: bool,
+    /// The response must be revalidated before each use (`no-cache`).
+    pub no_cache: bool,
+    /// Once stale, the response must be revalidated before it is used (`must-revalidate`).
+    pub must_revalidate: bool,
+    /// The response may only be stored by private caches, such as the one of a single user (`private`).
+    pub private: bool,
+    /// The response may be stored by any cache (`public`).
+    pub public: bool,
+    /// The response will not change while it is fresh, so it need not be revalidated (`immutable`).
+    pub immutable: bool,
+    /// The response must not be transformed, e.g. recompressed, by intermediaries (`no-transform`).
+    pub no_transform: bool,
+}
+impl CacheControl {
+    /// Parse the value of a `Cache-Control` header, ignoring directives which are unknown or malformed.
+    ///
+    /// Directive names are case-insensitive, and arguments may be quoted.
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        let mut cache = Self::default();
+        for directive in value.split(',') {
+            let (name, argument) = directive.split_once('=').unwrap_or((directive, ""));
+            let name = name.trim().to_ascii_lowercase();
+            let seconds = argument
+                .trim()
+                .trim_matches('"')
+                .parse::<u64>()
+                .ok()
+                .map(Duration::from_secs);
+            match name.as_str() {
+                "max-age" => cache.max_age = seconds,
+                "s-maxage" => cache.s_maxage = seconds,
+                "stale-while-revalidate" => cache.stale_while_revalidate = seconds,
+                "stale-if-error" => cache.stale_if_error = seconds,
+                "no-store" => cache.no_store = true,
+                "no-cache" => cache.no_cache = true,
+                "must-revalidate" => cache.must_revalidate = true,
+                "private" => cache.private = true,
+                "public" => cache.public = true,
+                "immutable" => cache.immutable = true,
+                "no-transform" => cache.no_transform = true,
+                _ => {}
+            }
+        }
+        cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, CacheControl, CacheStore, FileStore, MemoryStore};
+    use crate::{Client, Method, Next, Request, Response};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[test]
+    fn cache() {
+        // a server which counts requests, and checks the validator of the stale response
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        let server = move |request: Request, _: Next| {
+            let message = request.to_string();
+            received.lock().unwrap().push(message.clone());
+            let response = match (request.method, &*request.url) {
+                (Method::GET, "example.org/fresh") => "HTTP/1.1 200 OK\r\nCache-Control: max-age=600\r\nVary: Accept-Language\r\n\r\nfresh",
+                (Method::GET, "example.org/stale") if message.contains("If-None-Match: \"v1\"") => "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nX-Checked: yes\r\n\r\n",
+                (Method::GET, "example.org/stale") => "HTTP/1.1 200 OK\r\nCache-Control: no-cache\r\nETag: \"v1\"\r\n\r\nstale",
+                (Method::GET, _) => "HTTP/1.1 200 OK\r\nCache-Control: no-store\r\n\r\nprivate",
+                _ => "HTTP/1.1 204 No Content\r\n\r\n",
+            };
+            Response::parse(response).map_err(|e| std::io::Error::other(e).into())
+        };
+        let store = MemoryStore::new();
+        let client = Client::new()
+            .with(Cache::with_store(store.clone()))
+            .with(server);
+        let count = || requests.lock().unwrap().len();
+
+        for _ in 0..2 {
+            let response = client.get("example.org/fresh").unwrap();
+            assert_eq!(response.body, b"fresh");
+        }
+        assert_eq!(count(), 1);
+        let request = Request::get("example.org/fresh").header("Accept-Language", "nl");
+        client.send(request).unwrap();
+        assert_eq!(count(), 2);
+
+        // stale responses are revalidated, and a 304 response is answered with the stored response
+        client.get("example.org/stale").unwrap();
+        let response = client.get("example.org/stale").unwrap();
+        assert_eq!(count(), 4);
+        assert_eq!(
+            (response.status.as_u16(), &response.body[..]),
+            (200, &b"stale"[..])
+        );
+        assert_eq!(response.headers.get("X-Checked"), Some("yes"));
+
+        // responses which must not be stored are not, and unsafe requests invalidate stored responses
+        client.get("example.org/secret").unwrap();
+        assert_eq!(store.len(), 2);
+        client.post("example.org/fresh", "data").unwrap();
+        assert_eq!(store.len(), 1);
+        client.get("example.org/fresh").unwrap();
+        assert_eq!(count(), 7);
+    }
+
+    #[test]
+    fn file_store() {
+        let directory = std::env::temp_dir().join(format!("request-cache-{}", std::process::id()));
+        let requests = Arc::new(Mutex::new(0));
+        let count = requests.clone();
+        let server = move |_: Request, _: Next| {
+            *count.lock().unwrap() += 1;
+            let response = "HTTP/1.1 200 OK\r\nCache-Control: max-age=600\r\nVary: Accept\r\n\r\n\x00\n\nbinary";
+            Response::parse(response).map_err(|e| std::io::Error::other(e).into())
+        };
+
+        // the response is served from the directory by another cache, as if by another run of the program
+        for _ in 0..2 {
+            let client = Client::new()
+                .with(Cache::with_store(FileStore::new(&directory)))
+                .with(server.clone());
+            let response = client.get("example.org/data").unwrap();
+            assert_eq!(response.body, b"\x00\n\nbinary");
+            assert_eq!(response.headers.get("Vary"), Some("Accept"));
+        }
+        assert_eq!(*requests.lock().unwrap(), 1);
+
+        let store = FileStore::new(&directory);
+        assert!(store.get("example.org/data").is_none());
+        assert!(store.get("http://example.org/data").is_some());
+        store.clear();
+        assert!(store.get("http://example.org/data").is_none());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn cache_control() {
+        let cache = CacheControl::parse(
+            "No-Cache, private=\"Set-Cookie\", max-age=\"90\", s-maxage=x, stale-if-error=5, unknown",
+        );
+        assert!(cache.no_cache && cache.private);
+        assert!(!cache.no_store && !cache.public);
+        assert_eq!(cache.max_age, Some(Duration::from_secs(90)));
+        assert_eq!(cache.s_maxage, None);
+        assert_eq!(cache.stale_if_error, Some(Duration::from_secs(5)));
+        assert_eq!(CacheControl::parse(""), CacheControl::default());
+    }
+}
@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+/// Decode text in a character encoding, given by its label, e.g. `utf-8` or `ISO-8859-1`.
+///
+/// A byte order mark takes precedence over the label, like in browsers.
+/// Latin-1 labels are decoded as Windows-1252, which is a superset, and unknown encodings are decoded as UTF-8.
+/// Invalid sequences are replaced with U+FFFD.
+pub(crate) fn decode<'b>(bytes: &'b [u8], label: Option<&str>) -> Cow<'b, str> {
+    let label = label.unwrap_or("utf-8").trim().to_ascii_lowercase();
+    match bytes {
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest),
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes).into(),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes).into(),
+        _ => match label.as_str() {
+            "utf-16" | "utf-16le" => utf16(bytes, u16::from_le_bytes).into(),
+            "utf-16be" => utf16(bytes, u16::from_be_bytes).into(),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "l1" | "us-ascii" | "ascii"
+            | "windows-1252" | "cp1252" => bytes
+                .iter()
+                .map(|&b| windows1252(b))
+                .collect::<String>()
+                .into(),
+            _ => String::from_utf8_lossy(bytes),
+        },
+    }
+}
+
+/// Decode UTF-16 with the given byte order.
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut text = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect::<String>();
+    if bytes.len() % 2 == 1 {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+/// Decode a Windows-1252 byte, which matches Latin-1 except for printable characters in 0x80-0x9F.
+fn windows1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}',
+        '\u{8f}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}',
+        '\u{2014}', '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}',
+        '\u{178}',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn charsets() {
+        assert_eq!(decode("héllo".as_bytes(), None), "héllo");
+        assert_eq!(decode(b"h\xe9llo \x80", Some("ISO-8859-1")), "héllo €");
+        assert_eq!(decode(b"h\x00i\x00", Some("UTF-16")), "hi");
+        assert_eq!(decode(b"\xfe\xff\x00h\x00i", Some("utf-8")), "hi");
+        assert_eq!(decode(b"\xef\xbb\xbfhi", Some("latin1")), "hi");
+        assert_eq!(decode(b"h\xffi", Some("x-unknown")), "h\u{fffd}i");
+    }
+}
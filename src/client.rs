@@ -0,0 +1,699 @@
+use crate::{
+    dns, uri, CookieJar, DnsResolver, Error, Metrics, Observer, OnRedirect, OnRequest, OnResponse,
+    Pool, Request, Resolver, Response, TcpConnector, Timings, Url,
+};
+use std::{
+    borrow::Cow,
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, PoisonError, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The client which is used by [`get`](crate::get) and [`post`](crate::post), see [`set_default_client`].
+static DEFAULT_CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+/// Set the client which is used by the free functions [`get`](crate::get) and [`post`](crate::post).
+///
+/// This lets an application configure requests once at startup, e.g. with default headers, a timeout or a proxy,
+/// so requests which libraries send with these functions inherit the settings. Requests which are sent directly are not affected.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::time::Duration;
+/// request::set_default_client(
+///     Client::new()
+///         .user_agent(Some("my-app/1.0"))
+///         .timeout(Duration::from_secs(10))
+///         .default_header("Accept", "application/json"),
+/// );
+/// ```
+pub fn set_default_client(client: Client) {
+    *DEFAULT_CLIENT
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(client);
+}
+
+/// The client which is used by the free functions [`get`](crate::get) and [`post`](crate::post),
+/// which is a client without any options unless one was set with [`set_default_client`].
+pub fn default_client() -> Client {
+    let client = DEFAULT_CLIENT
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    client.clone().unwrap_or_default()
+}
+
+/// A client which sends requests through a stack of middleware.
+///
+/// Middleware is run in the order it was added, so the first middleware sees the request first and the response last.
+///
+/// By default, each request opens its own connection, which is closed once its response has been received.
+/// Connections are reused between requests if the client has a [`Pool`], see [`Client::pool`],
+/// which also limits how many connections are open and closes those which are idle for too long.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let client = Client::new().with(|request: Request, next: Next| {
+///     println!("sending {request}");
+///     let response = next.run(request)?;
+///     println!("received {}", response.status);
+///     Ok(response)
+/// });
+/// // ... send requests with `client.send(Request::get("localhost:8000"))` ...
+/// ```
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    /// Middleware which handles every request, outermost first.
+    middleware: Vec<Arc<dyn Middleware>>,
+    /// `User-Agent` header for every request, or `None` to keep the one of the request.
+    #[allow(clippy::option_option)]
+    user_agent: Option<Option<String>>,
+    /// Timeout for every request which does not have its own.
+    timeout: Option<Duration>,
+    /// URL of the proxy every request without its own proxy is sent through.
+    proxy: Option<String>,
+    /// Headers which are sent with every request which does not have them already.
+    default_headers: Vec<(String, String)>,
+    /// Options for the TCP connections of every request.
+    tcp: Option<TcpConnector>,
+    /// Pool which the connections of every request are taken from.
+    pool: Option<Pool>,
+    /// Cookies which are sent with every request, and which store the cookies set by responses.
+    cookie_jar: Option<Arc<CookieJar>>,
+    /// How the hosts of every request are resolved, if not with the built-in resolver.
+    resolver: Option<Arc<dyn Resolver>>,
+    /// Addresses which hosts are connected to without resolving them.
+    overrides: Vec<(String, IpAddr)>,
+    /// URL which the URLs of requests without a scheme are resolved against.
+    base_url: Option<Url>,
+    /// Observers which are notified when requests start and finish.
+    observers: Vec<Arc<dyn Observer>>,
+    /// Hook which is passed the message of every request before it is sent.
+    on_request: Option<Arc<dyn OnRequest>>,
+    /// Hook which is passed every final response once it has been received.
+    on_response: Option<Arc<dyn OnResponse>>,
+    /// Hook which is notified of every redirect which is followed.
+    on_redirect: Option<Arc<dyn OnRedirect>>,
+}
+impl Client {
+    /// Create a new client without any middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a middleware to the client.
+    ///
+    /// It runs after (inside of) the middleware which was added before it.
+    pub fn with(self, middleware: impl Middleware + 'static) -> Self {
+        let mut client = self;
+        client.middleware.push(Arc::new(middleware));
+        client
+    }
+
+    /// Notify an observer when requests start and finish, e.g. to collect metrics, see [`Observer`].
+    pub fn observe(self, observer: impl Observer + 'static) -> Self {
+        let mut client = self;
+        client.observers.push(Arc::new(observer));
+        client
+    }
+
+    /// Pass the message of every request to a hook right before it is sent, see [`Request::on_request`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new()
+    ///     .on_request(|message: &[u8]| eprintln!("> {}", String::from_utf8_lossy(message)))
+    ///     .on_response(|response: &Response| eprintln!("< {} {}", response.status, response.reason));
+    /// ```
+    pub fn on_request(self, hook: impl OnRequest + 'static) -> Self {
+        let mut client = self;
+        client.on_request = Some(Arc::new(hook));
+        client
+    }
+
+    /// Pass every final response to a hook once it has been received, see [`Request::on_response`].
+    pub fn on_response(self, hook: impl OnResponse + 'static) -> Self {
+        let mut client = self;
+        client.on_response = Some(Arc::new(hook));
+        client
+    }
+
+    /// Notify a hook of every redirect which is followed, see [`Request::on_redirect`].
+    pub fn on_redirect(self, hook: impl OnRedirect + 'static) -> Self {
+        let mut client = self;
+        client.on_redirect = Some(Arc::new(hook));
+        client
+    }
+
+    /// Set the `User-Agent` header sent with every request, or send none at all.
+    ///
+    /// This replaces the user agent of each request, but not a `User-Agent` header added with [`Request::header`].
+    pub fn user_agent(self, agent: Option<&str>) -> Self {
+        let mut client = self;
+        client.user_agent = Some(agent.map(str::to_string));
+        client
+    }
+
+    /// Set a timeout for the entire request, for every request which does not have its own, see [`Request::timeout`].
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let mut client = self;
+        client.timeout = Some(timeout);
+        client
+    }
+
+    /// Send every request which does not have its own proxy through a proxy, see [`Request::proxy`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new().proxy("http://proxy.example.org:3128");
+    /// ```
+    pub fn proxy(self, url: &str) -> Self {
+        let mut client = self;
+        client.proxy = Some(url.to_string());
+        client
+    }
+
+    /// Add a header to every request which does not have a header with the same name already.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new()
+    ///     .default_header("Accept", "application/json")
+    ///     .default_header("X-Api-Key", "mF_9.B5f-4.1JqM");
+    /// ```
+    pub fn default_header(self, name: &str, value: &str) -> Self {
+        let mut client = self;
+        client
+            .default_headers
+            .push((name.to_string(), value.to_string()));
+        client
+    }
+
+    /// Set the options for the TCP connections of every request, such as `TCP_NODELAY` and keepalive.
+    ///
+    /// This replaces the options of each request, see [`Request::tcp`].
+    pub fn tcp(self, options: TcpConnector) -> Self {
+        let mut client = self;
+        client.tcp = Some(options);
+        client
+    }
+
+    /// Reuse the connections of requests, keeping them in a pool once their responses have been received, see [`Request::pool`].
+    ///
+    /// The pool can be shared with other clients, as clones of a pool share their connections.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new().pool(Pool::new().max_idle_per_host(2));
+    /// ```
+    pub fn pool(self, pool: Pool) -> Self {
+        let mut client = self;
+        client.pool = Some(pool);
+        client
+    }
+
+    /// Keep cookies in a cookie jar, which sends them with every request and stores the cookies set by every response.
+    ///
+    /// The jar can be shared with other clients, or inspected after requests are sent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// # use std::sync::Arc;
+    /// let jar = Arc::new(CookieJar::new());
+    /// let client = Client::new().cookie_jar(jar.clone());
+    /// // ... log in with `client.post(...)`, and the session cookie is sent with later requests ...
+    /// ```
+    pub fn cookie_jar(self, jar: Arc<CookieJar>) -> Self {
+        let mut client = self;
+        client.cookie_jar = Some(jar);
+        client
+    }
+
+    /// Resolve the hosts of every request with a custom resolver, see [`Request::resolver`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new().resolver(SystemResolver);
+    /// ```
+    pub fn resolver(self, resolver: impl Resolver + 'static) -> Self {
+        let mut client = self;
+        client.resolver = Some(Arc::new(resolver));
+        client
+    }
+
+    /// Resolve the hosts of every request by asking these nameservers instead of the system nameservers.
+    ///
+    /// Nameservers are given as an IP address, optionally with a port, which defaults to 53.
+    /// Addresses which can not be parsed are ignored. The answers are cached separately for each client.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let client = Client::new().dns_servers(&["10.0.0.2:53", "[fd00::2]:53", "10.0.0.3"]);
+    /// ```
+    pub fn dns_servers(self, servers: &[&str]) -> Self {
+        let servers = (servers.iter())
+            .filter_map(|server| {
+                let address = server.parse::<SocketAddr>().ok();
+                let address = address.or_else(|| Some(SocketAddr::new(server.parse().ok()?, 53)));
+                if address.is_none() {
+                    debug!("ignoring invalid nameserver {server}");
+                }
+                address
+            })
+            .collect::<Vec<_>>();
+        self.resolver(DnsResolver::new().servers(&servers))
+    }
+
+    /// Connect to an address for a host instead of resolving it, for every request, see [`Request::resolve_override`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// # use std::net::IpAddr;
+    /// // send the requests for the production host to a staging server
+    /// let client = Client::new().resolve_override("api.example.org", IpAddr::from([192, 0, 2, 10]));
+    /// ```
+    pub fn resolve_override(self, host: &str, address: IpAddr) -> Self {
+        let mut client = self;
+        client.overrides.push((host.to_string(), address));
+        client
+    }
+
+    /// Resolve the URLs of requests against a base URL, so requests can be sent with only a path.
+    ///
+    /// URLs with a scheme are used as-is, while all others are resolved like links (see [`Url::join`]),
+    /// so a host without a scheme such as `example.org` is treated as a relative path.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// let client = Client::new().base_url("https://api.example.org/v2/".parse().unwrap());
+    /// // sends a request to https://api.example.org/v2/items
+    /// let response = client.get("items");
+    /// ```
+    pub fn base_url(self, url: Url) -> Self {
+        let mut client = self;
+        client.base_url = Some(url);
+        client
+    }
+
+    /// Resolve the host of an URL in a background thread, so its addresses are cached before the first request.
+    ///
+    /// The host is resolved like the requests of the client would resolve it, with its resolver and overrides.
+    /// Connections are not opened ahead of time, even if the client has a pool.
+    /// The handle can be joined to wait for the addresses, or dropped to let the thread finish on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// let client = Client::new();
+    /// client.prefetch("https://api.example.org/items");
+    /// // ... do other work, the lookup no longer delays the request ...
+    /// let response = client.get("https://api.example.org/items").unwrap();
+    /// ```
+    #[allow(clippy::must_use_candidate)]
+    pub fn prefetch(&self, url: &str) -> thread::JoinHandle<Result<Vec<IpAddr>, io::Error>> {
+        let url = self.absolute(url).unwrap_or(Cow::Borrowed(url));
+        let host = uri::host(&url).unwrap_or(&url).to_string();
+        let overrides = (self.overrides.iter())
+            .filter(|(name, _)| name.eq_ignore_ascii_case(&host))
+            .map(|&(_, address)| address)
+            .collect::<Vec<_>>();
+        let resolver = self.resolver.clone();
+        thread::spawn(move || {
+            if !overrides.is_empty() {
+                return Ok(overrides);
+            }
+            match (resolver, dns::literal(&host)) {
+                (_, Some(address)) => Ok(vec![address]),
+                (Some(resolver), None) => resolver.resolve(&host),
+                (None, None) => dns::resolve(&host, None),
+            }
+        })
+    }
+
+    /// Send a request through the middleware.
+    ///
+    /// # Errors
+    ///
+    /// May error if a middleware fails, or for the same reasons as [`Request::send`].
+    pub fn send<'a>(&'a self, request: Request<'a>) -> Result<Response, Error> {
+        let request = self.prepare(request)?;
+        let next = Next {
+            middleware: &self.middleware,
+        };
+        if self.observers.is_empty() {
+            return next.run(request);
+        }
+
+        for observer in &self.observers {
+            observer.on_start(&request);
+        }
+        let (method, url, sent) = (request.method, request.absolute_url(), request.body_size());
+        let start = Instant::now();
+        let result = next.run(request);
+        let metrics = Metrics {
+            method,
+            url,
+            status: result.as_ref().ok().map(|response| response.status),
+            error: result.as_ref().err().map(Error::kind),
+            elapsed: start.elapsed(),
+            sent,
+            received: (result.as_ref()).map_or(0, |response| response.body.len() as u64),
+            timings: (result.as_ref()).map_or(Timings::default(), |response| response.timings),
+        };
+        for observer in &self.observers {
+            observer.on_finish(&metrics);
+        }
+        result
+    }
+
+    /// Apply the options of the client to a request.
+    pub(crate) fn prepare<'a>(&'a self, request: Request<'a>) -> Result<Request<'a>, Error> {
+        let request = match self.absolute(&request.url)? {
+            Cow::Owned(url) => request.url(url),
+            Cow::Borrowed(_) => request,
+        };
+        let request = match &self.user_agent {
+            Some(agent) => request.user_agent(agent.as_deref()),
+            None => request,
+        };
+        let request = match self.timeout {
+            Some(timeout) if request.timeouts.total.is_none() => request.timeout(timeout),
+            _ => request,
+        };
+        let request = match &self.proxy {
+            Some(proxy) if request.proxy.is_none() => request.proxy(proxy),
+            _ => request,
+        };
+        let request = (self.default_headers.iter()).fold(request, |request, (name, value)| {
+            if request.headers.contains(name) {
+                request
+            } else {
+                request.header(name, value)
+            }
+        });
+        let request = match &self.tcp {
+            Some(options) => request.tcp(options.clone()),
+            None => request,
+        };
+        let request = match &self.pool {
+            Some(pool) => request.pool(pool),
+            None => request,
+        };
+        let request = match &self.cookie_jar {
+            Some(jar) => request.cookie_jar(jar),
+            None => request,
+        };
+        let request = match &self.resolver {
+            Some(resolver) => request.resolver(&**resolver),
+            None => request,
+        };
+        let request = match &self.on_request {
+            Some(hook) => request.on_request(&**hook),
+            None => request,
+        };
+        let request = match &self.on_response {
+            Some(hook) => request.on_response(&**hook),
+            None => request,
+        };
+        let request = match &self.on_redirect {
+            Some(hook) => request.on_redirect(&**hook),
+            None => request,
+        };
+        Ok(
+            (self.overrides.iter()).fold(request, |request, (host, address)| {
+                request.resolve_override(host, *address)
+            }),
+        )
+    }
+
+    /// An URL resolved against the base URL of the client, unless it has a scheme.
+    fn absolute<'u>(&self, url: &'u str) -> Result<Cow<'u, str>, Error> {
+        match &self.base_url {
+            Some(base) if uri::scheme(url).is_none() => Ok(Cow::Owned(base.join(url)?.to_string())),
+            _ => Ok(Cow::Borrowed(url)),
+        }
+    }
+
+    /// GET the resource at an URL.
+    ///
+    /// # Errors
+    ///
+    /// May error if the provided URL is invalid, or if network issues arise.
+    pub fn get(&self, url: &str) -> Result<Response, Error> {
+        self.send(Request::get(url))
+    }
+
+    /// POST a body to the URL.
+    ///
+    /// # Errors
+    ///
+    /// May error if the provided URL is invalid, or if network issues arise.
+    pub fn post(&self, url: &str, body: &(impl AsRef<[u8]> + ?Sized)) -> Result<Response, Error> {
+        self.send(Request::post(url, body))
+    }
+}
+
+/// A hook which handles requests sent by a [`Client`], e.g. for logging, authentication, caching or metrics.
+///
+/// A middleware can modify the request before passing it on with [`Next::run`], inspect or modify the response,
+/// or respond without sending the request at all.
+/// Any closure taking a [`Request`] and a [`Next`] can be used as a middleware.
+pub trait Middleware: Send + Sync {
+    /// Handle a request, usually by passing it on to the next middleware.
+    ///
+    /// # Errors
+    ///
+    /// May error if the request fails, or if the middleware rejects it.
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error>;
+}
+impl<F> Middleware for F
+where
+    F: Fn(Request, Next) -> Result<Response, Error> + Send + Sync,
+{
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error> {
+        self(request, next)
+    }
+}
+impl fmt::Debug for dyn Middleware + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Middleware")
+    }
+}
+
+/// The rest of the middleware stack, which eventually sends the request.
+#[derive(Debug, Clone, Copy)]
+pub struct Next<'c> {
+    middleware: &'c [Arc<dyn Middleware>],
+}
+impl Next<'_> {
+    /// Pass the request on to the next middleware, or send it if there is none left.
+    ///
+    /// # Errors
+    ///
+    /// May error if a middleware fails, or for the same reasons as [`Request::send`].
+    pub fn run(self, request: Request) -> Result<Response, Error> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next { middleware: rest }),
+            None => request.send(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        set_default_client, Client, ErrorKind, Method, Metrics, Mock, MockTransport, Next, Request,
+        Response,
+    };
+    use std::{
+        io,
+        net::IpAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[test]
+    fn middleware_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let (outer, inner) = (log.clone(), log.clone());
+        let client = Client::new()
+            .with(move |request: Request, next: Next| {
+                outer.lock().unwrap().push("outer");
+                next.run(request.header("X-Outer", "1"))
+            })
+            .with(move |request: Request, _: Next| {
+                inner.lock().unwrap().push("inner");
+                // respond without touching the network
+                let status = if request.to_string().contains("X-Outer: 1") {
+                    200
+                } else {
+                    400
+                };
+                Response::parse(format!("HTTP/1.1 {status} Cached\r\n\r\n"))
+                    .map_err(|e| std::io::Error::other(e).into())
+            });
+        let response = client.get("example.org").unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(*log.lock().unwrap(), ["outer", "inner"]);
+    }
+
+    #[test]
+    fn defaults() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let log = seen.clone();
+        let client = Client::new()
+            .timeout(Duration::from_secs(10))
+            .proxy("localhost:3128")
+            .default_header("Accept", "application/json")
+            .default_header("X-Api-Key", "secret")
+            .with(move |request: Request, _: Next| {
+                let proxy = request.proxy.map(str::to_string);
+                (log.lock().unwrap()).push((request.timeouts.total, proxy, request.to_string()));
+                Response::parse("HTTP/1.1 204 No Content\r\n\r\n")
+                    .map_err(|e| io::Error::other(e).into())
+            });
+        client.get("example.org").unwrap();
+        // the options of the request take precedence
+        let request = Request::get("example.org")
+            .timeout(Duration::from_secs(1))
+            .proxy("localhost:8080")
+            .header("accept", "text/html");
+        client.send(request).unwrap();
+
+        let seen = seen.lock().unwrap();
+        let (timeout, proxy, message) = &seen[0];
+        assert_eq!(*timeout, Some(Duration::from_secs(10)));
+        assert_eq!(proxy.as_deref(), Some("localhost:3128"));
+        assert!(message.contains("Accept: application/json\r\nX-Api-Key: secret\r\n"));
+        let (timeout, proxy, message) = &seen[1];
+        assert_eq!(*timeout, Some(Duration::from_secs(1)));
+        assert_eq!(proxy.as_deref(), Some("localhost:8080"));
+        assert!(message.contains("accept: text/html\r\nX-Api-Key: secret\r\n"));
+        assert!(!message.contains("application/json"));
+    }
+
+    #[test]
+    fn base_url() {
+        let urls = Arc::new(Mutex::new(vec![]));
+        let log = urls.clone();
+        let client = Client::new()
+            .base_url("https://api.example.org/v2/".parse().unwrap())
+            .with(move |request: Request, _: Next| {
+                log.lock().unwrap().push(request.url.to_string());
+                Response::parse("HTTP/1.1 204 No Content\r\n\r\n")
+                    .map_err(|e| io::Error::other(e).into())
+            });
+        for url in [
+            "items?page=2",
+            "/health",
+            "../v1/items",
+            "http://other.org/",
+        ] {
+            client.get(url).unwrap();
+        }
+        assert_eq!(
+            *urls.lock().unwrap(),
+            [
+                "https://api.example.org/v2/items?page=2",
+                "https://api.example.org/health",
+                "https://api.example.org/v1/items",
+                "http://other.org/",
+            ]
+        );
+    }
+
+    #[test]
+    fn observer() {
+        let finished = Arc::new(Mutex::new(vec![]));
+        let log = finished.clone();
+        let transport = MockTransport::new().mock(
+            Mock::new(Method::POST, "example.org/a").respond("HTTP/1.1 201 Created\r\n\r\ncreated"),
+        );
+        let client = Client::new()
+            .observe(move |metrics: &Metrics| log.lock().unwrap().push(metrics.clone()))
+            .with(transport);
+        client.post("example.org/a", "hello").unwrap();
+        client.get("example.org/b").unwrap_err();
+
+        let finished = finished.lock().unwrap();
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].method, Method::POST);
+        assert_eq!(finished[0].url, "http://example.org/a");
+        assert_eq!(finished[0].status, Some(201.try_into().unwrap()));
+        assert_eq!((finished[0].sent, finished[0].received), (5, 7));
+        assert_eq!(finished[1].status, None);
+        assert_eq!(finished[1].error, Some(ErrorKind::Io));
+    }
+
+    #[test]
+    fn prefetch() {
+        let lookups = Arc::new(Mutex::new(vec![]));
+        let log = lookups.clone();
+        let client = Client::new()
+            .resolver(move |host: &str| {
+                log.lock().unwrap().push(host.to_string());
+                Ok(vec![IpAddr::from([192, 0, 2, 1])])
+            })
+            .resolve_override("pinned.example.org", IpAddr::from([192, 0, 2, 2]));
+        let addresses = |url| client.prefetch(url).join().unwrap().unwrap();
+        assert_eq!(
+            addresses("https://api.example.org:8443/items"),
+            [IpAddr::from([192, 0, 2, 1])]
+        );
+        assert_eq!(
+            addresses("pinned.example.org"),
+            [IpAddr::from([192, 0, 2, 2])]
+        );
+        assert_eq!(
+            addresses("http://[::1]/"),
+            ["::1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(*lookups.lock().unwrap(), ["api.example.org"]);
+        let error = Client::new()
+            .resolver(|_: &str| Err(io::Error::new(io::ErrorKind::NotFound, "unknown host")))
+            .prefetch("unknown.example.org")
+            .join()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn default_client() {
+        let transport = MockTransport::new()
+            .mock(Mock::new(Method::GET, "example.org/a"))
+            .mock(Mock::new(Method::POST, "example.org/b").respond("HTTP/1.1 201 Created\r\n\r\n"));
+        set_default_client(Client::new().with(transport.clone()));
+        let get = crate::get("example.org/a");
+        let post = crate::post("example.org/b", "");
+        set_default_client(Client::new());
+        assert_eq!(get.unwrap().status, 200);
+        assert_eq!(post.unwrap().status, 201);
+        assert_eq!(transport.requests().len(), 2);
+    }
+}
@@ -0,0 +1,585 @@
+use crate::{date::DateTime, uri, Response};
+use std::{
+    fmt::{self, Write},
+    fs, io,
+    net::IpAddr,
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+/// A cookie which was set by a server with the `Set-Cookie` header.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let cookie = Cookie::parse("session=abc; Path=/app; Domain=Example.org").unwrap();
+/// assert_eq!((cookie.name(), cookie.value()), ("session", "abc"));
+/// assert_eq!(cookie.domain(), Some("example.org"));
+/// assert_eq!(cookie.path(), Some("/app"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// Name of the cookie.
+    name: String,
+    /// Value of the cookie.
+    value: String,
+    /// Domain the cookie is sent to, without a leading dot.
+    domain: Option<String>,
+    /// Path prefix of the URLs the cookie is sent to.
+    path: Option<String>,
+    /// When the cookie expires, or `None` if it lasts for the session.
+    expires: Option<SystemTime>,
+    /// Whether the cookie is only sent to the host which set it, rather than to its subdomains as well.
+    host_only: bool,
+    /// Whether the cookie is only sent over HTTPS.
+    secure: bool,
+    /// Whether the cookie is hidden from scripts in browsers.
+    http_only: bool,
+    /// Whether browsers send the cookie with requests from other sites.
+    same_site: Option<SameSite>,
+}
+impl Cookie {
+    /// Parse the value of a `Set-Cookie` header, ignoring attributes which are unknown or malformed.
+    ///
+    /// Returns `None` if the cookie has no name.
+    #[must_use]
+    pub fn parse(set_cookie: &str) -> Option<Self> {
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+            host_only: true,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+        let mut max_age = None;
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    cookie.domain = Some(domain).filter(|d| !d.is_empty());
+                }
+                "path" if value.starts_with('/') => cookie.path = Some(value.to_string()),
+                "expires" => {
+                    // an invalid date is ignored, rather than treated as expired
+                    if let Some(date) = DateTime::parse(value) {
+                        cookie.expires = Some(SystemTime::from(date));
+                    }
+                }
+                "max-age" => max_age = value.parse::<i64>().ok().or(max_age),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => cookie.same_site,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // max-age takes precedence over expires, and a cookie without a positive age has already expired
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(match u64::try_from(seconds) {
+                Ok(seconds) if seconds > 0 => SystemTime::now() + Duration::from_secs(seconds),
+                _ => SystemTime::UNIX_EPOCH,
+            });
+        }
+        Some(cookie)
+    }
+
+    /// Name of the cookie.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Value of the cookie.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Domain the cookie is sent to, along with its subdomains unless the cookie is only sent to the host which set it.
+    #[must_use]
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Path prefix of the URLs the cookie is sent to.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// When the cookie expires, or `None` if it lasts for the session.
+    #[must_use]
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Whether the cookie is only sent over HTTPS (`Secure`).
+    #[must_use]
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Whether the cookie is hidden from scripts in browsers (`HttpOnly`).
+    ///
+    /// This does not affect which requests the cookie is sent with.
+    #[must_use]
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Whether browsers send the cookie with requests from other sites (`SameSite`).
+    ///
+    /// This does not affect which requests the cookie is sent with, as the requests are not made from a site.
+    #[must_use]
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    /// Whether the cookie has expired.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+
+    /// Whether the cookie should be sent with a request to a host and path, over HTTPS or not.
+    fn matches(&self, host: &str, path: &str, https: bool) -> bool {
+        if self.secure && !https {
+            return false;
+        }
+        let domain = self.domain.as_deref().unwrap_or_default();
+        let domain_matches = host == domain
+            || (!self.host_only
+                && host.ends_with(domain)
+                && host[..host.len() - domain.len()].ends_with('.'));
+        domain_matches && path_matches(path, self.path.as_deref().unwrap_or("/"))
+    }
+}
+impl fmt::Display for Cookie {
+    /// Format the cookie as it is sent in the `Cookie` header, i.e. `name=value`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+/// When browsers send a cookie with requests which come from other sites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only with requests from the same site.
+    Strict,
+    /// Also when navigating to the site from other sites.
+    Lax,
+    /// With all requests, if the cookie is also `Secure`.
+    None,
+}
+
+/// Whether a request path is within the path of a cookie (RFC 6265 section 5.1.4).
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/'))
+}
+
+/// The path of a URL without the query or fragment.
+fn url_path(url: &str) -> &str {
+    uri::path(url).unwrap_or("/")
+}
+
+/// Public suffixes with more than one label, under which anyone can register a domain.
+///
+/// This is a short list of common suffixes, rather than the whole public suffix list.
+const PUBLIC_SUFFIXES: [&str; 24] = [
+    "ac.uk",
+    "co.in",
+    "co.jp",
+    "co.kr",
+    "co.nz",
+    "co.uk",
+    "co.za",
+    "com.au",
+    "com.br",
+    "com.cn",
+    "com.mx",
+    "com.tr",
+    "gov.uk",
+    "me.uk",
+    "net.au",
+    "org.au",
+    "org.uk",
+    "appspot.com",
+    "blogspot.com",
+    "github.io",
+    "gitlab.io",
+    "herokuapp.com",
+    "netlify.app",
+    "pages.dev",
+];
+
+/// Whether a domain is a public suffix, which is a single label such as `com`, or one of the known longer suffixes.
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || PUBLIC_SUFFIXES.contains(&domain)
+}
+
+/// A store of cookies, which are sent along with requests to the servers which set them.
+///
+/// Cookies are only sent to the domain and path they were set for, and `Secure` cookies only over HTTPS.
+/// Expired cookies are removed, and the cookies can be kept in a file with [`CookieJar::save`] and [`CookieJar::load`].
+/// The jar can be shared between threads and clients, see [`Client::cookie_jar`](crate::Client::cookie_jar),
+/// or used for a single request with [`Request::cookie_jar`](crate::Request::cookie_jar).
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let jar = CookieJar::new();
+/// jar.set("https://example.org/login", "session=abc; Path=/");
+/// assert_eq!(jar.header("https://example.org/account").as_deref(), Some("session=abc"));
+/// assert_eq!(jar.header("https://example.com/"), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    /// Stored cookies, with their domain and path resolved.
+    cookies: Mutex<Vec<Cookie>>,
+}
+impl CookieJar {
+    /// Create an empty cookie jar.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a cookie from a `Set-Cookie` header, which was received in response to a request to the URL.
+    ///
+    /// The cookie is ignored if it is malformed, or if its domain does not match the host of the URL.
+    /// Cookies can not be set for a public suffix, such as `com` or `co.uk`, which would send them to every domain under it,
+    /// nor for the parent domains of an IP address. A cookie whose domain is the host itself is only sent to that host
+    /// if the host is a public suffix or an IP address.
+    /// A cookie with the same name, domain and path is replaced, or removed if the new cookie has expired.
+    pub fn set(&self, url: &str, set_cookie: &str) {
+        let Some(mut cookie) = Cookie::parse(set_cookie) else {
+            return;
+        };
+        let Some(host) = uri::host(url).map(str::to_ascii_lowercase) else {
+            return;
+        };
+        // only secure origins can set secure cookies
+        if cookie.secure && uri::scheme(url) != Some("https") {
+            return;
+        }
+
+        // a cookie can only be set for the host itself or one of its parent domains which is not a public suffix
+        let ip = (host.trim_start_matches('[').trim_end_matches(']'))
+            .parse::<IpAddr>()
+            .is_ok();
+        match &cookie.domain {
+            Some(domain) if host == *domain => cookie.host_only = ip || is_public_suffix(domain),
+            Some(domain)
+                if !ip && !is_public_suffix(domain) && host.ends_with(&format!(".{domain}")) =>
+            {
+                cookie.host_only = false;
+            }
+            Some(_) => return,
+            None => cookie.domain = Some(host),
+        }
+        if cookie.path.is_none() {
+            // the default path is the directory of the request path
+            let path = url_path(url);
+            let directory = path
+                .rfind('/')
+                .filter(|&i| i > 0)
+                .map_or("/", |i| &path[..i]);
+            cookie.path = Some(directory.to_string());
+        }
+
+        let mut cookies = self.cookies.lock().unwrap_or_else(PoisonError::into_inner);
+        cookies.retain(|c| {
+            (&c.name, &c.domain, &c.path) != (&cookie.name, &cookie.domain, &cookie.path)
+        });
+        if !cookie.is_expired() {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Store the cookies set by a response to a request to the URL.
+    pub fn store(&self, url: &str, response: &Response) {
+        for set_cookie in response.headers.get_all("Set-Cookie") {
+            self.set(url, set_cookie);
+        }
+    }
+
+    /// The value of the `Cookie` header for a request to the URL, or `None` if no cookies should be sent.
+    ///
+    /// Cookies with longer paths are listed first.
+    #[must_use]
+    pub fn header(&self, url: &str) -> Option<String> {
+        let host = uri::host(url)?.to_ascii_lowercase();
+        let path = url_path(url);
+        let https = uri::scheme(url) == Some("https");
+        let mut cookies = self.cookies.lock().unwrap_or_else(PoisonError::into_inner);
+        cookies.retain(|c| !c.is_expired());
+        let mut matching = (cookies.iter())
+            .filter(|c| c.matches(&host, path, https))
+            .collect::<Vec<_>>();
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.as_ref().map_or(0, String::len)));
+        let header = (matching.iter())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        Some(header).filter(|h| !h.is_empty())
+    }
+
+    /// All cookies in the jar which have not expired.
+    #[must_use]
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let cookies = self.cookies.lock().unwrap_or_else(PoisonError::into_inner);
+        cookies
+            .iter()
+            .filter(|c| !c.is_expired())
+            .cloned()
+            .collect()
+    }
+
+    /// Load cookies from a file in the Netscape cookie file format, which is also used by curl and wget.
+    ///
+    /// Lines which are malformed are skipped, and so are cookies which have expired.
+    ///
+    /// # Errors
+    ///
+    /// May error if the file can not be read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// let jar = CookieJar::load("cookies.txt").unwrap();
+    /// // ... send requests with the jar ...
+    /// jar.save("cookies.txt").unwrap();
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let contents = fs::read_to_string(path)?;
+        let mut cookies = Vec::new();
+        for line in contents.lines() {
+            // http-only cookies are marked with a prefix, which would otherwise make the line a comment
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (line, true),
+                None if line.starts_with('#') => continue,
+                None => (line, false),
+            };
+            let fields = line.trim_end_matches('\r').split('\t').collect::<Vec<_>>();
+            let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+                continue;
+            };
+            let Ok(expires) = expires.parse::<u64>() else {
+                continue;
+            };
+            let cookie = Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: Some(domain.trim_start_matches('.').to_ascii_lowercase()),
+                path: Some(path.to_string()),
+                // session cookies are stored with an expiry of zero
+                expires: (expires > 0)
+                    .then(|| SystemTime::UNIX_EPOCH + Duration::from_secs(expires)),
+                host_only: subdomains != "TRUE",
+                secure: secure == "TRUE",
+                http_only,
+                same_site: None,
+            };
+            if !cookie.name.is_empty() && !cookie.is_expired() {
+                cookies.push(cookie);
+            }
+        }
+        Ok(Self {
+            cookies: Mutex::new(cookies),
+        })
+    }
+
+    /// Save the cookies to a file in the Netscape cookie file format, so they can be loaded again with [`CookieJar::load`].
+    ///
+    /// Session cookies are saved as well, so that sessions survive restarts of the program.
+    ///
+    /// # Errors
+    ///
+    /// May error if the file can not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let mut contents = String::from("# Netscape HTTP Cookie File\n");
+        for cookie in self.cookies() {
+            let flag = |set: bool| if set { "TRUE" } else { "FALSE" };
+            let expires = (cookie.expires)
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            let prefix = if cookie.http_only { "#HttpOnly_" } else { "" };
+            let dot = if cookie.host_only { "" } else { "." };
+            let _ = writeln!(
+                contents,
+                "{prefix}{dot}{}\t{}\t{}\t{}\t{expires}\t{}\t{}",
+                cookie.domain.as_deref().unwrap_or_default(),
+                flag(!cookie.host_only),
+                cookie.path.as_deref().unwrap_or("/"),
+                flag(cookie.secure),
+                cookie.name,
+                cookie.value,
+            );
+        }
+        fs::write(path, contents)
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.cookies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cookie, CookieJar, SameSite};
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn parse() {
+        let cookie = Cookie::parse(
+            "id=\"a3fWa\"; Expires=Wed, 21 Oct 2015 07:28:01 GMT; domain=.Example.org; Path=/docs; HttpOnly",
+        )
+        .unwrap();
+        assert_eq!(cookie.to_string(), "id=a3fWa");
+        assert_eq!(cookie.domain(), Some("example.org"));
+        assert_eq!(cookie.path(), Some("/docs"));
+        let expires = SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_481);
+        assert_eq!(cookie.expires(), Some(expires));
+        assert!(cookie.is_expired());
+
+        let cookie = Cookie::parse(
+            "a=b; Max-Age=60; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Secure; SameSite=Lax",
+        )
+        .unwrap();
+        assert!(!cookie.is_expired() && cookie.is_secure() && !cookie.is_http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert!(Cookie::parse("a=b; Max-Age=0").unwrap().is_expired());
+
+        assert_eq!(Cookie::parse("=value"), None);
+        assert_eq!(Cookie::parse("novalue"), None);
+        assert_eq!(Cookie::parse("a=b; Path=relative").unwrap().path(), None);
+    }
+
+    #[test]
+    fn jar() {
+        let jar = CookieJar::new();
+        jar.set("http://www.example.org/docs/page", "local=1");
+        jar.set("http://www.example.org/", "shared=2; Domain=example.org");
+        jar.set("http://www.example.org/", "deep=3; Path=/docs/api");
+        jar.set("http://www.example.org/", "foreign=4; Domain=example.com");
+        jar.set(
+            "http://www.example.org/",
+            "gone=5; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        assert_eq!(jar.cookies().len(), 3);
+
+        let header = |url| jar.header(url).unwrap_or_default();
+        assert_eq!(header("http://www.example.org/"), "shared=2");
+        assert_eq!(
+            header("http://www.example.org/docs/api/v1?q=1"),
+            "deep=3; local=1; shared=2"
+        );
+        assert_eq!(header("http://www.example.org/docsearch"), "shared=2");
+        assert_eq!(header("http://api.example.org/docs/"), "shared=2");
+        assert_eq!(header("http://example.org/docs/"), "shared=2");
+        assert_eq!(header("http://notexample.org/"), "");
+
+        // cookies are replaced, and removed by setting them to expire
+        jar.set("http://www.example.org/docs/", "local=6");
+        assert_eq!(header("http://www.example.org/docs/a"), "local=6; shared=2");
+        jar.set(
+            "http://www.example.org/",
+            "shared=; Domain=example.org; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        assert_eq!(header("http://www.example.org/docs/a"), "local=6");
+        jar.clear();
+        assert_eq!(jar.header("http://www.example.org/"), None);
+
+        // secure cookies are only set and sent over https
+        jar.set("http://example.org/", "a=1; Secure");
+        jar.set("https://example.org/", "b=2; Secure");
+        assert_eq!(jar.header("http://example.org/"), None);
+        assert_eq!(jar.header("https://example.org/").as_deref(), Some("b=2"));
+    }
+
+    #[test]
+    fn public_suffixes() {
+        let jar = CookieJar::new();
+        jar.set("http://www.example.org/", "tld=1; Domain=org");
+        jar.set("http://www.example.co.uk/", "suffix=2; Domain=co.uk");
+        jar.set("http://example.co.uk/", "parent=3; Domain=example.co.uk");
+        assert_eq!(jar.header("http://other.org/"), None);
+        assert_eq!(jar.header("http://other.co.uk/"), None);
+        assert_eq!(
+            jar.header("http://www.example.co.uk/").as_deref(),
+            Some("parent=3")
+        );
+
+        // a single label host can only set cookies for itself
+        jar.set("http://localhost/", "local=4; Domain=localhost");
+        assert_eq!(jar.header("http://localhost/").as_deref(), Some("local=4"));
+        assert_eq!(jar.header("http://www.localhost/"), None);
+
+        // an IP address has no parent domains
+        jar.set("http://127.0.0.1/", "ip=5; Domain=127.0.0.1");
+        jar.set("http://127.0.0.1/", "parent=6; Domain=0.1");
+        jar.set("http://[::1]/", "ipv6=7; Domain=[::1]");
+        assert_eq!(jar.header("http://127.0.0.1/").as_deref(), Some("ip=5"));
+        assert_eq!(jar.header("http://[::1]/").as_deref(), Some("ipv6=7"));
+        assert_eq!(jar.cookies().len(), 4);
+    }
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("request-cookies-{}", std::process::id()));
+        let jar = CookieJar::new();
+        jar.set("https://www.example.org/", "session=abc; Secure; HttpOnly");
+        jar.set(
+            "https://www.example.org/",
+            "theme=dark; Domain=example.org; Path=/app; Expires=Fri, 01 Jan 2100 00:00:00 GMT",
+        );
+        jar.save(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Netscape HTTP Cookie File\n\
+             #HttpOnly_www.example.org\tFALSE\t/\tTRUE\t0\tsession\tabc\n\
+             .example.org\tTRUE\t/app\tFALSE\t4102444800\ttheme\tdark\n"
+        );
+
+        let loaded = CookieJar::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.cookies(), jar.cookies());
+        assert_eq!(
+            loaded.header("https://www.example.org/app").as_deref(),
+            Some("theme=dark; session=abc")
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A point in time broken down into UTC calendar fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DateTime {
+    pub(crate) year: i64,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    pub(crate) hour: u32,
+    pub(crate) minute: u32,
+    pub(crate) second: u32,
+}
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let (days, rem) = (secs / 86400, secs % 86400);
+
+        // convert days since epoch to a civil date (proleptic gregorian calendar)
+        let z = i64::try_from(days).unwrap_or(0) + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        DateTime {
+            year,
+            month: month as u32,
+            day: day as u32,
+            hour: (rem / 3600) as u32,
+            minute: (rem / 60 % 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+}
+impl From<DateTime> for SystemTime {
+    fn from(t: DateTime) -> Self {
+        // convert a civil date to days since epoch (inverse of the above)
+        let month = i64::from(t.month);
+        let year = t.year - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let doy =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(t.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let secs = days * 86400
+            + i64::from(t.hour) * 3600
+            + i64::from(t.minute) * 60
+            + i64::from(t.second);
+        UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).unwrap_or(0))
+    }
+}
+impl DateTime {
+    /// Parse an HTTP-date (RFC 9110), in either the preferred IMF-fixdate format or one of the obsolete formats.
+    ///
+    /// `Sun, 06 Nov 1994 08:49:37 GMT`, `Sunday, 06-Nov-94 08:49:37 GMT` or `Sun Nov  6 08:49:37 1994`.
+    pub(crate) fn parse(date: &str) -> Option<Self> {
+        let parts = date
+            .split([' ', ',', '-'])
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+        // imf-fixdate and rfc 850: weekday, day, month, year, time, GMT
+        // asctime: weekday, month, day, time, year
+        let ([_, day, month, year, time, "GMT"] | [_, month, day, time, year]) = parts[..] else {
+            return None;
+        };
+
+        let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? + 1;
+        let year = match year.parse::<i64>().ok()? {
+            // two-digit years: interpret as the most recent matching year (approximately)
+            y @ 0..70 => y + 2000,
+            y @ 70..100 => y + 1900,
+            y => y,
+        };
+        let mut time = time.split(':').map(str::parse::<u32>);
+        let (Some(Ok(hour)), Some(Ok(minute)), Some(Ok(second)), None) =
+            (time.next(), time.next(), time.next(), time.next())
+        else {
+            return None;
+        };
+
+        let t = DateTime {
+            year,
+            month: u32::try_from(month).ok()?,
+            day: day.parse().ok()?,
+            hour,
+            minute,
+            second,
+        };
+        ((1..=31).contains(&t.day) && t.hour < 24 && t.minute < 60 && t.second <= 60).then_some(t)
+    }
+}
+impl fmt::Display for DateTime {
+    /// Format the date as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = SystemTime::from(*self)
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() / 86400);
+        let weekday = WEEKDAYS[usize::try_from((days + 4) % 7).unwrap_or(0)]; // 1970-01-01 was a thursday
+        let month = MONTHS[(self.month as usize).clamp(1, 12) - 1];
+        write!(
+            f,
+            "{weekday}, {:02} {month} {:04} {:02}:{:02}:{:02} GMT",
+            self.day, self.year, self.hour, self.minute, self.second
+        )
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_dates() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        for date in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            let parsed = DateTime::parse(date).unwrap();
+            assert_eq!(SystemTime::from(parsed), expected);
+            assert_eq!(parsed.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        }
+        assert_eq!(DateTime::parse("yesterday"), None);
+    }
+}
@@ -1,23 +1,598 @@
+//! Resolving host names to addresses, with a dependency-free DNS client.
+//!
+//! The built-in resolver reads the system configuration, such as the hosts file and the nameservers of resolv.conf,
+//! and caches answers for as long as their time to live allows. It can be used on its own with [`resolve_all`].
+
+use crate::Request;
 use std::{
-    io, iter,
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
-    sync::LazyLock,
+    fmt, fs,
+    io::{self, prelude::*},
+    iter,
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
 };
 
-/// Resolve DNS request using system nameservers.
-pub(crate) fn resolve(query: &str) -> Result<IpAddr, io::Error> {
-    // todo: local overrides
-    if query.starts_with("localhost") {
-        return Ok(IpAddr::V4(Ipv4Addr::LOCALHOST));
+/// A resolver which finds the addresses of hosts, such as a DNS client or a fixed map of hosts.
+///
+/// Any closure taking a host name and returning its addresses can be used as a resolver.
+/// IP addresses are never passed to the resolver, as they are connected to directly.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::net::IpAddr;
+/// // send requests for every host to a local server
+/// let resolver = |_: &str| Ok(vec![IpAddr::from([127, 0, 0, 1])]);
+/// let request = Request::get("example.org").resolver(&resolver);
+/// ```
+pub trait Resolver: Send + Sync {
+    /// Find the addresses of a host, which are tried in order when connecting.
+    ///
+    /// # Errors
+    ///
+    /// May error if the host does not exist, or if the addresses could not be looked up.
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error>;
+}
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> Result<Vec<IpAddr>, io::Error> + Send + Sync,
+{
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error> {
+        self(host)
+    }
+}
+impl fmt::Debug for dyn Resolver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+/// The built-in resolver, which asks the system nameservers and caches their answers, and is used by default.
+///
+/// Names in the hosts file are not looked up, and answers are cached for as long as their time to live allows.
+/// Bare names, such as `backend`, are completed with the search domains of resolv.conf,
+/// and `.local` names, such as `printer.local`, are resolved with multicast DNS.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::net::SocketAddr;
+/// // ask a nameserver of the internal network, instead of the ones of the system
+/// let resolver = DnsResolver::new().servers(&[SocketAddr::from(([10, 0, 0, 2], 53))]);
+/// let client = Client::new().resolver(resolver);
+/// ```
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct DnsResolver {
+    /// The nameservers which are asked instead of the system nameservers, if any.
+    servers: Option<Vec<SocketAddr>>,
+    /// Answers of these nameservers, which are not shared with the answers of the system nameservers.
+    cache: Arc<Mutex<Vec<CacheEntry>>>,
+}
+impl DnsResolver {
+    /// Create a resolver which asks the system nameservers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask these nameservers, in order, instead of the system nameservers.
+    pub fn servers(self, servers: &[SocketAddr]) -> Self {
+        let mut resolver = self;
+        resolver.servers = Some(servers.to_vec());
+        resolver
+    }
+}
+impl Resolver for DnsResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error> {
+        match &self.servers {
+            Some(servers) => resolve_with(host, Some(servers), &self.cache, None),
+            None => resolve(host, None),
+        }
+    }
+}
+
+/// A resolver which uses the resolver of the operating system (`getaddrinfo`), through [`ToSocketAddrs`].
+///
+/// This supports everything the system is configured for, such as mDNS, at the cost of blocking without a timeout.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let request = Request::get("printer.local").resolver(&SystemResolver);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error> {
+        let mut addresses = Vec::new();
+        for address in (host, 0).to_socket_addrs()? {
+            if !addresses.contains(&address.ip()) {
+                addresses.push(address.ip());
+            }
+        }
+        Ok(addresses)
+    }
+}
+
+/// A resolver which sends DNS queries over HTTPS (RFC 8484), e.g. to avoid networks which block or observe port 53.
+///
+/// Answers are cached by each resolver and its clones, for as long as their time to live allows.
+/// Servers with an `https` URL require the `tls` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let client = Client::new().resolver(DohResolver::new("https://1.1.1.1/dns-query"));
+/// ```
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    /// URL of the server, to which queries are posted.
+    url: String,
+    /// How long to wait for each answer.
+    timeout: Duration,
+    /// Answers which are still valid.
+    cache: Arc<Mutex<Vec<CacheEntry>>>,
+}
+impl DohResolver {
+    /// Create a resolver which sends queries to a DNS-over-HTTPS server, such as `https://dns.google/dns-query`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            timeout: QUERY_TIMEOUT,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Set how long to wait for each answer, which is 5 seconds by default.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let mut resolver = self;
+        resolver.timeout = timeout;
+        resolver
+    }
+}
+impl Resolver for DohResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error> {
+        if let Some(answer) = cached(&self.cache, host) {
+            return existing(host, answer);
+        }
+
+        // the id is always 0, so responses can be cached by http caches
+        let mut answers: Answers = [None, None];
+        for (i, message) in queries(host, [0, 0]).iter().enumerate() {
+            let response = Request::post(&self.url, message)
+                .header("Content-Type", "application/dns-message")
+                .header("Accept", "application/dns-message")
+                .timeout(self.timeout)
+                .send()?;
+            if !response.status.is_success() {
+                return Err(io::Error::other(format!(
+                    "dns-over-https server responded with {}",
+                    response.status
+                )));
+            }
+            answers[i] = Some(checked(&response.body, message, host)?);
+        }
+        let answer = found(answers);
+        remember(&self.cache, host, &answer);
+        existing(host, answer)
+    }
+}
+
+/// A resolver which sends DNS queries over TLS (RFC 7858) to a configurable server, e.g. `1.1.1.1:853`.
+///
+/// The server's certificate is verified for its name, which may also be its IP address.
+/// Answers are cached by each resolver and its clones, for as long as their time to live allows.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let resolver = DotResolver::new("1.1.1.1:853".parse().unwrap(), "cloudflare-dns.com");
+/// let client = Client::new().resolver(resolver);
+/// ```
+#[cfg(feature = "tls")]
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct DotResolver {
+    /// Address of the server.
+    server: SocketAddr,
+    /// Name of the server, which its certificate is verified for.
+    name: String,
+    /// How long to wait for the answers.
+    timeout: Duration,
+    /// Answers which are still valid.
+    cache: Arc<Mutex<Vec<CacheEntry>>>,
+}
+#[cfg(feature = "tls")]
+impl DotResolver {
+    /// Create a resolver which sends queries to a DNS-over-TLS server, verifying that its certificate is valid for the name.
+    pub fn new(server: SocketAddr, name: &str) -> Self {
+        Self {
+            server,
+            name: name.to_string(),
+            timeout: QUERY_TIMEOUT,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Set how long to wait for the connection and each answer, which is 5 seconds by default.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let mut resolver = self;
+        resolver.timeout = timeout;
+        resolver
+    }
+}
+#[cfg(feature = "tls")]
+impl Resolver for DotResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, io::Error> {
+        if let Some(answer) = cached(&self.cache, host) {
+            return existing(host, answer);
+        }
+
+        // both queries are sent over the same connection, one after the other
+        let stream = connect(self.server, Some(self.timeout))?;
+        let mut stream = crate::tls::wrap(stream, &self.name)?;
+        let mut answers: Answers = [None, None];
+        for (i, message) in queries(host, ids()).iter().enumerate() {
+            let response = query_stream(&mut stream, message)?;
+            answers[i] = Some(checked(&response, message, host)?);
+        }
+        let answer = found(answers);
+        remember(&self.cache, host, &answer);
+        existing(host, answer)
+    }
+}
+
+/// Parse the addresses from a response to a query, erroring if the server failed to process it.
+fn checked(response: &[u8], query: &[u8], host: &str) -> Result<Answer, io::Error> {
+    if !same_question(query, response) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "dns response does not match the query",
+        ));
+    }
+    match rcode(response) {
+        Some(NOERROR | NXDOMAIN) => Ok(answer(response, host).unwrap_or_default()),
+        Some(code) => Err(failed(code, host)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid dns response",
+        )),
+    }
+}
+
+/// Parse a host which is an IP address, including an IPv6 address in brackets.
+pub(crate) fn literal(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Resolve DNS request using system nameservers, returning the IPv4 and IPv6 addresses of the host.
+///
+/// IP addresses, including IPv6 addresses in brackets, are returned as-is, and names in the hosts file are not looked up.
+/// Names are completed with the search domains of resolv.conf, depending on its `ndots` option.
+/// Names in the `.local` domain are resolved with multicast DNS, by asking the devices on the local network.
+/// Answers are cached for as long as their time to live allows, including answers that a host has no addresses,
+/// see [`flush_dns_cache`].
+/// Fails with [`io::ErrorKind::TimedOut`] if no nameserver answers within the timeout.
+pub(crate) fn resolve(query: &str, timeout: Option<Duration>) -> Result<Vec<IpAddr>, io::Error> {
+    resolve_with(query, None, &CACHE, timeout)
+}
+
+/// Resolve a host like [`resolve`], but asking the given nameservers instead of the system nameservers, if any.
+fn resolve_with(
+    query: &str,
+    servers: Option<&[SocketAddr]>,
+    cache: &Mutex<Vec<CacheEntry>>,
+    timeout: Option<Duration>,
+) -> Result<Vec<IpAddr>, io::Error> {
+    if let Some(address) = literal(query) {
+        return Ok(vec![address]);
+    }
+    // names pinned in the hosts file are not looked up
+    let addresses = hosts(&read_hosts(), query);
+    if !addresses.is_empty() {
+        return Ok(addresses);
+    }
+    if is_localhost(query) {
+        return Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+    }
+
+    // bare names are completed with the search domains, like other programs on the system do
+    let config = system();
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut last = Answer::default();
+    for name in candidates(query, &config.domains, config.ndots) {
+        let answer = if let Some(answer) = cached(cache, &name) {
+            answer
+        } else {
+            let left = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let answer = if is_local(&name) {
+                multicast(MDNS_GROUP, &name, left)?
+            } else {
+                let servers = servers.unwrap_or_else(|| config.nameservers(&name));
+                lookup(&name, servers, left)?
+            };
+            remember(cache, &name, &answer);
+            answer
+        };
+        if !answer.addresses.is_empty() {
+            return Ok(answer.addresses);
+        }
+        last = answer;
+    }
+    existing(query, last)
+}
+
+/// The names to look up for a name, in order, completing it with the search domains.
+///
+/// Names with at least `ndots` dots are tried as-is first, others after the search domains,
+/// and names ending with a dot are fully qualified, so they are only tried as-is, like `.local` names.
+fn candidates(name: &str, domains: &[String], ndots: usize) -> Vec<String> {
+    if name.ends_with('.') || is_local(name) {
+        return vec![name.trim_end_matches('.').to_string()];
+    }
+    let mut names = (domains.iter())
+        .map(|domain| format!("{name}.{domain}"))
+        .collect::<Vec<_>>();
+    let position = if name.matches('.').count() >= ndots {
+        0
+    } else {
+        names.len()
+    };
+    names.insert(position, name.to_string());
+    names
+}
+
+/// Parse the search domains and the `ndots` option from the contents of resolv.conf.
+///
+/// The last `search` or `domain` line is used, as they override each other,
+/// and `ndots` defaults to 1 and is capped at 15, like the system resolver does.
+fn search_list(resolv: &str) -> (Vec<String>, usize) {
+    let mut domains = Vec::new();
+    let mut ndots = 1;
+    for line in resolv.lines() {
+        let mut fields = line
+            .split(['#', ';'])
+            .next()
+            .unwrap_or_default()
+            .split_ascii_whitespace();
+        match fields.next() {
+            Some("search" | "domain") => {
+                domains = fields
+                    .map(|domain| domain.trim_end_matches('.').to_string())
+                    .filter(|domain| !domain.is_empty())
+                    .collect();
+            }
+            Some("options") => {
+                let value = fields
+                    .filter_map(|option| option.strip_prefix("ndots:"))
+                    .next_back();
+                ndots = value
+                    .and_then(|n| n.parse().ok())
+                    .map_or(ndots, |n: usize| n.min(15));
+            }
+            _ => {}
+        }
+    }
+    (domains, ndots)
+}
+
+/// Find the cached answer for a name, removing the answers which have expired.
+fn cached(cache: &Mutex<Vec<CacheEntry>>, name: &str) -> Option<Answer> {
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    let now = Instant::now();
+    cache.retain(|entry| entry.expires > now);
+    let entry = cache
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(name))?;
+    Some(entry.answer.clone())
+}
+
+/// Cache the answer for a name for as long as its time to live allows.
+fn remember(cache: &Mutex<Vec<CacheEntry>>, name: &str, answer: &Answer) {
+    if answer.ttl.is_zero() {
+        return;
     }
+    let name = name.to_ascii_lowercase();
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.retain(|entry| entry.name != name);
+    if cache.len() >= CACHE_ENTRIES {
+        // make room by removing the entry which would expire first
+        let first = (cache.iter().enumerate())
+            .min_by_key(|(_, entry)| entry.expires)
+            .map(|(i, _)| i);
+        cache.swap_remove(first.unwrap_or_default());
+    }
+    cache.push(CacheEntry {
+        name,
+        answer: answer.clone(),
+        expires: Instant::now() + answer.ttl,
+    });
+}
 
-    // todo: dns caching
-    // create dns query header: [id, flags, questions, answers, authority, additional]
-    let header: [u16; 6] = [0xabcd, 0x0100, 0x0001, 0x0000, 0x0000, 0x0000].map(|b: u16| b.to_be());
-    let question: [u16; 2] = [0x0001, 0x0001].map(|b: u16| b.to_be()); // [qtype, qclass] = [A, IN(ternet)]
+/// The hosts file, which maps names to addresses locally.
+static HOSTS: LazyLock<PathBuf> = LazyLock::new(|| {
+    #[cfg(windows)]
+    {
+        let root = std::env::var_os("SystemRoot").unwrap_or_else(|| r"C:\Windows".into());
+        PathBuf::from(root).join(r"System32\drivers\etc\hosts")
+    }
+    #[cfg(not(windows))]
+    PathBuf::from("/etc/hosts")
+});
 
+/// The contents of the hosts file, which is read again whenever it changes, and is empty if it can not be read.
+fn read_hosts() -> Arc<str> {
+    static CONTENTS: Mutex<Option<(Option<SystemTime>, Arc<str>)>> = Mutex::new(None);
+    let modified = fs::metadata(&*HOSTS).and_then(|m| m.modified()).ok();
+    let mut contents = CONTENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, current)) = contents.as_ref().filter(|(time, _)| *time == modified) {
+        return current.clone();
+    }
+    let current = Arc::<str>::from(fs::read_to_string(&*HOSTS).unwrap_or_default());
+    *contents = Some((modified, current.clone()));
+    current
+}
+
+/// Find the addresses of a name in the contents of a hosts file, where each line is an address followed by names.
+fn hosts(contents: &str, name: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| {
+            let mut fields = line.split_ascii_whitespace();
+            let address = fields.next()?.parse().ok()?;
+            fields
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+                .then_some(address)
+        })
+        .collect()
+}
+
+/// The type of DNS records to look up, which determines the family of the addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    /// `A` records, which contain IPv4 addresses.
+    A,
+    /// `AAAA` records, which contain IPv6 addresses.
+    Aaaa,
+}
+
+/// Resolve a host with the built-in resolver, returning its IPv4 addresses followed by its IPv6 addresses.
+///
+/// This is the resolver which is used for requests by default, see [`DnsResolver`].
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::NotFound`] if the host has no addresses,
+/// or with [`io::ErrorKind::TimedOut`] if no nameserver answers.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let addresses = request::dns::resolve_all("example.org").unwrap();
+/// ```
+pub fn resolve_all(host: &str) -> Result<Vec<IpAddr>, io::Error> {
+    resolve(host, None)
+}
+
+/// Resolve a host with the built-in resolver, returning only the addresses of one type of record.
+///
+/// Both types are still looked up, so the answers are cached together.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::NotFound`] if the host has no addresses of the type,
+/// or with [`io::ErrorKind::TimedOut`] if no nameserver answers.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use request::dns::{resolve_records, RecordType};
+/// let addresses = resolve_records("example.org", RecordType::Aaaa).unwrap();
+/// assert!(addresses.iter().all(|address| address.is_ipv6()));
+/// ```
+pub fn resolve_records(host: &str, kind: RecordType) -> Result<Vec<IpAddr>, io::Error> {
+    let addresses = (resolve(host, None)?.into_iter())
+        .filter(|address| address.is_ipv6() == (kind == RecordType::Aaaa))
+        .collect::<Vec<_>>();
+    if addresses.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no {kind:?} record found for {host}"),
+        ));
+    }
+    Ok(addresses)
+}
+
+/// Remove all cached DNS answers, so hosts are resolved again the next time they are requested.
+///
+/// # Examples
+///
+/// ```rust
+/// // ... the addresses of hosts have changed ...
+/// request::flush_dns_cache();
+/// ```
+pub fn flush_dns_cache() {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+/// A cached DNS answer.
+#[derive(Debug)]
+struct CacheEntry {
+    /// The name which was resolved, in lowercase.
+    name: String,
+    /// The answer, which may be negative.
+    answer: Answer,
+    /// When the answer may no longer be used.
+    expires: Instant,
+}
+
+/// DNS answers which are still valid, shared by the whole process.
+static CACHE: Mutex<Vec<CacheEntry>> = Mutex::new(Vec::new());
+
+/// Maximum number of cached DNS answers.
+const CACHE_ENTRIES: usize = 256;
+
+/// Ask the nameservers for the addresses of a host, returning them along with how long they may be cached.
+///
+/// Both A and AAAA records are queried, and IPv4 addresses are listed before IPv6 addresses.
+/// Each nameserver is asked in turn until one of them answers, and the whole list is tried a second time before giving up.
+fn lookup(
+    query: &str,
+    servers: &[SocketAddr],
+    timeout: Option<Duration>,
+) -> Result<Answer, io::Error> {
+    let messages = queries(query, ids());
+
+    // rotate through the nameservers, giving each of them a limited time to answer
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no nameservers are configured");
+    for server in (0..ATTEMPTS).flat_map(|_| servers) {
+        let left = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if left.is_some_and(|left| left.is_zero()) {
+            return Err(timed_out());
+        }
+        let timeout = left.map_or(QUERY_TIMEOUT, |left| left.min(QUERY_TIMEOUT));
+        match exchange(*server, &messages, query, timeout) {
+            Ok(answers) => return Ok(found(answers)),
+            Err(e) => {
+                debug!("nameserver {server} did not answer for {query}: {e}");
+                error = e;
+            }
+        }
+    }
+    Err(io::Error::new(
+        error.kind(),
+        format!("no nameserver answered for {query}: {error}"),
+    ))
+}
+
+/// Generate two different random ids for queries, so responses can not be forged without seeing the queries.
+fn ids() -> [u16; 2] {
+    let random = crate::random::u64();
+    let [a, b, ..] = random.to_le_bytes().map(u16::from);
+    let [c, d, ..] = (random >> 16).to_le_bytes().map(u16::from);
+    let first = a << 8 | b;
+    let second = c << 8 | d;
+    [first, if second == first { !first } else { second }]
+}
+
+/// Construct the messages which query the A and AAAA records of a name, with the given ids.
+fn queries(query: &str, ids: [u16; 2]) -> [Vec<u8>; 2] {
     // convert query to standard dns name notation (max 63 characters for each label)
-    let ascii = query.chars().filter(char::is_ascii).collect::<String>();
+    let ascii = crate::idna::to_ascii(query);
     let name = ascii
         .split('.')
         .flat_map(|l| {
@@ -26,51 +601,1118 @@ pub(crate) fn resolve(query: &str) -> Result<IpAddr, io::Error> {
         .chain(iter::once(0))
         .collect::<Vec<u8>>();
 
-    // construct the message
-    let mut message = bytemuck::cast::<[u16; 6], [u8; 12]>(header).to_vec();
-    message.extend(&name[..]);
-    message.extend(bytemuck::cast_slice(&question));
+    // construct a message for each record type: [qtype, qclass] = [A or AAAA, IN(ternet)]
+    let [a, aaaa] = ids;
+    [(a, A), (aaaa, AAAA)].map(|(id, kind): (u16, u16)| {
+        // create dns query header: [id, flags, questions, answers, authority, additional]
+        let header: [u16; 6] = [id, 0x0100, 0x0001, 0x0000, 0x0000, 0x0001].map(|b: u16| b.to_be());
+        let question: [u16; 2] = [kind, 0x0001].map(|b: u16| b.to_be());
+        let mut message = bytemuck::cast::<[u16; 6], [u8; 12]>(header).to_vec();
+        message.extend(&name[..]);
+        message.extend(bytemuck::cast_slice(&question));
+        // an OPT record advertises the size of responses which can be received (EDNS, RFC 6891):
+        // [root name, type, payload size, extended rcode and flags, data length]
+        message.push(0);
+        let opt: [u16; 5] = [OPT, MAX_DATAGRAM, 0x0000, 0x0000, 0x0000].map(|b: u16| b.to_be());
+        message.extend(bytemuck::cast_slice(&opt));
+        message
+    })
+}
+
+/// The answer to a query for the addresses of a name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Answer {
+    /// The addresses of the name, which are empty for a negative answer.
+    addresses: Vec<IpAddr>,
+    /// How long the answer may be cached.
+    ttl: Duration,
+    /// Whether the name does not exist at all (NXDOMAIN), as opposed to not having addresses.
+    missing: bool,
+}
+
+/// The answers to the A and AAAA queries, if they were answered.
+type Answers = [Option<Answer>; 2];
+
+/// Combine the answers to the queries into the addresses of a host and how long they may be cached.
+///
+/// A host without addresses is not an error here, so the negative answer can be cached.
+fn found(answers: Answers) -> Answer {
+    let [ipv4, ipv6] = answers.map(Option::unwrap_or_default);
+    let addresses = [&ipv4.addresses[..], &ipv6.addresses[..]].concat();
+    // a name without addresses is cached for as long as both negative answers allow
+    let ttl = [&ipv4, &ipv6]
+        .iter()
+        .filter(|answer| answer.addresses.is_empty() == addresses.is_empty())
+        .map(|answer| answer.ttl)
+        .min()
+        .unwrap_or_default();
+    Answer {
+        missing: addresses.is_empty() && (ipv4.missing || ipv6.missing),
+        addresses,
+        ttl,
+    }
+}
+
+/// The addresses of a name, erroring if it has none, with a distinct message if it does not exist at all.
+fn existing(query: &str, answer: Answer) -> Result<Vec<IpAddr>, io::Error> {
+    if answer.missing {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{query} does not exist"),
+        ));
+    }
+    if answer.addresses.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no address found for {query}"),
+        ));
+    }
+    Ok(answer.addresses)
+}
+
+/// The error for a server which failed to process a query, e.g. with `SERVFAIL`.
+fn failed(code: u16, query: &str) -> io::Error {
+    let reason = match code {
+        1 => "the query was malformed (FORMERR)",
+        2 => "server failure (SERVFAIL)",
+        4 => "not implemented (NOTIMP)",
+        5 => "the query was refused (REFUSED)",
+        16 => "unsupported EDNS version (BADVERS)",
+        _ => "unknown error",
+    };
+    io::Error::other(format!(
+        "nameserver failed to answer for {query}: {reason}, response code {code}"
+    ))
+}
+
+/// Send the queries to a nameserver and receive its answers, giving up after the timeout.
+///
+/// Fails if the nameserver does not answer in time, can not be reached, or fails to process the queries.
+fn exchange(
+    server: SocketAddr,
+    messages: &[Vec<u8>; 2],
+    query: &str,
+    timeout: Duration,
+) -> Result<Answers, io::Error> {
+    // create the socket, with the same address family as the nameserver
+    let local = match server {
+        SocketAddr::V6(_) => "[::]:0",
+        SocketAddr::V4(_) => "0.0.0.0:0",
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(server)?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    // write dns lookup messages
+    for message in messages {
+        socket.send(message)?;
+    }
+
+    // read dns responses, which may arrive in any order
+    let overall = Instant::now() + timeout;
+    let mut deadline = overall;
+    let mut answers: Answers = [None, None];
+    let mut failure = None;
+    while answers.iter().any(Option::is_none) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut buf = vec![0u8; usize::from(MAX_DATAGRAM)];
+        let received = match left {
+            left if left.is_zero() => Err(io::ErrorKind::TimedOut.into()),
+            left => (socket.set_read_timeout(Some(left))).and_then(|()| socket.recv(&mut buf)),
+        };
+        let n = match received {
+            Ok(n) => n,
+            // one of the answers may be enough, if the other record type is never answered
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if answers.iter().flatten().any(|a| !a.addresses.is_empty()) {
+                    break;
+                }
+                return Err(timed_out());
+            }
+            Err(e) => return Err(e),
+        };
+        buf.truncate(n);
+
+        // match the response to its query by the id and the question, ignoring unexpected or forged responses
+        let Some(i) = response_to(messages, &buf) else {
+            continue;
+        };
+
+        // responses which do not fit in a datagram are truncated, so the query is repeated over tcp
+        if buf[2] & 0x02 != 0 || n == usize::from(MAX_DATAGRAM) {
+            let timeout = overall.saturating_duration_since(Instant::now());
+            buf = query_tcp(server, &messages[i], Some(timeout))?;
+        }
+
+        // names which do not exist are an answer, but other errors mean the nameserver could not answer
+        match rcode(&buf) {
+            Some(NOERROR | NXDOMAIN) | None => {
+                answers[i] = Some(answer(&buf, query).unwrap_or_default());
+            }
+            Some(code) => {
+                failure = Some(code);
+                answers[i] = Some(Answer::default());
+            }
+        }
+
+        // wait only briefly for the other answer once there are addresses to connect to
+        if answers[i].as_ref().is_some_and(|a| !a.addresses.is_empty()) {
+            deadline = deadline.min(Instant::now() + RESOLUTION_DELAY);
+        }
+    }
+
+    match failure {
+        Some(code) if answers.iter().flatten().all(|a| a.addresses.is_empty()) => {
+            Err(failed(code, query))
+        }
+        _ => Ok(answers),
+    }
+}
+
+/// Find the query which a response answers by its id and question, if any, so unexpected or forged responses are ignored.
+fn response_to(messages: &[Vec<u8>; 2], response: &[u8]) -> Option<usize> {
+    let i = (messages.iter()).position(|m| response.len() >= 12 && m[..2] == response[..2])?;
+    (response[2] & 0x80 != 0 && same_question(&messages[i], response)).then_some(i)
+}
 
-    // create the socket
+/// Ask the devices on the local network for the addresses of a `.local` name with multicast DNS (RFC 6762).
+///
+/// The queries are sent from an ephemeral port, so the device which owns the name answers directly,
+/// like a nameserver would. Only IPv4 is used, as IPv6 multicast requires choosing a network interface.
+fn multicast(
+    group: SocketAddr,
+    query: &str,
+    timeout: Option<Duration>,
+) -> Result<Answer, io::Error> {
+    let messages = queries(query, ids());
     let socket = UdpSocket::bind("0.0.0.0:0")?;
-    socket.connect(&DNS_SERVERS[..])?;
+    for message in &messages {
+        socket.send_to(message, group)?;
+    }
 
-    // write dns lookup message
-    socket.send_to(&message, &DNS_SERVERS[..]).unwrap();
+    // any device may answer, so responses are accepted from every address
+    let mut deadline = Instant::now() + timeout.map_or(MDNS_TIMEOUT, |t| t.min(MDNS_TIMEOUT));
+    let mut answers: Answers = [None, None];
+    while answers.iter().any(Option::is_none) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        let mut buf = vec![0u8; MAX_MULTICAST_DATAGRAM];
+        socket.set_read_timeout(Some(left))?;
+        let n = match socket.recv_from(&mut buf) {
+            Ok((n, _)) => n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        buf.truncate(n);
+        let Some(i) = response_to(&messages, &buf) else {
+            continue;
+        };
+        answers[i] = Some(answer(&buf, query).unwrap_or_default());
+        if answers[i].as_ref().is_some_and(|a| !a.addresses.is_empty()) {
+            deadline = deadline.min(Instant::now() + RESOLUTION_DELAY);
+        }
+    }
+    // names which no device answered for are not cached, as the device may just be offline
+    Ok(found(answers))
+}
 
-    // read dns response
-    let mut buf = vec![0u8; 256];
-    socket.peek_from(&mut buf)?;
-    let n = socket.recv(&mut buf)?;
-    buf.resize(n, 0);
+/// Whether a name is `localhost` or in the `.localhost` domain, which always refer to the loopback address (RFC 6761).
+fn is_localhost(name: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    let suffix = name.len().checked_sub(".localhost".len());
+    name.eq_ignore_ascii_case("localhost")
+        || suffix.is_some_and(|i| {
+            name.get(i..)
+                .is_some_and(|s| s.eq_ignore_ascii_case(".localhost"))
+        })
+}
 
-    // parse out the address
-    let ip = &buf.get(message.len()..).unwrap()[12..];
-    let address = IpAddr::V4(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]));
+/// Whether a name is in the `.local` domain, which is resolved with multicast DNS instead of nameservers.
+fn is_local(name: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    name.len() > 6 && name[name.len() - 6..].eq_ignore_ascii_case(".local")
+}
+
+/// The group which devices listen to for multicast DNS queries.
+const MDNS_GROUP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+/// How long devices are given to answer a multicast DNS query.
+const MDNS_TIMEOUT: Duration = Duration::from_secs(2);
+/// Maximum size of a multicast DNS message, which may be as large as the network allows.
+const MAX_MULTICAST_DATAGRAM: usize = 9000;
 
-    Ok(address)
+/// How long each nameserver is given to answer, the default of resolv.conf.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many times each nameserver is asked, see [`QUERY_TIMEOUT`].
+const ATTEMPTS: usize = 2;
+
+/// Send a query over TCP, for responses which are too large for a datagram (RFC 7766).
+///
+/// Messages are prefixed with their length, as TCP does not preserve message boundaries.
+fn query_tcp(
+    server: SocketAddr,
+    message: &[u8],
+    timeout: Option<Duration>,
+) -> Result<Vec<u8>, io::Error> {
+    let mut stream = connect(server, timeout)?;
+    query_stream(&mut stream, message)
 }
-static DNS_SERVERS: LazyLock<Vec<SocketAddr>> = LazyLock::new(|| {
-    // find name servers (platform-dependent)
-    #[cfg(unix)]
+
+/// Open a TCP connection to a nameserver, with read and write timeouts.
+fn connect(server: SocketAddr, timeout: Option<Duration>) -> Result<TcpStream, io::Error> {
+    let stream = match timeout {
+        Some(timeout) if timeout.is_zero() => return Err(timed_out()),
+        Some(timeout) => TcpStream::connect_timeout(&server, timeout)?,
+        None => TcpStream::connect(server)?,
+    };
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    Ok(stream)
+}
+
+/// Send a query over a stream and receive the response, both prefixed with their length.
+fn query_stream(stream: &mut (impl Read + Write), message: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let length = u16::try_from(message.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+    stream.write_all(&[&length.to_be_bytes()[..], message].concat())?;
+
+    let map = |e: io::Error| match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => timed_out(),
+        _ => e,
+    };
+    let mut length = [0u8; 2];
+    stream.read_exact(&mut length).map_err(map)?;
+    let mut response = vec![0u8; usize::from(u16::from_be_bytes(length))];
+    stream.read_exact(&mut response).map_err(map)?;
+    if response.get(..2) != Some(&message[..2]) || !same_question(message, &response) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "dns response does not match the query",
+        ));
+    }
+    Ok(response)
+}
+
+/// The error for a nameserver which did not answer in time.
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "dns query timed out")
+}
+
+/// Maximum size of DNS responses over UDP, which is advertised with EDNS and avoids fragmentation of the datagrams.
+const MAX_DATAGRAM: u16 = 1232;
+
+/// How long to wait for the answer to the other query, once one of them has been answered with addresses (RFC 8305).
+const RESOLUTION_DELAY: Duration = Duration::from_millis(50);
+
+/// The record type of IPv4 addresses.
+const A: u16 = 1;
+/// The record type of IPv6 addresses.
+const AAAA: u16 = 28;
+
+/// Parse the addresses of a name from a DNS response, along with the shortest time to live of the records leading to them.
+///
+/// CNAME records are followed from the name to the names it is an alias for, and records of other names are ignored.
+/// Returns `None` if the response is malformed, e.g. if a record does not fit in the message or has an invalid length.
+fn answer(response: &[u8], name: &str) -> Option<Answer> {
+    let header = response.get(..12)?;
+    // the response must be a reply, and names which do not exist have no addresses
+    if header[2] & 0x80 == 0 {
+        return None;
+    }
+    match rcode(response)? {
+        NOERROR => {}
+        NXDOMAIN => {
+            let ttl = negative_ttl(response)?;
+            return Some(Answer {
+                addresses: Vec::new(),
+                ttl,
+                missing: true,
+            });
+        }
+        _ => return Some(Answer::default()),
+    }
+
+    // read the answers, which may be in any order
+    let mut offset = skip_questions(response)?;
+    let mut records = Vec::new();
+    for _ in 0..u16::from_be_bytes([header[6], header[7]]) {
+        let (owner, kind, ttl, data) = read_record(response, offset)?;
+        offset = data.end;
+        let bytes = &response[data.clone()];
+        let data = match kind {
+            A => Record::Address(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+            AAAA => Record::Address(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+            CNAME => {
+                let (target, end) = read_name(response, data.start)?;
+                if end != data.end {
+                    return None;
+                }
+                Record::Alias(target)
+            }
+            _ => Record::Other,
+        };
+        records.push((owner, ttl, data));
+    }
+
+    // follow the aliases of the name, guarding against loops
+    let mut names = vec![name.trim_end_matches('.').to_ascii_lowercase()];
+    let mut addresses = Vec::new();
+    let mut shortest = u32::MAX;
+    let mut i = 0;
+    while let Some(name) = names.get(i).cloned() {
+        for (_, ttl, data) in records.iter().filter(|(owner, ..)| *owner == name) {
+            match data {
+                Record::Address(address) => addresses.push(*address),
+                Record::Alias(target) if !names.contains(target) => names.push(target.clone()),
+                _ => continue,
+            }
+            shortest = shortest.min(*ttl);
+        }
+        i += 1;
+    }
+    let ttl = if addresses.is_empty() {
+        negative_ttl(response)?
+    } else {
+        Duration::from_secs(u64::from(shortest))
+    };
+    Some(Answer {
+        addresses,
+        ttl,
+        missing: false,
+    })
+}
+
+/// How long a negative answer may be cached, which is the smaller of the time to live of the SOA record in the
+/// authority section and its minimum field (RFC 2308 section 5), limited to [`MAX_NEGATIVE_TTL`].
+///
+/// Answers without an SOA record are not cached.
+fn negative_ttl(response: &[u8]) -> Option<Duration> {
+    let count = |i: usize| usize::from(u16::from_be_bytes([response[i], response[i + 1]]));
+    let mut offset = skip_questions(response)?;
+    for i in 0..count(6) + count(8) {
+        let (_, kind, ttl, data) = read_record(response, offset)?;
+        offset = data.end;
+        if i < count(6) || kind != SOA {
+            continue;
+        }
+        // the data is the primary nameserver, the mailbox, and five numbers of which the minimum is the last
+        let (_, end) = read_name(response, data.start)?;
+        let (_, end) = read_name(response, end)?;
+        let minimum = response
+            .get(end + 16..end + 20)
+            .filter(|_| end + 20 == data.end)?;
+        let minimum = u32::from_be_bytes(minimum.try_into().ok()?);
+        let ttl = Duration::from_secs(u64::from(ttl.min(minimum)));
+        return Some(ttl.min(MAX_NEGATIVE_TTL));
+    }
+    Some(Duration::ZERO)
+}
+
+/// Maximum time negative answers are cached, so names which are created soon after are found.
+const MAX_NEGATIVE_TTL: Duration = Duration::from_mins(15);
+
+/// Find the response code of a DNS response, which is extended by the OPT record of EDNS (RFC 6891 section 6.1.3).
+fn rcode(response: &[u8]) -> Option<u16> {
+    let header = response.get(..12)?;
+    let mut code = u16::from(header[3] & 0x0f);
+    let records = [6, 8, 10].map(|i| usize::from(u16::from_be_bytes([header[i], header[i + 1]])));
+    let mut offset = skip_questions(response)?;
+    for _ in 0..records.iter().sum::<usize>() {
+        let (_, kind, ttl, data) = read_record(response, offset)?;
+        offset = data.end;
+        if kind == OPT {
+            code |= u16::try_from(ttl >> 24).ok()? << 4;
+        }
+    }
+    Some(code)
+}
+
+/// Whether a response is for the same question as a query, comparing the names case-insensitively.
+fn same_question(query: &[u8], response: &[u8]) -> bool {
+    let question = |message: &[u8]| {
+        let (name, end) = read_name(message, 12)?;
+        Some((name, message.get(end..end + 4)?.to_vec()))
+    };
+    let count = |message: &[u8]| message.get(4..6).map(<[u8]>::to_vec);
+    count(response) == count(query)
+        && question(response).is_some()
+        && question(response) == question(query)
+}
+
+/// Skip the questions of a DNS message, each a name followed by the type and class, returning the offset after them.
+fn skip_questions(message: &[u8]) -> Option<usize> {
+    let questions = u16::from_be_bytes([*message.get(4)?, *message.get(5)?]);
+    let mut offset = 12;
+    for _ in 0..questions {
+        (_, offset) = read_name(message, offset)?;
+        offset += 4;
+    }
+    Some(offset)
+}
+
+/// Read a resource record from a DNS message, returning its owner, type, time to live, and where its data is.
+fn read_record(message: &[u8], offset: usize) -> Option<(String, u16, u32, Range<usize>)> {
+    let (owner, offset) = read_name(message, offset)?;
+    let record = message.get(offset..offset + 10)?;
+    let kind = u16::from_be_bytes([record[0], record[1]]);
+    let ttl = u32::from_be_bytes([record[4], record[5], record[6], record[7]]);
+    let length = usize::from(u16::from_be_bytes([record[8], record[9]]));
+    let data = offset + 10..offset + 10 + length;
+    message.get(data.clone())?;
+    Some((owner, kind, ttl, data))
+}
+
+/// The data of a DNS resource record.
+#[derive(Debug)]
+enum Record {
+    /// An IPv4 or IPv6 address, from an A or AAAA record.
+    Address(IpAddr),
+    /// The name which the owner of a CNAME record is an alias for.
+    Alias(String),
+    /// Records of any other type, which are not used.
+    Other,
+}
+
+/// The record type of aliases.
+const CNAME: u16 = 5;
+/// The response code of a successful response.
+const NOERROR: u16 = 0;
+/// The response code of a response for a name which does not exist.
+const NXDOMAIN: u16 = 3;
+
+/// The record type of the start of a zone of authority, which is included in negative answers.
+const SOA: u16 = 6;
+/// The record type of the pseudo-record which carries EDNS options.
+const OPT: u16 = 41;
+
+/// Read a name from a DNS message, returning it in lowercase along with the offset after it.
+///
+/// Names may be compressed by ending them with a pointer to a name elsewhere in the message (RFC 1035 section 4.1.4).
+fn read_name(message: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    // pointers must point backwards, which rules out loops
+    let mut limit = offset;
+    loop {
+        let length = *message.get(position)?;
+        match length {
+            0 => break,
+            length if length & 0xc0 == 0xc0 => {
+                let pointer = usize::from(u16::from_be_bytes([
+                    length & 0x3f,
+                    *message.get(position + 1)?,
+                ]));
+                end.get_or_insert(position + 2);
+                if pointer >= limit {
+                    return None;
+                }
+                (position, limit) = (pointer, pointer);
+            }
+            length if length & 0xc0 == 0 => {
+                let label = message.get(position + 1..position + 1 + usize::from(length))?;
+                labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                position += 1 + usize::from(length);
+            }
+            _ => return None,
+        }
+    }
+    Some((labels.join("."), end.unwrap_or(position + 1)))
+}
+
+/// The configuration of the system resolver.
+#[derive(Debug, Default)]
+struct Config {
+    /// The nameservers which are asked for names outside of the scoped domains.
+    servers: Vec<SocketAddr>,
+    /// Nameservers for the names in a domain, which are asked instead of the others, e.g. those of a VPN.
+    scoped: Vec<(String, Vec<SocketAddr>)>,
+    /// The domains which bare names are completed with.
+    domains: Vec<String>,
+    /// How many dots a name needs to be tried as-is before it is completed with the search domains.
+    ndots: usize,
+}
+impl Config {
+    /// The nameservers to ask for a name, which are the ones scoped to the most specific domain containing it, if any.
+    fn nameservers(&self, name: &str) -> &[SocketAddr] {
+        let name = name.trim_end_matches('.');
+        let contains = |domain: &str| {
+            name.len() > domain.len()
+                && name[name.len() - domain.len() - 1..].starts_with('.')
+                && name[name.len() - domain.len()..].eq_ignore_ascii_case(domain)
+                || name.eq_ignore_ascii_case(domain)
+        };
+        (self.scoped.iter())
+            .filter(|(domain, _)| contains(domain))
+            .max_by_key(|(domain, _)| domain.len())
+            .map_or(&self.servers[..], |(_, servers)| &servers[..])
+    }
+}
+
+/// The resolver configuration of the system, which lists the nameservers on most Unix systems.
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// The configuration of the system resolver, which is read again whenever resolv.conf changes.
+fn system() -> Arc<Config> {
+    static CONFIG: Mutex<Option<(Option<SystemTime>, Arc<Config>)>> = Mutex::new(None);
+    let modified = fs::metadata(RESOLV_CONF).and_then(|m| m.modified()).ok();
+    let mut config = CONFIG.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, current)) = config.as_ref().filter(|(time, _)| *time == modified) {
+        return current.clone();
+    }
+    let current = Arc::new(load());
+    *config = Some((modified, current.clone()));
+    current
+}
+
+/// Read the configuration of the system resolver (platform-dependent).
+fn load() -> Config {
+    let resolv = fs::read_to_string(RESOLV_CONF).unwrap_or_default();
+    let (domains, ndots) = search_list(&resolv);
+    let mut config = Config {
+        domains,
+        ndots,
+        ..Config::default()
+    };
+    #[cfg(target_os = "macos")]
     {
-        use std::{fs, net::ToSocketAddrs};
-        let resolv = fs::read_to_string("/etc/resolv.conf").unwrap();
-        let servers = resolv
+        // resolv.conf only lists the primary nameservers, and may be missing entirely
+        let resolvers = std::process::Command::new("scutil")
+            .arg("--dns")
+            .output()
+            .map(|output| scutil(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default();
+        for (domain, servers) in resolvers {
+            match domain {
+                Some(domain) => config.scoped.push((domain, servers)),
+                None => config.servers.extend(servers),
+            }
+        }
+    }
+    #[cfg(unix)]
+    if config.servers.is_empty() {
+        config.servers = resolv
             .lines()
-            .filter_map(|l| l.split_once("nameserver ").map(|(_, s)| s.to_string()))
-            .flat_map(|ns| (ns, 53).to_socket_addrs().unwrap())
-            .collect::<Vec<_>>();
-
-        servers
+            .filter_map(|l| l.split_once("nameserver ").map(|(_, s)| s.trim()))
+            .filter_map(|ns| ns.parse::<IpAddr>().ok())
+            .map(|address| SocketAddr::new(address, 53))
+            .collect();
     }
     #[cfg(windows)]
     {
-        // todo: get windows name servers
-        vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 53)]
+        // the adapter configuration is stored in the registry, both configured and assigned by DHCP
+        config.servers = ["Tcpip", "Tcpip6"]
+            .iter()
+            .filter_map(|service| {
+                let key = format!(r"HKLM\SYSTEM\CurrentControlSet\Services\{service}\Parameters");
+                std::process::Command::new("reg")
+                    .args(["query", &key, "/s"])
+                    .output()
+                    .ok()
+            })
+            .flat_map(|output| registry_servers(&String::from_utf8_lossy(&output.stdout)))
+            .map(|address| SocketAddr::new(address, 53))
+            .collect();
+        if config.servers.is_empty() {
+            (config.servers).push(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 53));
+        }
     }
     #[cfg(not(any(unix, windows)))]
-    {
-        vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 53)]
+    (config.servers).push(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 53));
+    config
+}
+
+/// A resolver configuration: the domain it is scoped to, if any, and its nameservers.
+#[cfg(any(target_os = "macos", test))]
+type Scope = (Option<String>, Vec<SocketAddr>);
+
+/// Parse the resolver configurations from the output of `scutil --dns`, skipping those without nameservers (such as mDNS).
+///
+/// Only the configurations for unscoped queries are used, which are listed before the ones scoped to an interface.
+#[cfg(any(target_os = "macos", test))]
+fn scutil(output: &str) -> Vec<Scope> {
+    let mut resolvers = Vec::new();
+    let mut current: Option<(Option<String>, Vec<IpAddr>, u16)> = None;
+    let mut finish = |current: Option<(Option<String>, Vec<IpAddr>, u16)>| {
+        if let Some((domain, addresses, port)) = current.filter(|(_, a, _)| !a.is_empty()) {
+            let servers = addresses.into_iter().map(|a| SocketAddr::new(a, port));
+            resolvers.push((domain, servers.collect()));
+        }
+    };
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("DNS configuration (") {
+            break;
+        }
+        if line.starts_with("resolver #") {
+            finish(current.replace((None, Vec::new(), 53)));
+            continue;
+        }
+        let (Some((key, value)), Some((domain, addresses, port))) =
+            (line.split_once(':'), current.as_mut())
+        else {
+            continue;
+        };
+        match key.trim() {
+            "domain" => *domain = Some(value.trim().trim_end_matches('.').to_string()),
+            key if key.starts_with("nameserver[") => {
+                addresses.extend(value.trim().parse::<IpAddr>().ok());
+            }
+            "port" => *port = value.trim().parse().unwrap_or(53),
+            _ => {}
+        }
     }
-});
+    finish(current);
+    resolvers
+}
+
+/// Find the nameservers in the output of `reg query`, from the `NameServer` and `DhcpNameServer` values of each adapter.
+///
+/// Configured nameservers are listed before the ones assigned by DHCP, and each address is only listed once.
+#[cfg(any(windows, test))]
+fn registry_servers(output: &str) -> Vec<IpAddr> {
+    let values = |name: &str| {
+        output
+            .lines()
+            .filter_map(move |line| {
+                let mut fields = line.split_ascii_whitespace();
+                let matches = fields.next()? == name && fields.next()? == "REG_SZ";
+                matches.then(|| fields.collect::<Vec<_>>().join(" "))
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut servers = Vec::new();
+    for value in values("NameServer").iter().chain(&values("DhcpNameServer")) {
+        for address in value.split([',', ' ']).filter_map(|a| a.parse().ok()) {
+            if !servers.contains(&address) {
+                servers.push(address);
+            }
+        }
+    }
+    servers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        answer, cached, candidates, exchange, existing, hosts, ids, is_localhost, multicast,
+        query_tcp, rcode, read_hosts, registry_servers, remember, resolve, resolve_all,
+        resolve_records, resolve_with, same_question, scutil, search_list, Answer, RecordType,
+        CACHE,
+    };
+    use crate::{DnsResolver, DohResolver, Resolver};
+    use std::{
+        io::{self, prelude::*},
+        net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket},
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn answers() {
+        // a response for www.example.org, with a CNAME record pointing to example.org, and an A record for it
+        let mut response = vec![0xab, 0xcd, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        response.extend(b"\x03www\x07example\x03org\x00\x00\x01\x00\x01");
+        response.extend([0xc0, 0x0c, 0, 5, 0, 1, 0, 0, 0x0e, 0x10, 0, 2, 0xc0, 0x10]);
+        response.extend([
+            0xc0, 0x10, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 4, 93, 184, 215, 14,
+        ]);
+        let address = "93.184.215.14".parse::<IpAddr>().unwrap();
+        assert_eq!(
+            answer(&response, "www.example.org"),
+            Some(Answer {
+                addresses: vec![address],
+                ttl: Duration::from_secs(42),
+                missing: false
+            })
+        );
+        assert_eq!(
+            answer(&response[..response.len() - 1], "www.example.org"),
+            None
+        );
+        // an OPT record extends the response code, e.g. to BADVERS
+        let mut extended = response.clone();
+        extended[11] = 1;
+        extended.extend([0, 0, 41, 0x04, 0xd0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!((rcode(&response), rcode(&extended)), (Some(0), Some(16)));
+        assert_eq!(
+            answer(&extended, "www.example.org"),
+            Some(Answer::default())
+        );
+
+        // records of other names are ignored
+        assert_eq!(answer(&response, "example.net"), Some(Answer::default()));
+
+        // the response must be for the question of the query, regardless of case
+        let mut query = response[..33].to_vec();
+        query[2..4].copy_from_slice(&[1, 0]);
+        query[7] = 0;
+        query[13] = b'W';
+        assert!(same_question(&query, &response));
+        query[32] = 28;
+        assert!(!same_question(&query, &response));
+        let [first, second] = ids();
+        assert_ne!(first, second);
+
+        // a pointer which does not point backwards could loop forever
+        let mut looping = response[..response.len() - 16].to_vec();
+        looping.extend([
+            0xc0, 0x30, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 4, 93, 184, 215, 14,
+        ]);
+        assert_eq!(answer(&looping, "www.example.org"), None);
+
+        // an A record must contain exactly four bytes
+        let mut invalid = response[..response.len() - 16].to_vec();
+        invalid.extend([0xc0, 0x10, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 3, 93, 184, 215]);
+        assert_eq!(answer(&invalid, "www.example.org"), None);
+
+        // a response with two AAAA records, using the shortest time to live
+        let mut response = vec![0xab, 0xce, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        response.extend(b"\x07example\x03org\x00\x00\x1c\x00\x01");
+        for (ttl, last) in [(300u32, 1), (90, 2)] {
+            response.extend([0xc0, 0x0c, 0, 28, 0, 1]);
+            response.extend(ttl.to_be_bytes());
+            response.extend([
+                0, 16, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, last,
+            ]);
+        }
+        let addresses = ["2001:db8::1", "2001:db8::2"].map(|a| a.parse::<IpAddr>().unwrap());
+        assert_eq!(
+            answer(&response, "example.org"),
+            Some(Answer {
+                addresses: addresses.to_vec(),
+                ttl: Duration::from_secs(90),
+                missing: false
+            })
+        );
+
+        // addresses are not looked up
+        let address = "::1".parse::<IpAddr>().unwrap();
+        assert_eq!(resolve("[::1]", None).unwrap(), [address]);
+    }
+
+    #[test]
+    fn negative_answers() {
+        // a response for a name which does not exist, with the SOA record of the zone
+        let mut response = vec![0xab, 0xcd, 0x81, 0x83, 0, 1, 0, 0, 0, 1, 0, 0];
+        response.extend(b"\x07missing\x07example\x03org\x00\x00\x01\x00\x01");
+        response.extend([0xc0, 0x14, 0, 6, 0, 1, 0, 0, 0x0e, 0x10, 0, 33]);
+        response.extend(b"\x02ns\xc0\x14\x05admin\xc0\x14");
+        for field in [2024_u32, 7200, 900, 1_209_600, 270] {
+            response.extend(field.to_be_bytes());
+        }
+        let missing = answer(&response, "missing.example.org").unwrap();
+        assert_eq!(
+            (missing.ttl, missing.missing),
+            (Duration::from_secs(270), true)
+        );
+        // names without addresses are answered the same way, but do exist
+        response[3] = 0x80;
+        let empty = answer(&response, "missing.example.org").unwrap();
+        assert_eq!(
+            (empty.ttl, empty.missing),
+            (Duration::from_secs(270), false)
+        );
+
+        // the negative answer is cached, and is an error which tells the cases apart
+        let cache = Mutex::new(Vec::new());
+        remember(&cache, "missing.example.org", &missing);
+        let cached = cached(&cache, "missing.example.org").unwrap();
+        let error = existing("missing.example.org", cached).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "missing.example.org does not exist");
+        let error = existing("missing.example.org", empty).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no address found for missing.example.org"
+        );
+    }
+
+    #[test]
+    fn record_types() {
+        let addresses = resolve_records("[::1]", RecordType::Aaaa).unwrap();
+        assert_eq!(addresses, ["::1".parse::<IpAddr>().unwrap()]);
+        let error = resolve_records("127.0.0.1", RecordType::Aaaa).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            resolve_all("127.0.0.1").unwrap(),
+            [IpAddr::from([127, 0, 0, 1])]
+        );
+    }
+
+    #[test]
+    fn hosts_file() {
+        let contents = "127.0.0.1 localhost\n::1 localhost ip6-localhost # loopback\n\n# 192.0.2.1 example.org\n192.0.2.2\tdev.example.org  API.example.org\ninvalid example.org\n";
+        let addresses = |name| {
+            hosts(contents, name)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addresses("localhost"), ["127.0.0.1", "::1"]);
+        assert_eq!(addresses("api.example.org"), ["192.0.2.2"]);
+        assert!(addresses("example.org").is_empty());
+        assert!(addresses("loopback").is_empty());
+
+        // the file is only read again once it changes
+        assert!(Arc::ptr_eq(&read_hosts(), &read_hosts()));
+    }
+
+    #[test]
+    fn localhost() {
+        for name in ["localhost", "LocalHost.", "app.localhost"] {
+            assert!(is_localhost(name), "{name}");
+        }
+        let loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(resolve("app.localhost", None).unwrap(), [loopback]);
+        // names which only start with localhost are looked up like any other name
+        for name in ["localhost.example.org", "localhostfoo.com", "notlocalhost"] {
+            assert!(!is_localhost(name), "{name}");
+        }
+        // so they are sent to the nameserver, which does not answer here
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let servers = [silent.local_addr().unwrap()];
+        let timeout = Some(Duration::from_millis(200));
+        let cache = Mutex::new(vec![]);
+        let result = resolve_with("localhost.example.org", Some(&servers), &cache, timeout);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn search_domains() {
+        let resolv = "domain corp.example.org\nsearch default.svc.cluster.local svc.cluster.local. # comment\nnameserver 10.96.0.10\noptions ndots:5 timeout:2\n";
+        let (domains, ndots) = search_list(resolv);
+        assert_eq!(domains, ["default.svc.cluster.local", "svc.cluster.local"]);
+        assert_eq!(ndots, 5);
+        assert_eq!(search_list("options ndots:30"), (vec![], 15));
+
+        let domains = ["corp.example.org".to_string()];
+        assert_eq!(
+            candidates("backend", &domains, 1),
+            ["backend.corp.example.org", "backend"]
+        );
+        assert_eq!(
+            candidates("example.com", &domains, 1),
+            ["example.com", "example.com.corp.example.org"]
+        );
+        assert_eq!(candidates("example.com.", &domains, 1), ["example.com"]);
+    }
+
+    #[test]
+    fn windows_nameservers() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\{1}\r\n    DhcpNameServer    REG_SZ    192.168.1.1 192.168.1.2\r\n    NameServer    REG_SZ    \r\n\r\nHKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\{2}\r\n    NameServer    REG_SZ    10.0.0.53,192.168.1.1\r\n    DhcpServer    REG_SZ    192.168.1.254\r\n";
+        let servers = registry_servers(output)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(servers, ["10.0.0.53", "192.168.1.1", "192.168.1.2"]);
+    }
+
+    #[test]
+    fn macos_resolvers() {
+        let output = "\nDNS configuration\n\nresolver #1\n  search domain[0] : example.org\n  nameserver[0] : 192.168.1.1\n  nameserver[1] : fe80::1\n  if_index : 6 (en0)\n  reach    : 0x00020002 (Reachable,Directly Reachable Address)\n\nresolver #2\n  domain   : local\n  options  : mdns\n  timeout  : 5\n\nresolver #3\n  domain   : corp.example.\n  nameserver[0] : 10.0.0.53\n  port     : 5353\n\nDNS configuration (for scoped queries)\n\nresolver #1\n  nameserver[0] : 192.168.1.1\n";
+        let resolvers = scutil(output)
+            .into_iter()
+            .map(|(domain, servers)| {
+                (
+                    domain,
+                    servers.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            resolvers,
+            [
+                (
+                    None,
+                    vec!["192.168.1.1:53".to_string(), "[fe80::1]:53".to_string()]
+                ),
+                (
+                    Some("corp.example".to_string()),
+                    vec!["10.0.0.53:5353".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            // messages are prefixed with their length, and the response echoes the id of the query
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut query = [0u8; 31];
+            stream.read_exact(&mut query).unwrap();
+            assert_eq!(query[..4], [0, 29, 0xab, 0xcd]);
+            let mut response = query.to_vec();
+            response[4] = 0x81;
+            stream.write_all(&response).unwrap();
+        });
+        let mut query = vec![0xab, 0xcd, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend(b"\x07example\x03org\x00\x00\x01\x00\x01");
+        let response = query_tcp(server, &query, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(response[..3], [0xab, 0xcd, 0x81]);
+    }
+
+    #[test]
+    fn nameserver_failures() {
+        // a nameserver which only answers the A query, and then fails to process the queries
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 512];
+            for flags in [0x80, 0x82] {
+                for _ in 0..2 {
+                    let (n, peer) = socket.recv_from(&mut buffer).unwrap();
+                    if buffer[1] == 0xce && flags == 0x80 {
+                        continue;
+                    }
+                    let mut response = buffer[..n].to_vec();
+                    response[2..4].copy_from_slice(&[0x81, flags]);
+                    if flags == 0x80 {
+                        response[7] = 1;
+                        response
+                            .extend([0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 4, 192, 0, 2, 1]);
+                    }
+                    socket.send_to(&response, peer).unwrap();
+                }
+            }
+        });
+        let messages = [0xcd, 0xce].map(|id| {
+            let mut message = vec![0xab, id, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+            message.extend(b"\x07example\x03org\x00\x00");
+            message.extend([if id == 0xcd { 1 } else { 28 }, 0, 1]);
+            message
+        });
+        let timeout = Duration::from_secs(5);
+        let answers = exchange(server, &messages, "example.org", timeout).unwrap();
+        let address = "192.0.2.1".parse::<IpAddr>().unwrap();
+        let expected = Answer {
+            addresses: vec![address],
+            ttl: Duration::from_secs(42),
+            missing: false,
+        };
+        assert_eq!(answers, [Some(expected), None]);
+        let error = exchange(server, &messages, "example.org", timeout).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "nameserver failed to answer for example.org: server failure (SERVFAIL), response code 2"
+        );
+    }
+
+    #[test]
+    fn custom_nameservers() {
+        // a nameserver which only has an address for the A query
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 512];
+            for _ in 0..2 {
+                let (n, peer) = socket.recv_from(&mut buffer).unwrap();
+                let mut response = buffer[..n - 11].to_vec();
+                response[2..4].copy_from_slice(&[0x81, 0x80]);
+                response[11] = 0;
+                if buffer[n - 14] == 1 {
+                    response[7] = 1;
+                    response.extend([0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 4, 10, 0, 0, 7]);
+                }
+                socket.send_to(&response, peer).unwrap();
+            }
+        });
+        let resolver = DnsResolver::new().servers(&[server]);
+        let address = IpAddr::from([10, 0, 0, 7]);
+        assert_eq!(resolver.resolve("internal.example.").unwrap(), [address]);
+        handle.join().unwrap();
+        // the answer is cached by the resolver, but not for other resolvers
+        assert_eq!(resolver.resolve("internal.example.").unwrap(), [address]);
+        assert!(cached(&CACHE, "internal.example").is_none());
+    }
+
+    #[test]
+    fn multicast_dns() {
+        // a device which answers the A query for its name, from a different socket than the one it listens on
+        let group = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = group.local_addr().unwrap();
+        thread::spawn(move || {
+            let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let mut buffer = [0u8; 512];
+            for _ in 0..2 {
+                let (n, peer) = group.recv_from(&mut buffer).unwrap();
+                if buffer[n - 14] != 1 {
+                    continue;
+                }
+                let mut response = buffer[..n - 11].to_vec();
+                response[2..4].copy_from_slice(&[0x84, 0]);
+                response[7] = 1;
+                response[11] = 0;
+                // the class has the cache-flush bit set
+                response.extend([
+                    0xc0, 0x0c, 0, 1, 0x80, 1, 0, 0, 0, 75, 0, 4, 192, 168, 1, 42,
+                ]);
+                device.send_to(&response, peer).unwrap();
+            }
+        });
+        let answer = multicast(address, "Printer.local", Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(answer.addresses, [IpAddr::from([192, 168, 1, 42])]);
+        assert_eq!(answer.ttl, Duration::from_secs(75));
+        assert_eq!(
+            candidates("printer.local", &["example.org".to_string()], 2),
+            ["printer.local"]
+        );
+    }
+
+    #[test]
+    fn dns_over_https() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                // the query is the body of the request, which ends with the OPT record advertising 1232 bytes
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                while !request.ends_with(&[0, 0, 41, 0x04, 0xd0, 0, 0, 0, 0, 0, 0]) {
+                    let n = stream.read(&mut buffer).unwrap();
+                    request.extend(&buffer[..n]);
+                }
+                let head = String::from_utf8_lossy(&request).into_owned();
+                assert!(head.contains("Content-Type: application/dns-message\r\n"));
+                let query = &request[request.len() - 40..request.len() - 11];
+                let mut response = query.to_vec();
+                response[2..4].copy_from_slice(&[0x81, 0x80]);
+                response[11] = 0;
+                if query[26] == 1 {
+                    response[7] = 1;
+                    response.extend([0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 0x2a, 0, 4, 192, 0, 2, 1]);
+                }
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                    response.len()
+                );
+                stream
+                    .write_all(&[head.as_bytes(), &response].concat())
+                    .unwrap();
+            }
+        });
+        let resolver = DohResolver::new(&url);
+        let address = "192.0.2.1".parse::<IpAddr>().unwrap();
+        assert_eq!(resolver.resolve("example.org").unwrap(), [address]);
+        server.join().unwrap();
+        // the answer is cached, as the server is gone
+        assert_eq!(resolver.resolve("EXAMPLE.org").unwrap(), [address]);
+    }
+}
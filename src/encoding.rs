@@ -0,0 +1,256 @@
+use crate::Response;
+#[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+use std::fmt;
+#[cfg(feature = "zstd")]
+use std::io::BufReader;
+#[cfg(feature = "gzip")]
+use std::io::Write;
+use std::io::{self, Read};
+
+/// Content codings which can be decoded, in order of preference.
+pub(crate) const ACCEPTED: &[&str] = &[
+    #[cfg(feature = "gzip")]
+    "gzip",
+    #[cfg(feature = "gzip")]
+    "deflate",
+    #[cfg(feature = "brotli")]
+    "br",
+    #[cfg(feature = "zstd")]
+    "zstd",
+];
+
+/// A content coding which request bodies can be compressed with, see [`Request::compress_body`](crate::Request::compress_body).
+///
+/// Which codings are available depends on the enabled features.
+#[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Compress with gzip, which is understood by most servers.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Compress with zlib.
+    #[cfg(feature = "gzip")]
+    Deflate,
+    /// Compress with Brotli.
+    #[cfg(feature = "brotli")]
+    Brotli,
+    /// Compress with Zstandard.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+#[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+impl Encoding {
+    /// The name of the coding, as used in the `Content-Encoding` header.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => "gzip",
+            #[cfg(feature = "gzip")]
+            Self::Deflate => "deflate",
+            #[cfg(feature = "brotli")]
+            Self::Brotli => "br",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Compress some data with the coding.
+    pub(crate) fn encode(self, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "gzip")]
+            Self::Deflate => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "brotli")]
+            Self::Brotli => {
+                let mut encoded = Vec::new();
+                let params = brotli::enc::BrotliEncoderParams::default();
+                brotli::BrotliCompress(&mut &data[..], &mut encoded, &params)?;
+                Ok(encoded)
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+}
+#[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A reader which decodes a body with its content coding.
+pub(crate) enum Decoder<R: Read> {
+    /// The body is not encoded, or it is left as it is.
+    Identity(R),
+    /// The body is compressed with gzip.
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::read::MultiGzDecoder<R>),
+    /// The body is compressed with zlib.
+    #[cfg(feature = "gzip")]
+    Deflate(flate2::read::ZlibDecoder<R>),
+    /// The body is compressed with Brotli.
+    #[cfg(feature = "brotli")]
+    Brotli(Box<brotli::Decompressor<R>>),
+    /// The body is compressed with Zstandard.
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, BufReader<R>>),
+}
+impl<R: Read> Decoder<R> {
+    /// Decode the body of a response with its content coding.
+    ///
+    /// If the body is decoded, the `Content-Encoding` and `Content-Length` headers are removed, as they describe the encoded body.
+    /// Bodies with unsupported or multiple codings are left as they are, and so are all bodies if decoding is not enabled.
+    #[cfg_attr(not(feature = "zstd"), allow(clippy::unnecessary_wraps))]
+    pub(crate) fn new(
+        response: &mut Response,
+        reader: R,
+        enabled: bool,
+    ) -> Result<Self, io::Error> {
+        let coding = (response.headers.get("Content-Encoding")).filter(|_| enabled);
+        let decoder = match coding.map(|c| c.trim().to_ascii_lowercase()).as_deref() {
+            #[cfg(feature = "gzip")]
+            Some("gzip" | "x-gzip") => Self::Gzip(flate2::read::MultiGzDecoder::new(reader)),
+            #[cfg(feature = "gzip")]
+            Some("deflate") => Self::Deflate(flate2::read::ZlibDecoder::new(reader)),
+            #[cfg(feature = "brotli")]
+            Some("br") => Self::Brotli(Box::new(brotli::Decompressor::new(reader, BROTLI_BUFFER))),
+            #[cfg(feature = "zstd")]
+            Some("zstd") => Self::Zstd(zstd::stream::read::Decoder::new(reader)?),
+            _ => Self::Identity(reader),
+        };
+        if !decoder.is_identity() {
+            response.headers.remove("Content-Encoding");
+            response.headers.remove("Content-Length");
+        }
+        Ok(decoder)
+    }
+
+    /// Whether the body is left as it is.
+    pub(crate) fn is_identity(&self) -> bool {
+        match self {
+            Self::Identity(_) => true,
+            #[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+            _ => false,
+        }
+    }
+
+    /// The encoded body.
+    pub(crate) fn get_ref(&self) -> &R {
+        match self {
+            Self::Identity(reader) => reader,
+            #[cfg(feature = "gzip")]
+            Self::Gzip(decoder) => decoder.get_ref(),
+            #[cfg(feature = "gzip")]
+            Self::Deflate(decoder) => decoder.get_ref(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder.get_ref(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(decoder) => decoder.get_ref().get_ref(),
+        }
+    }
+
+    /// Take the encoded body out of the decoder.
+    pub(crate) fn into_inner(self) -> R {
+        match self {
+            Self::Identity(reader) => reader,
+            #[cfg(feature = "gzip")]
+            Self::Gzip(decoder) => decoder.into_inner(),
+            #[cfg(feature = "gzip")]
+            Self::Deflate(decoder) => decoder.into_inner(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder.into_inner(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(decoder) => decoder.finish().into_inner(),
+        }
+    }
+}
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Identity(reader) => reader.read(buf),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(decoder) => decoder.read(buf),
+            #[cfg(feature = "gzip")]
+            Self::Deflate(decoder) => decoder.read(buf),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder.read(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+/// Size of the buffer for compressed input when decoding Brotli.
+#[cfg(feature = "brotli")]
+const BROTLI_BUFFER: usize = 4096;
+
+#[cfg(test)]
+mod tests {
+    use super::Decoder;
+    use crate::Response;
+    use std::io::Read;
+
+    /// Decode a body with the given coding, returning the text and the remaining headers.
+    fn decode(coding: &str, body: &[u8]) -> (String, usize) {
+        let message = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: {coding}\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        let mut response = Response::parse(message).unwrap();
+        let mut text = String::new();
+        (Decoder::new(&mut response, body, true).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        (text, response.headers.len())
+    }
+
+    #[test]
+    fn identity() {
+        assert_eq!(
+            decode("identity", b"hello world"),
+            ("hello world".into(), 2)
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(b"hello world").unwrap();
+        let body = gzip.finish().unwrap();
+        assert_eq!(decode("gzip", &body), ("hello world".into(), 0));
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn brotli() {
+        let mut body = Vec::new();
+        let params = brotli::enc::BrotliEncoderParams::default();
+        brotli::BrotliCompress(&mut &b"hello world"[..], &mut body, &params).unwrap();
+        assert_eq!(decode("br", &body), ("hello world".into(), 0));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let body = zstd::encode_all(&b"hello world"[..], 0).unwrap();
+        assert_eq!(decode("zstd", &body), ("hello world".into(), 0));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{retry, Response, StatusError};
+use std::{error, fmt, io};
+
+/// An error which occurred while sending a request.
+///
+/// Besides what went wrong, see [`Error::kind`], the error tells in which phase of the request it happened and for
+/// which URL, and keeps the underlying I/O error, which is returned by [`source`](error::Error::source).
+/// Errors from the I/O-level hooks, such as a [`Resolver`](crate::Resolver) or a [`Connector`](crate::Connector),
+/// are classified by the phase of the request they failed in.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// match Request::get("https://example.org").send() {
+///     Ok(response) => println!("{}", response.status),
+///     Err(e) if e.kind() == ErrorKind::Dns => eprintln!("unknown host: {e}"),
+///     Err(e) if e.is_retryable() => eprintln!("failed, but may succeed later: {e}"),
+///     Err(e) => eprintln!("request to {:?} failed while {:?}: {e}", e.url(), e.phase()),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Error {
+    /// What went wrong.
+    kind: ErrorKind,
+    /// The phase of the request which failed, if the error happened while sending a request.
+    phase: Option<Phase>,
+    /// The URL of the request which failed, which is the target of the redirect if one was followed.
+    url: Option<String>,
+    /// The underlying I/O error, which is missing if the request itself did not fail.
+    source: Option<io::Error>,
+}
+
+/// The ways in which sending a request can fail, see [`Error::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The URL is invalid, or its host is missing.
+    Url,
+    /// A header name or value contains invalid characters.
+    Header,
+    /// The host could not be resolved.
+    Dns,
+    /// The connection to the server or the proxy could not be opened.
+    Connect,
+    /// The TLS handshake failed, or TLS is not supported.
+    Tls,
+    /// The server or the proxy sent an invalid response.
+    Protocol,
+    /// The server responded with a client or server error status, see [`Response::error_for_status`].
+    Status,
+    /// A timeout or the deadline was exceeded.
+    Timeout,
+    /// The redirect policy stopped following redirects.
+    TooManyRedirects,
+    /// Reading or writing failed, or the request was invalid or cancelled.
+    Io,
+}
+
+/// The phases of sending a request, in order, see [`Error::phase`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Phase {
+    /// Validating and formatting the request.
+    Prepare,
+    /// Resolving the host of the server or the proxy.
+    Resolve,
+    /// Opening the connection, including tunnels through proxies and the TLS handshake.
+    Connect,
+    /// Sending the head and body of the request.
+    Send,
+    /// Receiving the head and body of the response.
+    Receive,
+    /// Following a redirect.
+    Redirect,
+}
+
+impl Error {
+    /// Create an error of a kind, caused by an I/O error.
+    pub(crate) fn new(kind: ErrorKind, source: io::Error) -> Self {
+        Self {
+            kind,
+            phase: None,
+            url: None,
+            source: Some(source),
+        }
+    }
+
+    /// The error for a redirect which the redirect policy refused to follow.
+    pub(crate) fn too_many_redirects() -> Self {
+        Self {
+            kind: ErrorKind::TooManyRedirects,
+            phase: Some(Phase::Redirect),
+            url: None,
+            source: None,
+        }
+    }
+
+    /// Attach the phase of the request to errors, and classify those which are not classified yet as `kind`.
+    ///
+    /// Errors which were already attached to a phase keep it, and timeouts stay timeouts.
+    pub(crate) fn during(phase: Phase, kind: ErrorKind) -> impl Fn(io::Error) -> io::Error {
+        move |e| {
+            let mut e = Error::from(e);
+            e.phase.get_or_insert(phase);
+            if e.kind == ErrorKind::Io {
+                e.kind = kind;
+            }
+            e.into()
+        }
+    }
+
+    /// Attach the URL of the request to errors, unless they are already attached to one.
+    pub(crate) fn at(url: &str) -> impl Fn(io::Error) -> io::Error + '_ {
+        move |e| {
+            let mut e = Error::from(e);
+            e.url.get_or_insert_with(|| url.to_string());
+            e.into()
+        }
+    }
+
+    /// What went wrong.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The phase of the request in which the error happened,
+    /// or `None` if it did not happen while sending a request, e.g. if it was returned by a middleware.
+    #[must_use]
+    pub fn phase(&self) -> Option<Phase> {
+        self.phase
+    }
+
+    /// The URL of the request which failed, which is the target of the redirect if the error happened after a redirect.
+    #[must_use]
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Whether a timeout or the deadline of the request was exceeded.
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        self.kind == ErrorKind::Timeout
+    }
+
+    /// Whether the request may succeed if it is sent again, e.g. because it timed out or the connection was reset.
+    ///
+    /// These are the errors which are retried by a [`RetryPolicy`](crate::RetryPolicy).
+    /// Note that sending a request which is not idempotent again may repeat its effects.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        self.is_timeout() || retry::is_transient(self.io_kind())
+    }
+
+    /// The kind of the underlying I/O error, which is [`io::ErrorKind::Other`] if there is none.
+    #[must_use]
+    pub fn io_kind(&self) -> io::ErrorKind {
+        self.io().map_or(io::ErrorKind::Other, io::Error::kind)
+    }
+
+    /// The response with the error status, if the error is from [`Response::error_for_status`].
+    #[must_use]
+    pub fn response(&self) -> Option<&Response> {
+        let status = self.io()?.get_ref()?.downcast_ref::<StatusError>()?;
+        Some(&status.response)
+    }
+
+    /// The underlying I/O error, if any.
+    pub(crate) fn io(&self) -> Option<&io::Error> {
+        self.source.as_ref()
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            // plain I/O errors describe themselves, unless they happened while sending a request
+            ErrorKind::Io | ErrorKind::Timeout | ErrorKind::Status if self.url.is_none() => {
+                match &self.source {
+                    Some(source) => return source.fmt(f),
+                    None => "request failed",
+                }
+            }
+            ErrorKind::Url => "invalid url",
+            ErrorKind::Header => "invalid header",
+            ErrorKind::Dns => "failed to resolve host",
+            ErrorKind::Connect => "failed to connect",
+            ErrorKind::Tls => "tls failed",
+            ErrorKind::Protocol => "invalid response",
+            ErrorKind::Status => "error status",
+            ErrorKind::Timeout => "timed out",
+            ErrorKind::TooManyRedirects => "too many redirects",
+            ErrorKind::Io => "request failed",
+        };
+        f.write_str(description)?;
+        if let Some(url) = &self.url {
+            write!(f, " for {url}")?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {source}")?;
+        }
+        Ok(())
+    }
+}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.io().map(|e| e as _)
+    }
+}
+impl From<io::Error> for Error {
+    /// Unwrap an error which was passed through an I/O error, or classify an I/O error as a timeout or a plain I/O error.
+    fn from(e: io::Error) -> Self {
+        match e.downcast::<Error>() {
+            Ok(e) => e,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Error::new(ErrorKind::Timeout, e),
+            Err(e) => Error::new(ErrorKind::Io, e),
+        }
+    }
+}
+impl From<Error> for io::Error {
+    /// Wrap the error, keeping the kind of the underlying I/O error, so it can be unwrapped again.
+    fn from(e: Error) -> Self {
+        io::Error::new(e.io_kind(), e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, ErrorKind, Phase, Request, Url};
+    use std::{error::Error as _, io};
+
+    #[test]
+    fn context() {
+        let resolver = |_: &str| Err(io::Error::new(io::ErrorKind::NotFound, "unknown host"));
+        let error = Request::get("http://unknown.example.org/a")
+            .resolver(&resolver)
+            .send()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Dns);
+        assert_eq!(error.phase(), Some(Phase::Resolve));
+        assert_eq!(error.url(), Some("http://unknown.example.org/a"));
+        assert_eq!(error.io_kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.source().unwrap().to_string(), "unknown host");
+        assert_eq!(
+            error.to_string(),
+            "failed to resolve host for http://unknown.example.org/a: unknown host"
+        );
+        assert!(!error.is_retryable());
+        let error = "http://[::1".parse::<Url>().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Url);
+    }
+
+    #[test]
+    fn classification() {
+        // errors survive being passed through I/O errors, and timeouts are recognized
+        let tls = io::Error::from(Error::new(
+            ErrorKind::Tls,
+            io::Error::other("bad certificate"),
+        ));
+        assert_eq!(Error::from(tls).kind(), ErrorKind::Tls);
+        let timeout = Error::from(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        assert!(timeout.is_timeout() && timeout.is_retryable());
+        let reset = Error::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(reset.kind(), ErrorKind::Io);
+        assert!(reset.is_retryable());
+        let cancelled = Error::from(io::Error::from(io::ErrorKind::Interrupted));
+        assert!(!cancelled.is_retryable());
+    }
+}
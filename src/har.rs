@@ -0,0 +1,319 @@
+use crate::{
+    base64, date::DateTime, percent, ConnectionInfo, Error, Middleware, Next, Request, Response,
+    StatusCode, Timings,
+};
+use serde_json::{json, Value};
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A middleware which records requests and their responses in the HTTP Archive (HAR) format.
+///
+/// Clones of a recorder share the same archive, so a clone can be added to a [`Client`](crate::Client) while the original is kept to save the archive.
+/// Failed requests are not recorded.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let recorder = Recorder::new();
+/// let client = Client::new().with(recorder.clone());
+/// client.get("localhost:8000").unwrap();
+/// recorder.save("session.har").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    /// Recorded HAR entries.
+    entries: Arc<Mutex<Vec<Value>>>,
+}
+impl Recorder {
+    /// Create a new recorder with an empty archive.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recorded archive as HAR JSON.
+    #[must_use]
+    pub fn to_har(&self) -> String {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "request", "version": env!("CARGO_PKG_VERSION") },
+                "entries": *entries,
+            }
+        });
+        serde_json::to_string_pretty(&har).unwrap_or_default()
+    }
+
+    /// Write the recorded archive to a file.
+    ///
+    /// # Errors
+    ///
+    /// May error if the file can not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        fs::write(path, self.to_har())
+    }
+}
+impl Middleware for Recorder {
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error> {
+        let started = SystemTime::now();
+        let start = Instant::now();
+        let recorded = record_request(&request);
+        let response = next.run(request)?;
+        let time = start.elapsed().as_secs_f64() * 1000.0;
+
+        let mut entry = json!({
+            "startedDateTime": timestamp(started),
+            "time": time,
+            "request": recorded,
+            "response": record_response(&response),
+            "cache": {},
+            "timings": record_timings(response.timings, time),
+        });
+        if let Some(address) = response.connection.remote_addr {
+            entry["serverIPAddress"] = json!(address.ip().to_string());
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.push(entry);
+        Ok(response)
+    }
+}
+
+/// A middleware which responds with responses from an HTTP Archive (HAR), instead of sending requests.
+///
+/// Requests are matched by method and URL.
+/// If the same request was recorded multiple times, the responses are replayed in order, and the last one is repeated.
+/// Requests which were not recorded fail with [`io::ErrorKind::NotFound`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let client = Client::new().with(Replay::load("session.har").unwrap());
+/// let response = client.get("localhost:8000").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Replay {
+    /// Method, URL and response of each recorded entry.
+    entries: Vec<(String, String, Response)>,
+    /// Whether each entry has been replayed.
+    served: Mutex<Vec<bool>>,
+}
+impl Replay {
+    /// Read recorded responses from HAR JSON.
+    ///
+    /// # Errors
+    ///
+    /// May error if the archive is not valid JSON, or if an entry is missing required fields.
+    pub fn from_har(har: &str) -> Result<Self, io::Error> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP archive");
+        let har: Value = serde_json::from_str(har).map_err(|_| invalid())?;
+        let entries = har["log"]["entries"]
+            .as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|entry| {
+                let (request, response) = (&entry["request"], &entry["response"]);
+                let text = |value: &Value| value.as_str().map(str::to_string).ok_or_else(invalid);
+                let headers = response["headers"]
+                    .as_array()
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .map(|header| Ok((text(&header["name"])?, text(&header["value"])?)))
+                    .collect::<Result<_, io::Error>>()?;
+                let response = Response {
+                    version: text(&response["httpVersion"])?,
+                    status: response["status"]
+                        .as_u64()
+                        .and_then(|s| u16::try_from(s).ok())
+                        .and_then(StatusCode::new)
+                        .ok_or_else(invalid)?,
+                    reason: text(&response["statusText"])?,
+                    headers,
+                    body: match &response["content"] {
+                        content if content["encoding"] == "base64" => {
+                            base64::decode(&text(&content["text"])?).ok_or_else(invalid)?
+                        }
+                        content => text(&content["text"]).unwrap_or_default().into_bytes(),
+                    },
+                    history: Vec::new(),
+                    connection: ConnectionInfo::default(),
+                    timings: Timings::default(),
+                };
+                Ok((text(&request["method"])?, text(&request["url"])?, response))
+            })
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        let served = Mutex::new(vec![false; entries.len()]);
+        Ok(Self { entries, served })
+    }
+
+    /// Read recorded responses from a HAR file.
+    ///
+    /// # Errors
+    ///
+    /// May error if the file can not be read, or if it is not a valid archive.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        Self::from_har(&fs::read_to_string(path)?)
+    }
+}
+impl Middleware for Replay {
+    fn handle(&self, request: Request, _: Next) -> Result<Response, Error> {
+        let method = request.method.as_str();
+        let url = request.absolute_url();
+        let matching = (0..self.entries.len())
+            .filter(|&i| self.entries[i].0 == method && self.entries[i].1 == url)
+            .collect::<Vec<_>>();
+
+        // replay the first response which has not been served yet, or the last one
+        let mut served = self.served.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(&index) = matching.iter().find(|&&i| !served[i]).or(matching.last()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no recorded response for {method} {url}"),
+            )
+            .into());
+        };
+        served[index] = true;
+        Ok(self.entries[index].2.clone())
+    }
+}
+
+/// The HAR representation of a request.
+fn record_request(request: &Request) -> Value {
+    let url = request.absolute_url();
+    let host = request.host_header().unwrap_or_default();
+    let headers = [("Host".to_string(), host.to_string())]
+        .into_iter()
+        .chain(
+            request
+                .custom_headers()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        )
+        .chain(request.option_headers())
+        .collect::<Vec<_>>();
+    let query = url
+        .split_once('?')
+        .map(|(_, query)| query.split('#').next().unwrap_or_default())
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            json!({ "name": percent::decode_form(name), "value": percent::decode_form(value) })
+        })
+        .collect::<Vec<_>>();
+
+    let mut recorded = json!({
+        "method": request.method.as_str(),
+        "url": url,
+        "httpVersion": request.version.to_string(),
+        "cookies": [],
+        "headers": headers.iter().map(|(k, v)| json!({ "name": k, "value": v })).collect::<Vec<_>>(),
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": request.body.len(),
+    });
+    if !request.body.is_empty() {
+        let mime = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("Content-Type"))
+            .map_or("", |(_, v)| v.as_str());
+        let text = String::from_utf8_lossy(&request.body);
+        recorded["postData"] = json!({ "mimeType": mime, "text": text });
+    }
+    recorded
+}
+
+/// The HAR representation of the timings of a request, which took `time` milliseconds in total.
+///
+/// Responses which were not received over a connection, e.g. from a mock, only have a total time.
+fn record_timings(timings: Timings, time: f64) -> Value {
+    if timings == Timings::default() {
+        return json!({ "send": 0, "wait": time, "receive": 0 });
+    }
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    json!({
+        "dns": timings.dns.map_or(-1.0, millis),
+        "connect": millis(timings.connect),
+        "send": millis(timings.send),
+        "wait": millis(timings.wait),
+        "receive": millis(timings.receive),
+    })
+}
+
+/// The HAR representation of a response.
+fn record_response(response: &Response) -> Value {
+    // bodies which are not text are encoded as base64
+    let mut content = json!({
+        "size": response.body.len(),
+        "mimeType": response.headers.content_type().unwrap_or_default(),
+    });
+    if let Ok(text) = std::str::from_utf8(&response.body) {
+        content["text"] = json!(text);
+    } else {
+        content["text"] = json!(base64::encode(&response.body));
+        content["encoding"] = json!("base64");
+    }
+    json!({
+        "status": response.status.as_u16(),
+        "statusText": response.reason,
+        "httpVersion": response.version,
+        "cookies": [],
+        "headers": response
+            .headers
+            .iter()
+            .map(|(k, v)| json!({ "name": k, "value": v }))
+            .collect::<Vec<_>>(),
+        "content": content,
+        "redirectURL": response.headers.location().unwrap_or_default(),
+        "headersSize": -1,
+        "bodySize": response.body.len(),
+    })
+}
+
+/// Format a point in time as an ISO 8601 timestamp with milliseconds, e.g. `1994-11-06T08:49:37.000Z`.
+fn timestamp(time: SystemTime) -> String {
+    let t = DateTime::from(time);
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_millis();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, Next, Recorder, Replay, Request, Response};
+    use std::io;
+
+    #[test]
+    fn record_and_replay() {
+        let recorder = Recorder::new();
+        let client = Client::new()
+            .with(recorder.clone())
+            .with(move |_: Request, _: Next| {
+                Response::parse("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nhello")
+                    .map_err(|e| io::Error::other(e).into())
+            });
+        client.get("example.org/a?b=c").unwrap();
+        let har = recorder.to_har();
+        assert!(har.contains("\"url\": \"http://example.org/a?b=c\""));
+
+        let client = Client::new().with(Replay::from_har(&har).unwrap());
+        let response = client.get("http://example.org/a?b=c").unwrap();
+        assert_eq!(
+            (response.status.as_u16(), response.text()),
+            (200, "hello".into())
+        );
+        let error = client.post("example.org/a?b=c", "").unwrap_err();
+        assert_eq!(error.io_kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,278 @@
+//! Collections of headers, and the names of standard headers.
+//!
+//! The names can be used instead of string literals, which avoids typos in header names.
+//! Headers are compared case-insensitively either way, so a misspelled case still replaces the header.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use request::*;
+//! let request = Request::get("example.org").header(headers::CACHE_CONTROL, "no-cache");
+//! assert_eq!(request.get_headers().get(headers::CACHE_CONTROL), Some("no-cache"));
+//! ```
+
+/// A collection of HTTP headers, whose names are compared case-insensitively.
+///
+/// The original case of each name is kept, and used when the headers are sent or displayed.
+/// A header can have multiple values, e.g. `Set-Cookie`, which are kept as separate lines.
+/// Headers are kept in the order they were received or added.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let mut headers = Headers::new();
+/// headers.insert("Content-Type", "text/plain");
+/// headers.insert("content-length", "5");
+///
+/// assert_eq!(headers.get("content-type"), Some("text/plain"));
+/// assert_eq!(headers.content_length(), Some(5));
+///
+/// headers.append("Set-Cookie", "a=1");
+/// headers.append("Set-Cookie", "b=2");
+/// assert_eq!(headers.get_all("set-cookie").collect::<Vec<_>>(), ["a=1", "b=2"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Headers {
+    /// Name and value of each header line, in the order they were received or added.
+    lines: Vec<(String, String)>,
+}
+impl Headers {
+    /// Create an empty collection of headers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of a header, or the first one if it has multiple values.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let (_, value) = self.iter().find(|(k, _)| k.eq_ignore_ascii_case(name))?;
+        Some(value)
+    }
+
+    /// All values of a header, in order.
+    pub fn get_all<'h>(&'h self, name: &'h str) -> impl Iterator<Item = &'h str> {
+        self.iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Whether a header is present.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Set a header, replacing all of its values and returning the first previous one.
+    ///
+    /// The header keeps the position of its first value, or is added at the end if it was not present.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let (name, value) = (name.into(), value.into());
+        let Some(index) = (self.lines.iter()).position(|(k, _)| k.eq_ignore_ascii_case(&name))
+        else {
+            self.lines.push((name, value));
+            return None;
+        };
+        // the values of the header all come at or after its first value
+        let previous = self.remove(&name);
+        self.lines.insert(index, (name, value));
+        previous
+    }
+
+    /// Add a value to a header after all other headers, keeping any values it already has.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.lines.push((name.into(), value.into()));
+    }
+
+    /// Remove all values of a header, returning the first one.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = (self.lines.iter()).position(|(k, _)| k.eq_ignore_ascii_case(name))?;
+        let (_, value) = self.lines.remove(index);
+        self.lines.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        Some(value)
+    }
+
+    /// The number of header values, counting each value of a header separately.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Whether there are no headers.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Iterate over the names and values of the headers in order, with one item for each value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        (self.lines.iter()).map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The size of the body in bytes, from the `Content-Length` header.
+    #[must_use]
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("Content-Length")?.trim().parse().ok()
+    }
+
+    /// The media type of the body, from the `Content-Type` header.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    /// Where the resource can be found, from the `Location` header.
+    #[must_use]
+    pub fn location(&self) -> Option<&str> {
+        self.get("Location")
+    }
+}
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut headers = Self::new();
+        headers.extend(iter);
+        headers
+    }
+}
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for Headers {
+    /// Add the headers, keeping the values of headers which are already present.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
+/// `Accept`: the media types which are acceptable in the response.
+pub const ACCEPT: &str = "Accept";
+/// `Accept-Encoding`: the content codings which are acceptable in the response.
+pub const ACCEPT_ENCODING: &str = "Accept-Encoding";
+/// `Accept-Language`: the natural languages which are preferred in the response.
+pub const ACCEPT_LANGUAGE: &str = "Accept-Language";
+/// `Accept-Ranges`: whether the server supports range requests.
+pub const ACCEPT_RANGES: &str = "Accept-Ranges";
+/// `Authorization`: the credentials of the client.
+pub const AUTHORIZATION: &str = "Authorization";
+/// `Cache-Control`: directives for caches.
+pub const CACHE_CONTROL: &str = "Cache-Control";
+/// `Connection`: options for the current connection.
+pub const CONNECTION: &str = "Connection";
+/// `Content-Disposition`: whether the body is displayed or downloaded, and its file name.
+pub const CONTENT_DISPOSITION: &str = "Content-Disposition";
+/// `Content-Encoding`: the content codings which were applied to the body.
+pub const CONTENT_ENCODING: &str = "Content-Encoding";
+/// `Content-Length`: the size of the body in bytes.
+pub const CONTENT_LENGTH: &str = "Content-Length";
+/// `Content-Range`: which part of the resource a partial body is.
+pub const CONTENT_RANGE: &str = "Content-Range";
+/// `Content-Type`: the media type of the body.
+pub const CONTENT_TYPE: &str = "Content-Type";
+/// `Cookie`: cookies which were stored by the client.
+pub const COOKIE: &str = "Cookie";
+/// `Date`: when the message was sent.
+pub const DATE: &str = "Date";
+/// `ETag`: the entity tag of the resource.
+pub const ETAG: &str = "ETag";
+/// `Expect`: behaviour which the client expects from the server.
+pub const EXPECT: &str = "Expect";
+/// `Host`: the host and port of the server.
+pub const HOST: &str = "Host";
+/// `If-Match`: only apply the request if the entity tag matches.
+pub const IF_MATCH: &str = "If-Match";
+/// `If-Modified-Since`: only respond if the resource was modified since a point in time.
+pub const IF_MODIFIED_SINCE: &str = "If-Modified-Since";
+/// `If-None-Match`: only respond if the entity tag does not match.
+pub const IF_NONE_MATCH: &str = "If-None-Match";
+/// `Last-Modified`: when the resource was last modified.
+pub const LAST_MODIFIED: &str = "Last-Modified";
+/// `Location`: where the resource can be found.
+pub const LOCATION: &str = "Location";
+/// `Origin`: the origin which the request comes from.
+pub const ORIGIN: &str = "Origin";
+/// `Proxy-Authenticate`: how to authenticate with the proxy.
+pub const PROXY_AUTHENTICATE: &str = "Proxy-Authenticate";
+/// `Proxy-Authorization`: the credentials of the client for the proxy.
+pub const PROXY_AUTHORIZATION: &str = "Proxy-Authorization";
+/// `Range`: which part of the resource is requested.
+pub const RANGE: &str = "Range";
+/// `Referer`: the page which linked to the resource.
+pub const REFERER: &str = "Referer";
+/// `Retry-After`: how long to wait before sending the request again.
+pub const RETRY_AFTER: &str = "Retry-After";
+/// `Set-Cookie`: a cookie which the client should store.
+pub const SET_COOKIE: &str = "Set-Cookie";
+/// `Transfer-Encoding`: the transfer codings which were applied to the body.
+pub const TRANSFER_ENCODING: &str = "Transfer-Encoding";
+/// `Upgrade`: a protocol to switch the connection to.
+pub const UPGRADE: &str = "Upgrade";
+/// `User-Agent`: the software which sent the request.
+pub const USER_AGENT: &str = "User-Agent";
+/// `Vary`: the request headers which the response depends on.
+pub const VARY: &str = "Vary";
+/// `WWW-Authenticate`: how to authenticate with the server.
+pub const WWW_AUTHENTICATE: &str = "WWW-Authenticate";
+
+#[cfg(test)]
+mod tests {
+    use crate::Headers;
+
+    #[test]
+    fn case_insensitive() {
+        let mut headers = Headers::from_iter([("Content-Type", "text/html")]);
+        assert_eq!(headers.get("content-type"), Some("text/html"));
+        assert_eq!(headers.content_type(), Some("text/html"));
+
+        let previous = headers.insert("CONTENT-TYPE", "text/plain");
+        assert_eq!(previous.as_deref(), Some("text/html"));
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("CONTENT-TYPE", "text/plain")]
+        );
+
+        assert_eq!(
+            headers.remove("Content-Type").as_deref(),
+            Some("text/plain")
+        );
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn multiple_values() {
+        let mut headers = Headers::from_iter([("Set-Cookie", "a=1"), ("set-cookie", "b=2")]);
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.get("Set-Cookie"), Some("a=1"));
+        assert_eq!(
+            headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("Set-Cookie", "a=1"), ("set-cookie", "b=2")]
+        );
+
+        headers.insert("Set-Cookie", "c=3");
+        assert_eq!(headers.get_all("set-cookie").collect::<Vec<_>>(), ["c=3"]);
+        assert_eq!(headers.get_all("Cookie").count(), 0);
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut headers = Headers::from_iter([("B", "1"), ("a", "2"), ("C", "3"), ("b", "4")]);
+        let order = |headers: &Headers| {
+            headers
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(&headers), ["B=1", "a=2", "C=3", "b=4"]);
+
+        headers.insert("b", "5");
+        headers.insert("D", "6");
+        assert_eq!(order(&headers), ["b=5", "a=2", "C=3", "D=6"]);
+        headers.remove("A");
+        assert_eq!(order(&headers), ["b=5", "C=3", "D=6"]);
+    }
+}
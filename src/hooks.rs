@@ -0,0 +1,84 @@
+use crate::Response;
+use std::fmt;
+
+/// A hook which is passed each request message right before it is sent, e.g. to log it.
+///
+/// The message is the head of the request as it is written to the connection, followed by the body unless a file is uploaded.
+/// It is called for each attempt, including retries and redirects.
+/// Any closure taking a byte slice can be used as a hook.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let log = |message: &[u8]| eprintln!("> {}", String::from_utf8_lossy(message));
+/// let request = Request::get("example.org").on_request(&log);
+/// ```
+pub trait OnRequest: Send + Sync {
+    /// Handle a request message which is about to be sent.
+    fn on_request(&self, message: &[u8]);
+}
+impl<F: Fn(&[u8]) + Send + Sync> OnRequest for F {
+    fn on_request(&self, message: &[u8]) {
+        self(message);
+    }
+}
+impl fmt::Debug for dyn OnRequest + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnRequest")
+    }
+}
+
+/// A hook which is passed each final response once it has been received, e.g. to log it.
+///
+/// It is called for each attempt, including retries and redirect responses, before they are followed.
+/// The body is empty if it is left to be read, e.g. with [`Request::send_streaming`](crate::Request::send_streaming).
+/// Any closure taking a [`Response`] can be used as a hook.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let log = |response: &Response| eprintln!("< {} {}", response.status, response.reason);
+/// let request = Request::get("example.org").on_response(&log);
+/// ```
+pub trait OnResponse: Send + Sync {
+    /// Handle a response which has been received.
+    fn on_response(&self, response: &Response);
+}
+impl<F: Fn(&Response) + Send + Sync> OnResponse for F {
+    fn on_response(&self, response: &Response) {
+        self(response);
+    }
+}
+impl fmt::Debug for dyn OnResponse + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnResponse")
+    }
+}
+
+/// A hook which is notified of each redirect which is followed, with the redirect response and the URL it leads to.
+///
+/// Any closure taking a [`Response`] and an URL can be used as a hook.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let log = |response: &Response, url: &str| eprintln!("{} redirect to {url}", response.status);
+/// let request = Request::get("example.org").on_redirect(&log);
+/// ```
+pub trait OnRedirect: Send + Sync {
+    /// Handle a redirect which is about to be followed.
+    fn on_redirect(&self, response: &Response, url: &str);
+}
+impl<F: Fn(&Response, &str) + Send + Sync> OnRedirect for F {
+    fn on_redirect(&self, response: &Response, url: &str) {
+        self(response, url);
+    }
+}
+impl fmt::Debug for dyn OnRedirect + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnRedirect")
+    }
+}
@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+
+/// Convert an internationalized domain name to its ASCII form, e.g. `bücher.example` to `xn--bcher-kva.example`.
+///
+/// Labels are lowercased and separated by any of the dots of UTS #46, and labels with non-ASCII characters
+/// are encoded with punycode. The full mapping and normalization tables of UTS #46 are not applied,
+/// so names should already be in their normalized form (NFC).
+pub(crate) fn to_ascii(host: &str) -> Cow<'_, str> {
+    if host.is_ascii() {
+        return Cow::Borrowed(host);
+    }
+    let labels = host
+        .split(['.', '\u{3002}', '\u{ff0e}', '\u{ff61}'])
+        .map(|label| {
+            let label = label
+                .chars()
+                .flat_map(char::to_lowercase)
+                .collect::<String>();
+            if label.is_ascii() {
+                return label;
+            }
+            punycode(&label).map_or(label, |encoded| format!("xn--{encoded}"))
+        })
+        .collect::<Vec<_>>();
+    Cow::Owned(labels.join("."))
+}
+
+/// Encode a label with punycode (RFC 3492), or `None` if it is too long to encode.
+fn punycode(label: &str) -> Option<String> {
+    let input = label.chars().map(u32::from).collect::<Vec<_>>();
+
+    // the basic code points are copied, followed by a delimiter
+    let mut output = label.chars().filter(char::is_ascii).collect::<String>();
+    let basic = u32::try_from(output.len()).ok()?;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    // the other code points are encoded in order, as deltas of their position and value
+    let (mut n, mut delta, mut bias, mut handled) = (0x80, 0u32, 72, basic);
+    while (handled as usize) < input.len() {
+        let next = input.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((next - n).checked_mul(handled + 1)?)?;
+        n = next;
+        for &c in &input {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c != n {
+                continue;
+            }
+            let mut q = delta;
+            let mut k = BASE;
+            loop {
+                let t = k.saturating_sub(bias).clamp(T_MIN, T_MAX);
+                if q < t {
+                    break;
+                }
+                output.push(digit(t + (q - t) % (BASE - t)));
+                q = (q - t) / (BASE - t);
+                k += BASE;
+            }
+            output.push(digit(q));
+            bias = adapt(delta, handled + 1, handled == basic);
+            delta = 0;
+            handled += 1;
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(output)
+}
+
+/// Adapt the bias after a code point is encoded, so the deltas which follow are encoded efficiently.
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / 700 } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > (BASE - T_MIN) * T_MAX / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + 38)
+}
+
+/// The number of digits of punycode numbers.
+const BASE: u32 = 36;
+/// The smallest threshold of a digit of a punycode number.
+const T_MIN: u32 = 1;
+/// The largest threshold of a digit of a punycode number.
+const T_MAX: u32 = 26;
+
+/// The character of a digit of a punycode number, `a` to `z` followed by `0` to `9`.
+fn digit(d: u32) -> char {
+    let d = u8::try_from(d).unwrap_or_default();
+    char::from(if d < 26 { b'a' + d } else { b'0' + d - 26 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_ascii;
+
+    #[test]
+    fn punycode() {
+        assert_eq!(to_ascii("example.org"), "example.org");
+        assert_eq!(to_ascii("Bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(to_ascii("münchen。de"), "xn--mnchen-3ya.de");
+        assert_eq!(to_ascii("ü.example"), "xn--tda.example");
+        // examples from RFC 3492 section 7.1
+        assert_eq!(
+            to_ascii("他们为什么不说中文"),
+            "xn--ihqwcrb4cv8a8dqg056pqjye"
+        );
+        assert_eq!(
+            to_ascii("почемужеонинеговорятпорусски"),
+            "xn--b1abfaaepdrnnbgefbadotcwatmq2g4l"
+        );
+    }
+}
@@ -2,19 +2,119 @@
 #![deny(unsafe_code)]
 #![doc = include_str!("../README.md")]
 
-mod dns;
+/// Emit a debug event through the `log` crate, if the `log` feature is enabled.
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+        // the arguments are still used without the feature, but never formatted
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+mod base64;
+mod batch;
+mod body;
+mod breaker;
+mod cache;
+mod charset;
+mod client;
+mod cookie;
+mod date;
+pub mod dns;
+mod encoding;
+mod error;
+#[cfg(feature = "har")]
+mod har;
+pub mod headers;
+mod hooks;
+mod idna;
+mod limiter;
+mod link;
+mod metrics;
+mod mime;
+mod mock;
+#[cfg(feature = "json")]
+mod oauth;
+mod parser;
+pub mod percent;
+mod pool;
+mod progress;
+mod proxy;
+mod random;
+mod redirect;
 mod request;
 mod response;
+mod retry;
+#[cfg(feature = "async")]
+mod runtime;
+#[cfg(target_os = "linux")]
+mod sendfile;
+mod session;
+mod sign;
+mod sse;
+mod status;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tls")]
+mod tls;
+mod transport;
 mod uri;
+mod websocket;
 
+pub use batch::send_all;
+pub use body::Body;
+pub use breaker::CircuitBreaker;
+pub use cache::{Cache, CacheControl, CacheStore, FileStore, MemoryStore};
+pub use client::*;
+pub use cookie::{Cookie, CookieJar, SameSite};
+#[cfg(feature = "tls")]
+pub use dns::DotResolver;
+pub use dns::{flush_dns_cache, DnsResolver, DohResolver, Resolver, SystemResolver};
+#[cfg(any(feature = "gzip", feature = "brotli", feature = "zstd"))]
+pub use encoding::Encoding;
+pub use error::{Error, ErrorKind, Phase};
+#[cfg(feature = "har")]
+pub use har::*;
+pub use headers::Headers;
+pub use hooks::{OnRedirect, OnRequest, OnResponse};
+pub use limiter::RateLimiter;
+pub use metrics::{Metrics, Observer};
+pub use mime::MediaType;
+pub use mock::*;
+#[cfg(feature = "json")]
+pub use oauth::OAuth2;
+pub use pool::Pool;
+pub use progress::*;
+pub use redirect::*;
 pub use request::*;
 pub use response::*;
+pub use retry::*;
+#[cfg(feature = "async")]
+pub use runtime::*;
+pub use session::Session;
+pub use sign::*;
+pub use sse::{Event, EventSource};
+pub use status::StatusCode;
+#[cfg(unix)]
+pub use transport::UnixConnector;
+pub use transport::{Connector, ReadWrite, TcpConnector, Upgraded};
+pub use uri::{register_default_port, Url};
+pub use websocket::{Message, WebSocket};
 
-use std::io;
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    panic,
+    path::Path,
+    thread,
+};
 
 /// GET the resource at an URL.
 ///
-/// This is a convenience function over using [`Request::get`] and [`Request::send`].
+/// This is a convenience function over using [`Request::get`] and [`Client::send`] with the default client, see [`set_default_client`].
 ///
 /// # Errors
 ///
@@ -26,13 +126,13 @@ use std::io;
 /// let response = request::get("localhost:8000").unwrap();
 /// assert_eq!(response.status, 200);
 /// ```
-pub fn get(url: &str) -> Result<Response, io::Error> {
-    Request::get(url).send()
+pub fn get(url: &str) -> Result<Response, Error> {
+    default_client().get(url)
 }
 
 /// POST a body to the URL.
 ///
-/// This is a convenience function over using [`Request::post`] and [`Request::send`].
+/// This is a convenience function over using [`Request::post`] and [`Client::send`] with the default client, see [`set_default_client`].
 ///
 /// # Errors
 ///
@@ -44,6 +144,356 @@ pub fn get(url: &str) -> Result<Response, io::Error> {
 /// let response = request::post("localhost:8000", "hello server!").unwrap();
 /// assert_eq!(response.status, 501); // unsupported method
 /// ```
-pub fn post(url: &str, body: &str) -> Result<Response, io::Error> {
-    Request::post(url, body).send()
+pub fn post(url: &str, body: &(impl AsRef<[u8]> + ?Sized)) -> Result<Response, Error> {
+    default_client().post(url, body)
+}
+
+/// Ask which methods the resource at an URL supports, with an OPTIONS request.
+///
+/// The supported methods can be read with [`Response::allowed_methods`], e.g. to discover an API or to debug CORS preflight requests.
+/// This is a convenience function over using [`Request::new`] and [`Client::send`] with the default client, see [`set_default_client`].
+///
+/// # Errors
+///
+/// May error if the provided URL is invalid, or if network issues arise.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let methods = request::options("localhost:8000/items").unwrap().allowed_methods();
+/// println!("supported methods: {methods:?}");
+/// ```
+pub fn options(url: &str) -> Result<Response, Error> {
+    default_client().send(Request::new(url, Method::OPTIONS))
+}
+
+/// Download the resource at an URL to a file, without holding the whole resource in memory.
+///
+/// The body is streamed to a temporary file which replaces the file once the download is complete, see [`Body::save_to`].
+/// The file is left untouched if the response is not successful, in which case the body is kept in the response.
+///
+/// # Errors
+///
+/// May error if the provided URL is invalid, if network issues arise, or if the file can not be written.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let response = request::download("localhost:8000/file.txt", "file.txt").unwrap();
+/// assert!(response.is_success());
+/// ```
+pub fn download(url: &str, path: impl AsRef<Path>) -> Result<Response, Error> {
+    let (mut response, mut body) = Request::get(url).send_streaming()?;
+    if response.is_success() {
+        body.save_to(path)?;
+    } else {
+        body.read_to_end(&mut response.body)?;
+    }
+    Ok(response)
+}
+
+/// Download the resource at an URL to a file, resuming a previously interrupted download.
+///
+/// The body is appended to the file as it arrives, so a download which is interrupted can be resumed by calling this again.
+/// If the file already exists and the server accepts range requests, only the remainder of the resource is requested,
+/// with an `If-Range` header holding the `ETag` or `Last-Modified` date of the resource, so the server sends the entire resource
+/// instead if it changed since. The file is then overwritten, as it also is if the server does not support resuming downloads.
+/// The file is left untouched if the response is not successful, which is also the case if the download was already complete.
+///
+/// # Errors
+///
+/// May error if the provided URL is invalid, if network issues arise, or if the file can not be written.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let response = request::download_resumable("localhost:8000/file.txt", "file.txt").unwrap();
+/// assert!(matches!(response.status.as_u16(), 200 | 206));
+/// ```
+pub fn download_resumable(url: &str, path: impl AsRef<Path>) -> Result<Response, Error> {
+    let path = path.as_ref();
+    let offset = fs::metadata(path).map_or(0, |m| m.len());
+
+    // only ask for the remainder if the server supports byte ranges, and can tell whether the resource has changed
+    let validator = if offset > 0 {
+        let head = Request::new(url, Method::HEAD).send()?;
+        range_validator(&head).filter(|_| accepts_byte_ranges(&head))
+    } else {
+        None
+    };
+    let request = match &validator {
+        Some(validator) => (Request::get(url).range(offset, None)).header("If-Range", validator),
+        None => Request::get(url),
+    };
+    let (mut response, mut body) = request.send_streaming()?;
+
+    let mut file = match response.status.as_u16() {
+        206 => {
+            // make sure the server sent the part which is missing
+            if content_range_start(&response) != Some(offset) {
+                return Err(unexpected_range());
+            }
+            OpenOptions::new().append(true).open(path)?
+        }
+        200..300 => File::create(path)?,
+        _ => {
+            body.read_to_end(&mut response.body)?;
+            return Ok(response);
+        }
+    };
+    io::copy(&mut body, &mut file)?;
+    Ok(response)
+}
+
+/// Download the resource at an URL to a file, fetching parts of it over multiple connections at once.
+///
+/// The server is probed with a HEAD request first. If it accepts range requests and announces the size of the resource,
+/// the resource is split into as many byte ranges as there are connections, which are fetched in parallel and written into place.
+/// Otherwise, or with a single connection, the resource is downloaded like with [`download`].
+/// The parts are written to a temporary file which replaces the file once all parts are complete, and which is removed if a part fails.
+///
+/// On success, the response to the HEAD request is returned, whose headers describe the entire resource.
+///
+/// # Errors
+///
+/// May error if the provided URL is invalid, if network issues arise, if the file can not be written,
+/// or if the server does not respond to a range request with the requested part.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let response = request::download_parallel("localhost:8000/large.iso", "large.iso", 4).unwrap();
+/// assert!(response.is_success());
+/// ```
+pub fn download_parallel(
+    url: &str,
+    path: impl AsRef<Path>,
+    connections: usize,
+) -> Result<Response, Error> {
+    let path = path.as_ref();
+    let head = Request::new(url, Method::HEAD).send()?;
+    let length = head.headers.content_length().filter(|&length| length > 0);
+    let (true, Some(length), 2..) = (
+        head.is_success() && accepts_byte_ranges(&head),
+        length,
+        connections,
+    ) else {
+        return download(url, path);
+    };
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "path has no file name").into());
+    };
+    let mut partial = OsString::from(name);
+    partial.push(".part");
+    let partial = path.with_file_name(partial);
+    File::create(&partial)?.set_len(length)?;
+
+    // fetch a part of the resource, and write it into its place in the file
+    let fetch = |start: u64, end: u64| -> Result<(), Error> {
+        let (response, body) = Request::get(url).range(start, Some(end)).send_streaming()?;
+        if response.status != 206 || content_range_start(&response) != Some(start) {
+            return Err(unexpected_range());
+        }
+        let mut file = OpenOptions::new().write(true).open(&partial)?;
+        file.seek(SeekFrom::Start(start))?;
+        let size = end - start + 1;
+        if io::copy(&mut Read::take(body, size), &mut file)? < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "part of the resource ended early",
+            )
+            .into());
+        }
+        Ok(())
+    };
+    let parts = length.min(connections as u64);
+    let size = length.div_ceil(parts);
+    let results = thread::scope(|scope| {
+        let handles = (0..parts)
+            .map(|part| part * size)
+            .take_while(|&start| start < length)
+            .map(|start| scope.spawn(move || fetch(start, (start + size).min(length) - 1)))
+            .collect::<Vec<_>>();
+        (handles.into_iter())
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect::<Vec<_>>()
+    });
+    let result = (results.into_iter().collect::<Result<(), Error>>())
+        .and_then(|()| Ok(File::open(&partial)?.sync_all()?))
+        .and_then(|()| Ok(fs::rename(&partial, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map(|()| head)
+}
+
+/// Whether the server advertises support for byte ranges with the `Accept-Ranges` header.
+fn accepts_byte_ranges(response: &Response) -> bool {
+    (response.headers.get("Accept-Ranges")).is_some_and(|units| {
+        units
+            .split(',')
+            .any(|unit| unit.trim().eq_ignore_ascii_case("bytes"))
+    })
+}
+
+/// The validator which is sent in the `If-Range` header, so a part of the resource is only sent if the resource has not changed.
+///
+/// Weak entity tags can not be used for ranges, so the `Last-Modified` date is used instead.
+fn range_validator(response: &Response) -> Option<String> {
+    let etag = (response.headers.get("ETag")).filter(|tag| !tag.starts_with("W/"));
+    (etag.or_else(|| response.headers.get("Last-Modified"))).map(str::to_string)
+}
+
+/// The first byte of the part of the resource in a partial response, from the `Content-Range` header.
+fn content_range_start(response: &Response) -> Option<u64> {
+    (response.headers.get("Content-Range"))
+        .and_then(|r| r.strip_prefix("bytes "))
+        .and_then(|r| r.split_once('-'))
+        .and_then(|(start, _)| start.parse().ok())
+}
+
+/// Error for a partial response which is not the part which was requested.
+fn unexpected_range() -> Error {
+    let e = io::Error::new(
+        io::ErrorKind::InvalidData,
+        "server sent an unexpected content range",
+    );
+    Error::new(ErrorKind::Protocol, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::prelude::*, net::TcpListener, thread};
+
+    #[test]
+    fn resume_download() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            let mut received = vec![];
+            for response in [
+                // the connection is closed in the middle of the body
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello ",
+                "HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nETag: \"v1\"\r\n\r\n",
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 6-10/11\r\nContent-Length: 5\r\n\r\nworld",
+                // the resource changed, so it is sent in full
+                "HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nETag: \"v2\"\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nnew",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = vec![0u8; 4096];
+                let n = stream.read(&mut buffer).unwrap();
+                received.push(String::from_utf8_lossy(&buffer[..n]).into_owned());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            received
+        });
+
+        let path = std::env::temp_dir().join(format!("request-resume-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(crate::download_resumable(&url, &path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello ");
+        let response = crate::download_resumable(&url, &path).unwrap();
+        assert_eq!(response.status, 206);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        let response = crate::download_resumable(&url, &path).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        fs::remove_file(&path).unwrap();
+
+        let received = server.join().unwrap();
+        assert!(received[0].starts_with("GET / HTTP/1.1\r\n"));
+        assert!(received[1].starts_with("HEAD / HTTP/1.1\r\n"));
+        assert!(received[2].contains("\r\nRange: bytes=6-\r\n"));
+        assert!(received[2].contains("\r\nIf-Range: \"v1\"\r\n"));
+        assert!(received[4].contains("\r\nIf-Range: \"v2\"\r\n"));
+    }
+
+    #[test]
+    fn parallel_download() {
+        // a server which responds to each range request on its own connection
+        let content = "hello parallel world!";
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            let mut ranges = vec![];
+            let mut handles = vec![];
+            for _ in 0..4 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = vec![0u8; 4096];
+                let n = stream.read(&mut buffer).unwrap();
+                let request = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                let range = (request.lines())
+                    .find_map(|line| line.strip_prefix("Range: bytes="))
+                    .map(str::to_string);
+                ranges.extend(range.clone());
+                handles.push(thread::spawn(move || {
+                    let response = match range {
+                        None => format!(
+                            "HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nContent-Length: {}\r\n\r\n",
+                            content.len()
+                        ),
+                        Some(range) => {
+                            let (start, end) = range.split_once('-').unwrap();
+                            let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+                            let part = &content[start..=end];
+                            format!(
+                                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{end}/{}\r\nContent-Length: {}\r\n\r\n{part}",
+                                content.len(),
+                                part.len()
+                            )
+                        }
+                    };
+                    stream.write_all(response.as_bytes()).unwrap();
+                }));
+            }
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            ranges.sort();
+            ranges
+        });
+
+        let path = std::env::temp_dir().join(format!("request-parallel-{}", std::process::id()));
+        let response = crate::download_parallel(&url, &path, 3).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(server.join().unwrap(), ["0-6", "14-20", "7-13"]);
+    }
+
+    #[test]
+    fn download() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            for response in [
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world",
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\n\r\nmissing",
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0u8; 4096]).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let path = std::env::temp_dir().join(format!("request-download-{}", std::process::id()));
+        let response = crate::download(&url, &path).unwrap();
+        assert_eq!(response.body, b"");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        // the file is kept if the response is an error, or if the body ends early
+        let response = crate::download(&url, &path).unwrap();
+        assert_eq!(response.body, b"missing");
+        assert!(crate::download(&url, &path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert!(!path.with_extension("part").exists());
+        fs::remove_file(&path).unwrap();
+        server.join().unwrap();
+    }
 }
@@ -0,0 +1,141 @@
+use crate::{uri, Error, Middleware, Next, Request, Response};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A middleware which limits how many requests are sent to each host, which is used as a middleware of a [`Client`](crate::Client).
+///
+/// Each host has a token bucket, which holds up to `burst` tokens and is refilled at the configured rate.
+/// Sending a request takes a token, and requests wait until a token is available if the bucket is empty,
+/// so up to `burst` requests are sent at once, and after that at most the configured rate.
+/// Requests wait in the order they are sent, and hosts are told apart by their name, ignoring the port.
+/// Clones of a limiter share the buckets.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// // at most 2 requests per second to each host, and up to 5 at once after a pause
+/// let limiter = RateLimiter::new(2, std::time::Duration::from_secs(1)).burst(5);
+/// let client = Client::new().with(limiter);
+/// ```
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Time between tokens being added to a bucket.
+    interval: Duration,
+    /// The most tokens a bucket holds.
+    burst: u32,
+    /// Buckets of the hosts which requests have been sent to.
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+/// The token bucket of a host.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// When the bucket was last refilled.
+    updated: Instant,
+    /// Tokens in the bucket, which is negative if requests are waiting for tokens.
+    tokens: f64,
+}
+
+impl RateLimiter {
+    /// Create a limiter which allows a number of requests per period to each host, e.g. 10 per second, without bursts.
+    ///
+    /// A number of 0 is treated as 1.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            interval: per / requests.max(1),
+            burst: 1,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Allow up to this many requests to be sent at once, if no requests were sent to the host for a while.
+    ///
+    /// The default is 1, and a burst of 0 is treated as 1.
+    pub fn burst(self, burst: u32) -> Self {
+        let mut limiter = self;
+        limiter.burst = burst.max(1);
+        limiter
+    }
+
+    /// Take a token from the bucket of a host, returning how long to wait until the token is available.
+    fn acquire(&self, host: &str, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let burst = f64::from(self.burst);
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            updated: now,
+            tokens: burst,
+        });
+        let interval = self.interval.as_secs_f64();
+        let added = match interval {
+            0.0 => burst,
+            _ => now.duration_since(bucket.updated).as_secs_f64() / interval,
+        };
+        bucket.tokens = (bucket.tokens + added).min(burst) - 1.0;
+        bucket.updated = now;
+        match bucket.tokens {
+            tokens if tokens >= 0.0 => Duration::ZERO,
+            tokens => self.interval.mul_f64(-tokens),
+        }
+    }
+}
+impl Middleware for RateLimiter {
+    fn handle(&self, request: Request, next: Next) -> Result<Response, Error> {
+        let url = request.absolute_url();
+        let host = uri::host(&url).unwrap_or_default().to_ascii_lowercase();
+        let wait = self.acquire(&host, Instant::now());
+        if !wait.is_zero() {
+            debug!("waiting {wait:?} before sending a request to {host}");
+            thread::sleep(wait);
+        }
+        next.run(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, Method, Mock, MockTransport, RateLimiter};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn rate_limiter() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(100)).burst(2);
+        let now = Instant::now();
+        assert_eq!(limiter.acquire("example.org", now), Duration::ZERO);
+        assert_eq!(limiter.acquire("example.org", now), Duration::ZERO);
+        assert_eq!(
+            limiter.acquire("example.org", now),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            limiter.acquire("example.org", now),
+            Duration::from_millis(200)
+        );
+        // hosts have their own buckets, which are refilled over time
+        assert_eq!(limiter.acquire("example.com", now), Duration::ZERO);
+        let later = now + Duration::from_millis(250);
+        assert_eq!(
+            limiter.acquire("example.org", later),
+            Duration::from_millis(50)
+        );
+
+        let transport = MockTransport::new()
+            .mock(Mock::new(Method::GET, "example.org"))
+            .mock(Mock::new(Method::GET, "example.com"));
+        let limiter = RateLimiter::new(20, Duration::from_secs(1));
+        let client = Client::new().with(limiter).with(transport);
+        let start = Instant::now();
+        for _ in 0..3 {
+            client.get("example.org").unwrap();
+        }
+        client.get("example.com").unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+}
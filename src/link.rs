@@ -0,0 +1,78 @@
+/// Parse the value of a `Link` header (RFC 8288) into relation types and the targets they point to.
+///
+/// A link with several relation types, e.g. `rel="prev first"`, is returned once for each of them.
+/// Relation types are lowercased, as they are case-insensitive, and links without one are skipped.
+pub(crate) fn parse(value: &str) -> Vec<(String, &str)> {
+    let mut links = Vec::new();
+    let mut rest = value;
+    loop {
+        // each link starts with a target in angle brackets
+        let Some(start) = rest.find('<') else {
+            return links;
+        };
+        let Some(end) = rest[start..].find('>') else {
+            return links;
+        };
+        let target = rest[start + 1..start + end].trim();
+        rest = &rest[start + end + 1..];
+
+        // followed by parameters, up to the comma before the next link
+        let mut relation = None;
+        while let Some(after) = rest.trim_start().strip_prefix(';') {
+            let (name, value);
+            (name, value, rest) = parameter(after);
+            if relation.is_none() && name.eq_ignore_ascii_case("rel") {
+                relation = Some(value);
+            }
+        }
+        for kind in relation.iter().flat_map(|r| r.split_ascii_whitespace()) {
+            links.push((kind.to_ascii_lowercase(), target));
+        }
+    }
+}
+
+/// Split a parameter into its name and value, which may be a quoted string, and the rest of the header value.
+fn parameter(s: &str) -> (&str, String, &str) {
+    let end = s.find([';', ',', '=']).unwrap_or(s.len());
+    let name = s[..end].trim();
+    let Some(s) = s[end..].strip_prefix('=') else {
+        return (name, String::new(), &s[end..]);
+    };
+    let s = s.trim_start();
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s.find([';', ',']).unwrap_or(s.len());
+        return (name, s[..end].trim().to_string(), &s[end..]);
+    };
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (name, value, &quoted[i + 1..]),
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    (name, value, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn links() {
+        let value = r#"<https://api.example.org/items?page=2>; rel="next", <https://api.example.org/items?page=1,a>; title="a, \"b\"; c"; REL="prev First"; rel=last, </about>; title=x"#;
+        assert_eq!(
+            parse(value),
+            [
+                ("next".to_string(), "https://api.example.org/items?page=2"),
+                ("prev".to_string(), "https://api.example.org/items?page=1,a"),
+                (
+                    "first".to_string(),
+                    "https://api.example.org/items?page=1,a"
+                ),
+            ]
+        );
+        assert!(parse("invalid; rel=next").is_empty());
+    }
+}
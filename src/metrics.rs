@@ -0,0 +1,55 @@
+use crate::{ErrorKind, Method, Request, StatusCode, Timings};
+use std::{fmt, time::Duration};
+
+/// An observer which is notified when requests sent by a [`Client`](crate::Client) start and finish, e.g. to collect metrics.
+///
+/// Observers see every request which is sent with [`Client::send`](crate::Client::send), after the options of the client are applied,
+/// so applications can feed metrics systems such as Prometheus or statsd without wrapping every call site.
+/// Any closure taking [`Metrics`] can be used as an observer of finished requests.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let client = Client::new().observe(|metrics: &Metrics| {
+///     println!("{} {} -> {:?} in {:?}", metrics.method, metrics.url, metrics.status, metrics.elapsed);
+/// });
+/// ```
+pub trait Observer: Send + Sync {
+    /// Handle a request which is about to be sent.
+    fn on_start(&self, _request: &Request) {}
+
+    /// Handle a request which has finished, successfully or not.
+    fn on_finish(&self, metrics: &Metrics);
+}
+impl<F: Fn(&Metrics) + Send + Sync> Observer for F {
+    fn on_finish(&self, metrics: &Metrics) {
+        self(metrics);
+    }
+}
+impl fmt::Debug for dyn Observer + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// Measurements of a finished request, see [`Observer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    /// Method of the request.
+    pub method: Method,
+    /// Absolute URL of the request.
+    pub url: String,
+    /// Status of the response, or `None` if the request failed.
+    pub status: Option<StatusCode>,
+    /// Kind of the error, if the request failed.
+    pub error: Option<ErrorKind>,
+    /// Time from starting the request until the response was received, including middleware, redirects and retries.
+    pub elapsed: Duration,
+    /// Size of the request body in bytes, including uploaded files.
+    pub sent: u64,
+    /// Size of the response body in bytes, after decompression.
+    pub received: u64,
+    /// How long the phases of the last request took, such as resolving the host and connecting, which are zero if it failed.
+    pub timings: Timings,
+}
@@ -0,0 +1,286 @@
+use crate::parser::is_token;
+use std::{fmt, io, path::Path, str::FromStr};
+
+/// A media type, as sent in the `Content-Type` header, e.g. `text/html; charset=utf-8`.
+///
+/// The type, subtype and parameter names are case-insensitive, so they are lowercased.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let media: MediaType = "multipart/form-data; boundary=\"abc\"".parse().unwrap();
+/// assert_eq!(media.essence(), "multipart/form-data");
+/// assert_eq!(media.boundary(), Some("abc"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+    /// The type and subtype, separated by a slash.
+    essence: String,
+    /// Names and values of the parameters, in order.
+    parameters: Vec<(String, String)>,
+}
+impl MediaType {
+    /// The top-level type, e.g. `text` in `text/html`.
+    #[must_use]
+    pub fn type_(&self) -> &str {
+        self.essence.split_once('/').map_or("", |(t, _)| t)
+    }
+
+    /// The subtype, e.g. `html` in `text/html`.
+    #[must_use]
+    pub fn subtype(&self) -> &str {
+        self.essence.split_once('/').map_or("", |(_, s)| s)
+    }
+
+    /// The type and subtype without parameters, e.g. `text/html`.
+    #[must_use]
+    pub fn essence(&self) -> &str {
+        &self.essence
+    }
+
+    /// The value of a parameter, with its name compared case-insensitively.
+    #[must_use]
+    pub fn param(&self, name: &str) -> Option<&str> {
+        (self.parameters.iter())
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// All parameters, as names and values in the order they were given.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        (self.parameters.iter()).map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The `charset` parameter, e.g. `utf-8`.
+    #[must_use]
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    /// The `boundary` parameter of multipart types.
+    #[must_use]
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+
+    /// Whether the media type is in a media range, such as `text/html`, `text/*` or `*/*`, as sent in the `Accept` header.
+    ///
+    /// Types are compared case-insensitively, and parameters of the range are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let media: MediaType = "text/html; charset=utf-8".parse().unwrap();
+    /// assert!(media.matches("text/*") && media.matches("TEXT/HTML") && media.matches("*/*"));
+    /// assert!(!media.matches("text/plain"));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, range: &str) -> bool {
+        let range = range.split(';').next().unwrap_or_default().trim();
+        let (t, s) = range.split_once('/').unwrap_or((range, ""));
+        match (t, s) {
+            ("*", "*") => true,
+            (t, "*") => t.eq_ignore_ascii_case(self.type_()),
+            _ => range.eq_ignore_ascii_case(&self.essence),
+        }
+    }
+}
+impl FromStr for MediaType {
+    type Err = io::Error;
+
+    /// Parse a media type, skipping parameters which are malformed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (essence, mut rest) = s.split_once(';').unwrap_or((s, ""));
+        let essence = essence.trim().to_ascii_lowercase();
+        let valid = essence.split_once('/').is_some_and(|(t, s)| {
+            [t, s]
+                .iter()
+                .all(|part| !part.is_empty() && part.bytes().all(is_token))
+        });
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid media type",
+            ));
+        }
+
+        let mut parameters = Vec::new();
+        while !rest.is_empty() {
+            // parameters without a value are skipped
+            let end = rest.find([';', '=']).unwrap_or(rest.len());
+            let name = rest[..end].trim().to_ascii_lowercase();
+            if !rest[end..].starts_with('=') {
+                rest = rest.get(end + 1..).unwrap_or_default();
+                continue;
+            }
+            let value;
+            (value, rest) = parameter_value(rest[end + 1..].trim_start());
+            if !name.is_empty() && name.bytes().all(is_token) {
+                parameters.push((name, value));
+            }
+        }
+        Ok(Self {
+            essence,
+            parameters,
+        })
+    }
+}
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.essence)?;
+        for (name, value) in &self.parameters {
+            if !value.is_empty() && value.bytes().all(is_token) {
+                write!(f, "; {name}={value}")?;
+            } else {
+                let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "; {name}=\"{escaped}\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split a parameter value, which may be a quoted string, from the parameters after it.
+fn parameter_value(s: &str) -> (String, &str) {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let (value, rest) = s.split_once(';').unwrap_or((s, ""));
+        return (value.trim().to_string(), rest);
+    };
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                // skip anything between the closing quote and the next parameter
+                let rest = &quoted[i + 1..];
+                return (value, rest.split_once(';').map_or("", |(_, rest)| rest));
+            }
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+/// Format an `Accept` header from media ranges and their quality values, leaving out quality values of 1.
+///
+/// Quality values are clamped between 0 and 1, and rounded to three decimals.
+pub(crate) fn accept(types: &[(&str, f32)]) -> String {
+    let ranges = types.iter().map(|&(range, quality)| {
+        let quality = if quality.is_nan() {
+            0.0
+        } else {
+            quality.clamp(0.0, 1.0)
+        };
+        if quality >= 1.0 {
+            return range.to_string();
+        }
+        let quality = format!("{quality:.3}");
+        let quality = quality.trim_end_matches('0').trim_end_matches('.');
+        format!("{range};q={quality}")
+    });
+    ranges.collect::<Vec<_>>().join(", ")
+}
+
+/// Guess the media type of a file from its extension, falling back to `application/octet-stream`.
+pub(crate) fn from_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    TYPES
+        .iter()
+        .find(|(e, _)| *e == extension)
+        .map_or("application/octet-stream", |(_, mime)| mime)
+}
+
+/// Media types of common file extensions.
+const TYPES: &[(&str, &str)] = &[
+    ("7z", "application/x-7z-compressed"),
+    ("avif", "image/avif"),
+    ("bmp", "image/bmp"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("ogv", "video/ogg"),
+    ("otf", "font/otf"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("toml", "application/toml"),
+    ("ttf", "font/ttf"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("zip", "application/zip"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{accept, from_path, MediaType};
+    use std::path::Path;
+
+    #[test]
+    fn media_type() {
+        let media: MediaType = "Text/HTML; Charset=\"UTF-8\"; q=\"a;\\\"b\" ; bad; =x"
+            .parse()
+            .unwrap();
+        assert_eq!((media.type_(), media.subtype()), ("text", "html"));
+        assert_eq!(media.charset(), Some("UTF-8"));
+        assert_eq!(media.param("Q"), Some("a;\"b"));
+        assert_eq!(media.params().count(), 2);
+        assert_eq!(media.to_string(), "text/html; charset=UTF-8; q=\"a;\\\"b\"");
+        assert!("text".parse::<MediaType>().is_err());
+        assert!("text/ html".parse::<MediaType>().is_err());
+    }
+
+    #[test]
+    fn accept_header() {
+        let header = accept(&[
+            ("application/json", 1.0),
+            ("text/html", 0.5),
+            ("text/*", 0.1234),
+            ("*/*", 0.0),
+            ("image/png", 2.0),
+        ]);
+        assert_eq!(
+            header,
+            "application/json, text/html;q=0.5, text/*;q=0.123, */*;q=0, image/png"
+        );
+    }
+
+    #[test]
+    fn guess_from_extension() {
+        assert_eq!(from_path(Path::new("photo.JPG")), "image/jpeg");
+        assert_eq!(
+            from_path(Path::new("dir.d/archive.tar")),
+            "application/x-tar"
+        );
+        assert_eq!(from_path(Path::new("README")), "application/octet-stream");
+    }
+}
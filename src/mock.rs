@@ -0,0 +1,182 @@
+use crate::{uri, Error, ErrorKind, Method, Middleware, Next, Request, Response};
+use std::{
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// A middleware which responds to requests with canned responses, without opening any connections.
+///
+/// Requests are matched against the mocks in the order they were added, and requests without a matching mock fail with [`io::ErrorKind::NotFound`].
+/// Clones of a transport share the list of received requests.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let transport = MockTransport::new()
+///     .mock(Mock::new(Method::GET, "example.org/a").respond("HTTP/1.1 500 Internal Server Error\r\n\r\n"))
+///     .mock(Mock::new(Method::GET, "example.org/b").fail(std::io::ErrorKind::TimedOut));
+/// let client = Client::new().with(transport.clone());
+///
+/// assert_eq!(client.get("example.org/a").unwrap().status, 500);
+/// assert!(client.get("example.org/b").unwrap_err().is_timeout());
+/// assert_eq!(transport.requests().len(), 2);
+/// ```
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    /// Mocks to match requests against, in order.
+    mocks: Vec<Mock>,
+    /// Requests which have been received, as they would have been sent.
+    requests: Arc<Mutex<Vec<String>>>,
+}
+impl MockTransport {
+    /// Create a new transport without any mocks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mock to the transport.
+    pub fn mock(self, mock: Mock) -> Self {
+        let mut transport = self;
+        transport.mocks.push(mock);
+        transport
+    }
+
+    /// The requests which have been received so far, formatted as they would have been sent.
+    #[must_use]
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+impl Middleware for MockTransport {
+    fn handle(&self, request: Request, _: Next) -> Result<Response, Error> {
+        let message = request.to_string();
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+        requests.push(message);
+        drop(requests);
+
+        let Some(mock) = self.mocks.iter().find(|mock| mock.matches(&request)) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no mock for {} {}", request.method, request.absolute_url()),
+            )
+            .into());
+        };
+        match &mock.outcome {
+            Ok(message) => {
+                let mut response = Response::parse(message).map_err(|s| {
+                    Error::new(
+                        ErrorKind::Protocol,
+                        io::Error::new(io::ErrorKind::InvalidData, s),
+                    )
+                })?;
+                if request.method == Method::HEAD {
+                    response.body.clear();
+                }
+                Ok(response)
+            }
+            Err(kind) => Err(io::Error::new(*kind, "mocked failure").into()),
+        }
+    }
+}
+
+/// A canned response for requests with a method and URL, and optionally some headers.
+///
+/// By default, the response is `200 OK` without a body.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct Mock {
+    /// Method of matching requests.
+    method: Method,
+    /// Absolute URL of matching requests.
+    url: String,
+    /// Headers which matching requests must have.
+    headers: Vec<(String, String)>,
+    /// The raw response message, or the kind of error to fail with.
+    outcome: Result<String, io::ErrorKind>,
+}
+impl Mock {
+    /// Create a new mock for requests with a method and URL.
+    pub fn new(method: Method, url: &str) -> Self {
+        Self {
+            method,
+            url: uri::resolve(url, ""),
+            headers: Vec::new(),
+            outcome: Ok("HTTP/1.1 200 OK\r\n\r\n".to_string()),
+        }
+    }
+
+    /// Only match requests which have a header with this value.
+    pub fn header(self, key: &str, value: &str) -> Self {
+        let mut mock = self;
+        mock.headers.push((key.to_string(), value.to_string()));
+        mock
+    }
+
+    /// Respond with a raw HTTP response message.
+    ///
+    /// The message is parsed like a response received from a server, so malformed messages result in an error.
+    pub fn respond(self, message: &str) -> Self {
+        let mut mock = self;
+        mock.outcome = Ok(message.to_string());
+        mock
+    }
+
+    /// Fail with an error of the given kind, e.g. [`io::ErrorKind::TimedOut`].
+    pub fn fail(self, kind: io::ErrorKind) -> Self {
+        let mut mock = self;
+        mock.outcome = Err(kind);
+        mock
+    }
+
+    /// Whether the mock applies to a request.
+    fn matches(&self, request: &Request) -> bool {
+        let headers = request
+            .headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .chain(request.option_headers())
+            .collect::<Vec<_>>();
+        self.method == request.method
+            && self.url == request.absolute_url()
+            && self.headers.iter().all(|(key, value)| {
+                headers
+                    .iter()
+                    .any(|(k, v)| k.eq_ignore_ascii_case(key) && v == value)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, Method, Mock, MockTransport, Request};
+    use std::io;
+
+    #[test]
+    fn match_headers() {
+        let transport = MockTransport::new()
+            .mock(
+                Mock::new(Method::GET, "http://example.org/")
+                    .header("Authorization", "Bearer token")
+                    .respond("HTTP/1.1 200 OK\r\n\r\nsecret"),
+            )
+            .mock(
+                Mock::new(Method::GET, "example.org").respond("HTTP/1.1 401 Unauthorized\r\n\r\n"),
+            )
+            .mock(Mock::new(Method::GET, "example.org/malformed").respond("garbage"));
+        let client = Client::new().with(transport.clone());
+
+        let request = Request::get("example.org").header("authorization", "Bearer token");
+        assert_eq!(client.send(request).unwrap().text(), "secret");
+        assert_eq!(client.get("example.org").unwrap().status, 401);
+        let error = client.get("example.org/malformed").unwrap_err();
+        assert_eq!(error.io_kind(), io::ErrorKind::InvalidData);
+        let error = client.post("example.org", "").unwrap_err();
+        assert_eq!(error.io_kind(), io::ErrorKind::NotFound);
+        assert_eq!(transport.requests().len(), 4);
+    }
+}
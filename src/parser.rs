@@ -0,0 +1,509 @@
+use crate::{
+    response::has_body, BorrowedResponse, ConnectionInfo, Headers, Response, StatusCode, Timings,
+};
+use std::{borrow::Cow, mem};
+
+/// The part of a message which is being parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum State {
+    /// The status line of a response or the request line of a request, which may be preceded by empty lines.
+    StatusLine,
+    /// The header lines, up to the empty line.
+    Headers,
+    /// The head is complete, and the rest of the message is the body.
+    Body,
+}
+
+/// A complete line of the head, split into its parts, which borrow from the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'l> {
+    /// An empty line before the start line, or the one which ends the head.
+    Empty,
+    /// The status line of a response.
+    Status {
+        version: &'l str,
+        status: StatusCode,
+        reason: &'l str,
+    },
+    /// The request line of a request.
+    Request {
+        method: &'l str,
+        target: &'l str,
+        version: &'l str,
+    },
+    /// A header line, with a trimmed value.
+    Header { name: &'l str, value: &'l str },
+    /// A folded line, which continues the value of the previous header.
+    Folded(&'l str),
+    /// An invalid line which is ignored.
+    Skipped,
+}
+
+/// A parser for the head of a response, which is fed the message as it is received.
+///
+/// Parsers created with [`Parser::request`] parse the head of a request instead.
+/// Lines may end with either CRLF or LF, and empty lines before the status line are ignored.
+/// Header values which are folded over multiple lines are joined with a space.
+///
+/// Responses are parsed leniently, as real servers deviate from the grammar: whitespace around the status code
+/// and before the colon of a header is ignored, and so are header lines which are not valid.
+/// In strict mode, see [`Parser::strict`], lines must end with CRLF, and folded or invalid header lines are rejected.
+#[derive(Debug)]
+pub(crate) struct Parser {
+    /// The part of the response which is being parsed.
+    state: State,
+    /// Bytes of the line which has not been completely received yet.
+    line: Vec<u8>,
+    /// The response parsed so far, without a body.
+    response: Response,
+    /// Name and value of each header line parsed so far.
+    headers: Vec<(String, String)>,
+    /// Number of header lines parsed so far.
+    count: usize,
+    /// Number of bytes of the head received so far.
+    size: usize,
+    /// Maximum number of header lines and maximum size of the head in bytes.
+    limits: (usize, usize),
+    /// The method and target of the request line, if the head of a request is parsed.
+    request: Option<(String, String)>,
+    /// Whether the head of a request is parsed.
+    is_request: bool,
+    /// Whether deviations from the grammar are rejected.
+    strict: bool,
+}
+
+/// The head of a request, see [`Parser::request`].
+#[derive(Debug)]
+pub(crate) struct RequestHead {
+    /// The method, e.g. `GET`.
+    pub(crate) method: String,
+    /// The target, e.g. `/index.html` or `http://example.org/`.
+    pub(crate) target: String,
+    /// The version, e.g. `HTTP/1.1`.
+    pub(crate) version: String,
+    /// The header fields.
+    pub(crate) headers: Headers,
+}
+impl Parser {
+    /// Create a parser for a new response.
+    pub(crate) fn new() -> Self {
+        Self {
+            state: State::StatusLine,
+            line: Vec::new(),
+            response: Response {
+                version: String::new(),
+                status: StatusCode::OK,
+                reason: String::new(),
+                headers: crate::Headers::new(),
+                body: Vec::new(),
+                history: Vec::new(),
+                connection: ConnectionInfo::default(),
+                timings: Timings::default(),
+            },
+            headers: Vec::new(),
+            count: 0,
+            size: 0,
+            limits: (usize::MAX, usize::MAX),
+            request: None,
+            is_request: false,
+            strict: false,
+        }
+    }
+
+    /// Create a parser for the head of a new request, which starts with a request line instead of a status line.
+    pub(crate) fn request() -> Self {
+        let mut parser = Self::new();
+        parser.is_request = true;
+        parser
+    }
+
+    /// Limit the number of header lines and the size of the head in bytes, so a head which never ends is rejected.
+    pub(crate) fn limit(self, headers: usize, size: usize) -> Self {
+        let mut parser = self;
+        parser.limits = (headers, size);
+        parser
+    }
+
+    /// Reject messages which deviate from the grammar, instead of parsing them leniently.
+    pub(crate) fn strict(self, strict: bool) -> Self {
+        let mut parser = self;
+        parser.strict = strict;
+        parser
+    }
+
+    /// The part of the response which is being parsed.
+    pub(crate) fn state(&self) -> State {
+        self.state
+    }
+
+    /// Parse the next received bytes, returning how many of them belong to the head.
+    ///
+    /// Once the head is complete, the rest of the bytes are not consumed, as they belong to the body.
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> Result<usize, &'static str> {
+        let mut consumed = 0;
+        while self.state != State::Body {
+            let rest = &bytes[consumed..];
+            let Some(end) = rest.iter().position(|&b| b == b'\n') else {
+                self.grow(rest.len())?;
+                self.line.extend(rest);
+                return Ok(bytes.len());
+            };
+            self.grow(end + 1)?;
+            self.line.extend(&rest[..end]);
+            consumed += end + 1;
+            let line = mem::take(&mut self.line);
+            self.parse_line(self.end_line(&line)?)?;
+        }
+        Ok(consumed)
+    }
+
+    /// Parse a complete response without copying it, so the parts of the response borrow from the message.
+    ///
+    /// The message is split into lines and parsed like when it is fed, except that it must be valid UTF-8,
+    /// and the body is the rest of the message.
+    pub(crate) fn parse_borrowed(
+        self,
+        message: &[u8],
+    ) -> Result<BorrowedResponse<'_>, &'static str> {
+        let mut parser = self;
+        let mut response = BorrowedResponse {
+            version: "",
+            status: StatusCode::OK,
+            reason: "",
+            headers: Vec::new(),
+            body: &[],
+        };
+        let mut rest = message;
+        while parser.state != State::Body && !rest.is_empty() {
+            let line = if let Some(end) = rest.iter().position(|&b| b == b'\n') {
+                parser.grow(end + 1)?;
+                let line = parser.end_line(&rest[..end])?;
+                rest = &rest[end + 1..];
+                line
+            } else {
+                // the line which has not been ended, like when finishing
+                parser.grow(rest.len())?;
+                let line = rest.strip_suffix(b"\r").unwrap_or(rest);
+                rest = &[];
+                line
+            };
+            let line = std::str::from_utf8(line).map_err(|_| "invalid message")?;
+            match parser.advance(line)? {
+                Line::Status {
+                    version,
+                    status,
+                    reason,
+                } => {
+                    (response.version, response.status, response.reason) =
+                        (version, status, reason);
+                }
+                Line::Header { name, value } => response.headers.push((name, Cow::Borrowed(value))),
+                Line::Folded(folded) => {
+                    if let Some((_, value)) = response.headers.last_mut() {
+                        let value = value.to_mut();
+                        value.push(' ');
+                        value.push_str(folded);
+                    }
+                }
+                Line::Request { .. } | Line::Empty | Line::Skipped => {}
+            }
+        }
+        if parser.state == State::StatusLine {
+            return Err("invalid message");
+        }
+        // the rest is the body, which is never present in informational, 204 and 304 responses
+        if has_body(response.status) {
+            response.body = rest;
+        }
+        Ok(response)
+    }
+
+    /// Finish parsing the head, e.g. when the connection was closed before the empty line after the headers.
+    pub(crate) fn finish(self) -> Result<Response, &'static str> {
+        let mut parser = self;
+        parser.complete()?;
+        let mut response = parser.response;
+        response.headers = parser.headers.into_iter().collect();
+        Ok(response)
+    }
+
+    /// Finish parsing the head of a request.
+    pub(crate) fn finish_request(self) -> Result<RequestHead, &'static str> {
+        let mut parser = self;
+        parser.complete()?;
+        let (method, target) = parser.request.ok_or("invalid message")?;
+        Ok(RequestHead {
+            method,
+            target,
+            version: parser.response.version,
+            headers: parser.headers.into_iter().collect(),
+        })
+    }
+
+    /// Parse the line which has not been ended, erroring if the start line was never received.
+    fn complete(&mut self) -> Result<(), &'static str> {
+        if !self.line.is_empty() {
+            let line = mem::take(&mut self.line);
+            self.parse_line(line.strip_suffix(b"\r").unwrap_or(&line))?;
+        }
+        if self.state == State::StatusLine {
+            return Err("invalid message");
+        }
+        Ok(())
+    }
+
+    /// Account for more bytes of the head, erroring if the head grows too large.
+    fn grow(&mut self, length: usize) -> Result<(), &'static str> {
+        self.size += length;
+        if self.size > self.limits.1 {
+            return Err(if self.is_request {
+                "request head too large"
+            } else {
+                "response head too large"
+            });
+        }
+        Ok(())
+    }
+
+    /// Remove the line break from the end of a line, which must be CRLF in strict mode.
+    fn end_line<'l>(&self, line: &'l [u8]) -> Result<&'l [u8], &'static str> {
+        match line.strip_suffix(b"\r") {
+            Some(line) => Ok(line),
+            None if self.strict => Err("invalid line ending"),
+            None => Ok(line),
+        }
+    }
+
+    /// Parse a complete line, without the line break.
+    fn parse_line(&mut self, line: &[u8]) -> Result<(), &'static str> {
+        let line = String::from_utf8_lossy(line);
+        match self.advance(&line)? {
+            Line::Status {
+                version,
+                status,
+                reason,
+            } => {
+                self.response.version = version.to_string();
+                self.response.status = status;
+                self.response.reason = reason.to_string();
+            }
+            Line::Request {
+                method,
+                target,
+                version,
+            } => {
+                self.request = Some((method.to_string(), target.to_string()));
+                self.response.version = version.to_string();
+            }
+            Line::Header { name, value } => {
+                self.headers.push((name.to_string(), value.to_string()));
+            }
+            Line::Folded(folded) => {
+                if let Some((_, value)) = self.headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(folded);
+                }
+            }
+            Line::Empty | Line::Skipped => {}
+        }
+        Ok(())
+    }
+
+    /// Split a complete line, without the line break, into its parts, and move on to the next part of the message.
+    fn advance<'l>(&mut self, line: &'l str) -> Result<Line<'l>, &'static str> {
+        let parsed = match self.state {
+            State::StatusLine if line.is_empty() => Line::Empty,
+            State::StatusLine if self.is_request => {
+                // Method SP Request-Target SP HTTP-Version
+                let mut parts = line.split(' ');
+                let (Some(method), Some(target), Some(version), None) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err("invalid request line");
+                };
+                if method.is_empty() || !method.bytes().all(is_token) {
+                    return Err("invalid method");
+                }
+                if target.is_empty() {
+                    return Err("invalid request line");
+                }
+                validate_version(version)?;
+                self.state = State::Headers;
+                Line::Request {
+                    method,
+                    target,
+                    version,
+                }
+            }
+            State::StatusLine => {
+                // HTTP-Version SP Status-Code [SP Reason-Phrase]
+                let lenient = !self.strict;
+                let line = if lenient { line.trim() } else { line };
+                let (version, rest) = line.split_once(' ').ok_or("invalid message")?;
+                validate_version(version)?;
+                let rest = if lenient { rest.trim_start() } else { rest };
+                let (status, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+                let reason = if lenient { reason.trim_start() } else { reason };
+                let status = status.parse().map_err(|_| "invalid status")?;
+                self.state = State::Headers;
+                Line::Status {
+                    version,
+                    status,
+                    reason,
+                }
+            }
+            State::Headers if line.is_empty() => {
+                self.state = State::Body;
+                Line::Empty
+            }
+            State::Headers if line.starts_with([' ', '\t']) => {
+                match self.count {
+                    _ if self.strict => return Err("invalid header"),
+                    0 if self.is_request => return Err("invalid header"),
+                    // a folded line without a header to continue is ignored in responses
+                    0 => Line::Skipped,
+                    _ => Line::Folded(line.trim()),
+                }
+            }
+            State::Headers => {
+                let lenient = !self.strict && !self.is_request;
+                let (name, value) = line.split_once(':').unwrap_or((line, ""));
+                let name = if lenient { name.trim_end() } else { name };
+                if name.is_empty() || !name.bytes().all(is_token) || !line.contains(':') {
+                    // servers which send broken header lines are better served by ignoring them
+                    if lenient {
+                        return Ok(Line::Skipped);
+                    }
+                    return Err("invalid header");
+                }
+                if self.count == self.limits.0 {
+                    return Err("too many headers");
+                }
+                self.count += 1;
+                Line::Header {
+                    name,
+                    value: value.trim(),
+                }
+            }
+            State::Body => Line::Empty,
+        };
+        Ok(parsed)
+    }
+}
+
+/// Check that a version has the form `HTTP/d.d`.
+pub(crate) fn validate_version(version: &str) -> Result<(), &'static str> {
+    let digits = version.strip_prefix("HTTP/").map(str::as_bytes);
+    if !matches!(digits, Some([major, b'.', minor]) if major.is_ascii_digit() && minor.is_ascii_digit())
+    {
+        return Err("invalid version");
+    }
+    Ok(())
+}
+
+/// Whether a byte can be part of a token, such as a header name.
+pub(crate) fn is_token(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Parser, State};
+
+    #[test]
+    fn incremental() {
+        let message =
+            b"\r\nHTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Folded: a\r\n b\r\n\r\nbody";
+        let mut parser = Parser::new();
+        for (i, byte) in message.iter().enumerate() {
+            let consumed = parser.feed(&[*byte]).unwrap();
+            // the body starts after the empty line
+            assert_eq!(consumed, usize::from(i < message.len() - 4));
+        }
+        assert_eq!(parser.state(), State::Body);
+        let response = parser.finish().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.headers.content_type(), Some("text/plain"));
+        assert_eq!(response.headers.get("X-Folded"), Some("a b"));
+        assert_eq!(response.body, b"");
+
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"HTTP/1.1 204 No").unwrap(), 15);
+        assert_eq!(parser.state(), State::StatusLine);
+        assert_eq!(parser.feed(b" Content\r\n\r\nnext").unwrap(), 12);
+        assert_eq!(parser.finish().unwrap().reason, "No Content");
+
+        let mut parser = Parser::new().limit(1, 64);
+        assert_eq!(parser.feed(b"HTTP/1.1 200 OK\r\nA: 1\r\n").unwrap(), 23);
+        assert_eq!(parser.feed(b"B: 2\r\n"), Err("too many headers"));
+        let mut parser = Parser::new().limit(1, 64);
+        assert_eq!(parser.feed(&[b'x'; 65]), Err("response head too large"));
+    }
+
+    #[test]
+    fn lenient() {
+        let message = b"\n  HTTP/1.1  404  Not-Found (v2)\nContent-Type : text/plain\nbroken line\nX-Folded: a\n\tb\n\nbody";
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(message).unwrap(), message.len() - 4);
+        let response = parser.finish().unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.reason, "Not-Found (v2)");
+        assert_eq!(response.headers.content_type(), Some("text/plain"));
+        assert_eq!(response.headers.get("X-Folded"), Some("a b"));
+        assert_eq!(response.headers.len(), 2);
+
+        // strict parsers reject the deviations
+        for (message, error) in [
+            ("HTTP/1.1 200 OK\n", "invalid line ending"),
+            ("HTTP/1.1  200 OK\r\n", "invalid status"),
+            ("HTTP/1.1 200 OK\r\nA: 1\r\n 2\r\n", "invalid header"),
+            ("HTTP/1.1 200 OK\r\nA : 1\r\n", "invalid header"),
+            ("HTTP/1.1 200 OK\r\nbroken line\r\n", "invalid header"),
+        ] {
+            let mut parser = Parser::new().strict(true);
+            assert_eq!(parser.feed(message.as_bytes()), Err(error));
+        }
+        assert_eq!(
+            Parser::new()
+                .strict(true)
+                .parse_borrowed(b"HTTP/1.1 200 OK\r\nA: 1\r\n 2\r\n\r\n"),
+            Err("invalid header")
+        );
+        assert_eq!(
+            Parser::new()
+                .strict(true)
+                .parse_borrowed(b"HTTP/1.1 200 OK\n\n"),
+            Err("invalid line ending")
+        );
+        let mut parser = Parser::new().strict(true);
+        let message = b"HTTP/1.1 404 Not-Found (v2)\r\nA: 1\r\n\r\n";
+        assert_eq!(parser.feed(message).unwrap(), message.len());
+        assert_eq!(parser.finish().unwrap().reason, "Not-Found (v2)");
+    }
+
+    #[test]
+    fn request() {
+        let mut parser = Parser::request();
+        let message = b"GET /a?b HTTP/1.1\r\nHost: example.org\r\n\r\nbody";
+        assert_eq!(parser.feed(message).unwrap(), message.len() - 4);
+        let head = parser.finish_request().unwrap();
+        assert_eq!(
+            (head.method.as_str(), head.target.as_str()),
+            ("GET", "/a?b")
+        );
+        assert_eq!(head.version, "HTTP/1.1");
+        assert_eq!(head.headers.get("Host"), Some("example.org"));
+
+        for (line, error) in [
+            ("GET /\r\n", "invalid request line"),
+            ("GET  / HTTP/1.1\r\n", "invalid request line"),
+            ("G(T / HTTP/1.1\r\n", "invalid method"),
+            ("GET / HTTP/x\r\n", "invalid version"),
+        ] {
+            assert_eq!(Parser::request().feed(line.as_bytes()), Err(error));
+        }
+        // a response is not a request
+        let mut parser = Parser::request();
+        assert_eq!(parser.feed(b"HTTP/1.1 200 OK\r\n"), Err("invalid method"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Percent-encoding and decoding of the components of URLs and of form data (RFC 3986 section 2.1).
+//!
+//! Each context has its own set of characters which are left as-is, so the encoded text can not change the structure
+//! of the URL it is put in, e.g. a `/` in a path segment or a `&` in a query value is always encoded.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use request::percent;
+//! let url = format!(
+//!     "example.org/files/{}?name={}",
+//!     percent::encode_path_segment("a/b c.txt"),
+//!     percent::encode_query_component("x&y=z"),
+//! );
+//! assert_eq!(url, "example.org/files/a%2Fb%20c.txt?name=x%26y%3Dz");
+//! assert_eq!(percent::decode("a%2Fb%20c.txt"), "a/b c.txt");
+//! ```
+
+/// Encode a single segment of a path, such as a file name, so it can not add or remove segments.
+///
+/// Unreserved characters, sub-delimiters, `:` and `@` are left as-is.
+#[must_use]
+pub fn encode_path_segment(segment: &str) -> String {
+    encode(segment, b"-._~!$&'()*+,;=:@")
+}
+
+/// Encode a name or a value in a query, so it can not add parameters or end the query.
+///
+/// Unreserved characters and the delimiters which have no meaning in a query, such as `/` and `:`, are left as-is,
+/// but `&`, `=`, `+`, `#` and `%` are always encoded.
+#[must_use]
+pub fn encode_query_component(component: &str) -> String {
+    encode(component, b"-._~!$'()*,;:@/?")
+}
+
+/// Encode a name or a value as form data (`application/x-www-form-urlencoded`), where spaces are encoded as `+`.
+///
+/// Only letters, digits and `*-._` are left as-is, like browsers do.
+#[must_use]
+pub fn encode_form(component: &str) -> String {
+    encode(component, b"-._*").replace("%20", "+")
+}
+
+/// Encode text so that only unreserved characters are left as-is, as required by AWS Signature Version 4.
+#[cfg(feature = "sigv4")]
+pub(crate) fn encode_unreserved(text: &str) -> String {
+    encode(text, b"-._~")
+}
+
+/// Decode percent-encoded text, leaving invalid escapes as-is and replacing invalid UTF-8.
+#[must_use]
+pub fn decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        decoded.push(escaped.unwrap_or(bytes[i]));
+        i += if escaped.is_some() { 3 } else { 1 };
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Decode form data (`application/x-www-form-urlencoded`), where `+` is a space.
+#[must_use]
+pub fn decode_form(encoded: &str) -> String {
+    decode(&encoded.replace('+', " "))
+}
+
+/// Normalize percent-encoded text (RFC 3986 section 6.2.2): escapes of unreserved characters are decoded,
+/// and the hexadecimal digits of other escapes are uppercased.
+pub(crate) fn normalize(encoded: &str) -> String {
+    let mut normalized = String::with_capacity(encoded.len());
+    let mut rest = encoded;
+    while let Some(i) = rest.find('%') {
+        normalized.push_str(&rest[..i]);
+        let escape = &rest[i..];
+        match escape
+            .get(1..3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(char::from(byte));
+            }
+            Some(_) => normalized.push_str(&escape[..3].to_ascii_uppercase()),
+            None => {
+                normalized.push('%');
+                rest = &escape[1..];
+                continue;
+            }
+        }
+        rest = &escape[3..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// Encode every byte except letters, digits and the characters which are allowed as-is in the context.
+fn encode(text: &str, allowed: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || allowed.contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.extend([
+                '%',
+                char::from(HEX[usize::from(byte >> 4)]),
+                char::from(HEX[usize::from(byte & 15)]),
+            ]);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contexts() {
+        let text = "a b/c?d&e=f+g#h%i~ø";
+        assert_eq!(
+            encode_path_segment(text),
+            "a%20b%2Fc%3Fd&e=f+g%23h%25i~%C3%B8"
+        );
+        assert_eq!(
+            encode_query_component(text),
+            "a%20b/c?d%26e%3Df%2Bg%23h%25i~%C3%B8"
+        );
+        assert_eq!(
+            encode_form(text),
+            "a+b%2Fc%3Fd%26e%3Df%2Bg%23h%25i%7E%C3%B8"
+        );
+        for encoded in [encode_path_segment(text), encode_query_component(text)] {
+            assert_eq!(decode(&encoded), text);
+        }
+        assert_eq!(decode_form(&encode_form(text)), text);
+        assert_eq!(decode("100%25 %zz %4"), "100% %zz %4");
+        assert_eq!(normalize("%7e%2f%C3%b8%zz%4"), "~%2F%C3%B8%zz%4");
+    }
+}
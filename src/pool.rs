@@ -0,0 +1,535 @@
+use crate::ReadWrite;
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A pool of connections, which are kept open after a response has been received so later requests to the same server can reuse them.
+///
+/// A connection is returned to the pool once the body of its response has been read to the end, unless the server
+/// or the request asked to close it. Connections are told apart by the scheme, host and port of the server, by the proxy,
+/// and by the resolver overrides, local address and interface of the request. Requests with a custom connector are not pooled.
+/// Clones of a pool share the same connections.
+///
+/// Idle connections are closed once they have been idle for longer than the idle timeout, or have been open for longer
+/// than the maximum age, by a background thread which runs while there are idle connections.
+/// Connections which expired or which the server closed are dropped before they would be reused.
+/// If the pool holds as many connections as it allows, in total or to the server, requests wait for a connection
+/// to be returned or closed, until their total timeout or deadline passes.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::time::Duration;
+/// let pool = Pool::new()
+///     .max_idle_per_host(4)
+///     .max_connections_per_host(16)
+///     .max_connections(64)
+///     .idle_timeout(Duration::from_secs(30))
+///     .max_age(Duration::from_secs(300));
+/// let client = Client::new().pool(pool);
+/// ```
+#[must_use]
+#[derive(Clone)]
+pub struct Pool {
+    /// Maximum number of idle connections kept for each server.
+    max_idle_per_host: usize,
+    /// Maximum number of connections to each server which are open at once, both idle and in use.
+    max_per_host: usize,
+    /// Maximum number of connections which are open at once, both idle and in use.
+    max_connections: usize,
+    /// How long a connection may be idle before it is closed.
+    idle_timeout: Duration,
+    /// How long a connection may be open before it is closed instead of reused.
+    max_age: Duration,
+    /// The connections, which are shared with clones of the pool.
+    shared: Arc<Shared>,
+}
+
+/// The connections of a pool, and a way to wait for one to be returned.
+#[derive(Default)]
+struct Shared {
+    /// The idle connections, and the number of open connections.
+    state: Mutex<State>,
+    /// Notified when a connection is returned or closed.
+    returned: Condvar,
+}
+
+/// The idle connections of a pool, by server.
+#[derive(Default)]
+struct State {
+    /// Idle connections to each server, with the times they were opened and returned, oldest first.
+    idle: HashMap<String, Vec<Idle>>,
+    /// Number of connections which are open, both idle and in use.
+    open: usize,
+    /// Number of connections to each server which are open, both idle and in use.
+    open_per_host: HashMap<String, usize>,
+    /// Whether the thread which closes connections after the idle timeout is running.
+    reaping: bool,
+}
+
+/// An idle connection.
+struct Idle {
+    /// The connection to the server.
+    stream: Box<dyn ReadWrite>,
+    /// When the connection was opened.
+    opened: Instant,
+    /// When the connection was returned.
+    returned: Instant,
+}
+impl Idle {
+    /// Whether the connection has been idle for longer than the idle timeout, or open for longer than the maximum age.
+    fn is_expired(&self, now: Instant, idle_timeout: Duration, max_age: Duration) -> bool {
+        now - self.returned >= idle_timeout || now - self.opened >= max_age
+    }
+}
+
+impl Pool {
+    /// Create a pool which keeps up to 8 idle connections for each server for 90 seconds, without limiting how many are open.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many idle connections are kept for each server, after which returned connections are closed.
+    ///
+    /// A number of 0 keeps no connections at all.
+    pub fn max_idle_per_host(self, max: usize) -> Self {
+        let mut pool = self;
+        pool.max_idle_per_host = max;
+        pool
+    }
+
+    /// Set how many connections to each server may be open at once, both idle and in use.
+    ///
+    /// Once the limit is reached, requests to the server wait until one of its connections is returned or closed.
+    /// A number of 0 is treated as 1.
+    pub fn max_connections_per_host(self, max: usize) -> Self {
+        let mut pool = self;
+        pool.max_per_host = max.max(1);
+        pool
+    }
+
+    /// Set how many connections may be open at once, both idle and in use, across all servers.
+    ///
+    /// Once the limit is reached, the oldest idle connection is closed to make room for a connection to another server,
+    /// and if there is none, requests wait until a connection is returned or closed.
+    /// A number of 0 is treated as 1.
+    pub fn max_connections(self, max: usize) -> Self {
+        let mut pool = self;
+        pool.max_connections = max.max(1);
+        pool
+    }
+
+    /// Set how long a connection may be idle before it is closed.
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        let mut pool = self;
+        pool.idle_timeout = timeout;
+        pool
+    }
+
+    /// Set how long a connection may be open before it is closed, instead of being reused, e.g. so that
+    /// requests are spread over servers whose address changed, or which sit behind a load balancer.
+    ///
+    /// Connections which are in use when they reach the age are closed once their response has been received.
+    pub fn max_age(self, age: Duration) -> Self {
+        let mut pool = self;
+        pool.max_age = age;
+        pool
+    }
+
+    /// Take an idle connection to a server, or make room for a new connection if there is none,
+    /// waiting until the deadline for a connection to be returned if the pool is full.
+    pub(crate) fn checkout(
+        &self,
+        key: &str,
+        deadline: Option<Instant>,
+    ) -> Result<Lease, io::Error> {
+        let mut state = self.lock();
+        loop {
+            // connections which expired or which the server closed are dropped
+            let now = Instant::now();
+            let connections = state.idle.remove(key).unwrap_or_default();
+            let before = connections.len();
+            let mut connections = (connections.into_iter())
+                .filter(|idle| {
+                    !idle.is_expired(now, self.idle_timeout, self.max_age)
+                        && !idle.stream.is_closed()
+                })
+                .collect::<Vec<_>>();
+            state.close(key, before - connections.len());
+            let idle = connections.pop();
+            if !connections.is_empty() {
+                state.idle.insert(key.to_string(), connections);
+            }
+            if let Some(idle) = idle {
+                debug!("reusing a connection to {key}");
+                return Ok(self.lease(key, Some(idle.stream), idle.opened));
+            }
+
+            // make room for a new connection by closing the oldest idle connection to another server
+            let to_host = state.open_per_host.get(key).copied().unwrap_or_default();
+            if to_host < self.max_per_host && state.open >= self.max_connections {
+                let oldest = (state.idle.iter())
+                    .filter_map(|(key, connections)| {
+                        Some((key.clone(), connections.first()?.returned))
+                    })
+                    .min_by_key(|&(_, since)| since);
+                if let Some((oldest, _)) = oldest {
+                    state.close_oldest(&oldest);
+                }
+            }
+            if to_host < self.max_per_host && state.open < self.max_connections {
+                state.open += 1;
+                *state.open_per_host.entry(key.to_string()).or_default() += 1;
+                return Ok(self.lease(key, None, now));
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            if timeout.is_zero() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for a connection from the pool",
+                ));
+            }
+            debug!("waiting for a connection from the pool for {key}");
+            state = (self.shared.returned.wait_timeout(state, timeout))
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// A lease on a connection which is counted as open, and which was opened at a time.
+    fn lease(&self, key: &str, idle: Option<Box<dyn ReadWrite>>, opened: Instant) -> Lease {
+        Lease {
+            pool: self.clone(),
+            key: key.to_string(),
+            idle,
+            opened,
+            reusable: false,
+        }
+    }
+
+    /// Keep a connection which is no longer used, unless it is too old, or the server already has as many idle
+    /// connections as are kept.
+    fn checkin(&self, key: &str, stream: Box<dyn ReadWrite>, opened: Instant) {
+        let mut state = self.lock();
+        let returned = Instant::now();
+        if returned - opened >= self.max_age {
+            debug!("closing a connection to {key} which reached the maximum age");
+            state.close(key, 1);
+            self.shared.returned.notify_all();
+            return;
+        }
+        let connections = state.idle.entry(key.to_string()).or_default();
+        connections.push(Idle {
+            stream,
+            opened,
+            returned,
+        });
+        if connections.len() > self.max_idle_per_host {
+            state.close_oldest(key);
+        }
+        if !state.reaping && !state.idle.is_empty() {
+            state.reaping = true;
+            let shared = Arc::downgrade(&self.shared);
+            let (idle_timeout, max_age) = (self.idle_timeout, self.max_age);
+            thread::spawn(move || reap(&shared, idle_timeout, max_age));
+        }
+        // requests to any server may be waiting, for room in the pool or for this connection
+        self.shared.returned.notify_all();
+    }
+
+    /// Lock the connections of the pool.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        (self.shared.state.lock()).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Default for Pool {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 8,
+            max_per_host: usize::MAX,
+            max_connections: usize::MAX,
+            idle_timeout: Duration::from_secs(90),
+            max_age: Duration::MAX,
+            shared: Arc::default(),
+        }
+    }
+}
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Pool")
+            .field("max_idle_per_host", &self.max_idle_per_host)
+            .field("max_connections_per_host", &self.max_per_host)
+            .field("max_connections", &self.max_connections)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("max_age", &self.max_age)
+            .field("open", &state.open)
+            .finish_non_exhaustive()
+    }
+}
+
+impl State {
+    /// Close the oldest idle connection to a server.
+    fn close_oldest(&mut self, key: &str) {
+        let Some(connections) = self.idle.get_mut(key) else {
+            return;
+        };
+        let closed = usize::from(!connections.is_empty());
+        if closed > 0 {
+            connections.remove(0);
+        }
+        if connections.is_empty() {
+            self.idle.remove(key);
+        }
+        self.close(key, closed);
+    }
+
+    /// Count a number of connections to a server as closed.
+    fn close(&mut self, key: &str, closed: usize) {
+        if closed == 0 {
+            return;
+        }
+        self.open -= closed;
+        if let Some(open) = self.open_per_host.get_mut(key) {
+            *open -= closed;
+            if *open == 0 {
+                self.open_per_host.remove(key);
+            }
+        }
+    }
+}
+
+/// Close the connections of a pool which have been idle for longer than the timeout, or open for longer than the maximum age,
+/// until there are no idle connections left.
+fn reap(shared: &Weak<Shared>, idle_timeout: Duration, max_age: Duration) {
+    let interval = (idle_timeout.min(max_age) / 2).max(Duration::from_millis(10));
+    loop {
+        thread::sleep(interval);
+        // the pool may have been dropped in the meantime
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let mut state = (shared.state.lock()).unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let mut closed = Vec::new();
+        state.idle.retain(|key, connections| {
+            let before = connections.len();
+            connections.retain(|idle| !idle.is_expired(now, idle_timeout, max_age));
+            if connections.len() < before {
+                debug!(
+                    "closing {} expired connections to {key}",
+                    before - connections.len()
+                );
+                closed.push((key.clone(), before - connections.len()));
+            }
+            !connections.is_empty()
+        });
+        for (key, count) in &closed {
+            state.close(key, *count);
+        }
+        if !closed.is_empty() {
+            shared.returned.notify_all();
+        }
+        if state.idle.is_empty() {
+            state.reaping = false;
+            return;
+        }
+    }
+}
+
+/// A connection of a pool which is in use, which is counted as open until it is returned or closed.
+pub(crate) struct Lease {
+    /// The pool the connection belongs to.
+    pool: Pool,
+    /// The server the connection is connected to.
+    key: String,
+    /// The idle connection which was taken from the pool, if any.
+    idle: Option<Box<dyn ReadWrite>>,
+    /// When the connection was opened.
+    opened: Instant,
+    /// Whether the connection can be returned once the response has been received.
+    reusable: bool,
+}
+impl Lease {
+    /// Take the idle connection which was taken from the pool, if there was one.
+    pub(crate) fn take(&mut self) -> Option<Box<dyn ReadWrite>> {
+        self.idle.take()
+    }
+
+    /// Mark the connection as reusable once the response has been received.
+    pub(crate) fn set_reusable(&mut self, reusable: bool) {
+        self.reusable = reusable;
+    }
+
+    /// Whether the connection can be returned once the response has been received.
+    pub(crate) fn is_reusable(&self) -> bool {
+        self.reusable
+    }
+
+    /// Return the connection to the pool.
+    pub(crate) fn release(self, stream: Box<dyn ReadWrite>) {
+        let mut lease = self;
+        // the connection is still counted as open, now as an idle one, so the key is taken to not count it as closed
+        let key = std::mem::take(&mut lease.key);
+        lease.pool.checkin(&key, stream, lease.opened);
+    }
+}
+impl Drop for Lease {
+    /// Count the connection as closed, unless it was returned.
+    fn drop(&mut self) {
+        if self.key.is_empty() {
+            return;
+        }
+        let mut state = self.pool.lock();
+        state.close(&self.key, 1);
+        self.pool.shared.returned.notify_all();
+    }
+}
+impl fmt::Debug for Lease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, Pool, Request};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Serve responses on the connections which are accepted, returning the number of requests served on each connection.
+    fn serve(listener: TcpListener, connections: usize) -> thread::JoinHandle<Vec<usize>> {
+        thread::spawn(move || {
+            let mut served = vec![];
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut count = 0;
+                let mut buffer = [0; 4096];
+                // the connection ends when the client closes it
+                while stream.read(&mut buffer).unwrap_or(0) > 0 {
+                    count += 1;
+                    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    stream.write_all(response).unwrap();
+                }
+                served.push(count);
+            }
+            served
+        })
+    }
+
+    #[test]
+    fn reused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = serve(listener, 1);
+        let pool = Pool::new().idle_timeout(Duration::from_millis(100));
+        let client = Client::new().pool(pool.clone());
+        for _ in 0..3 {
+            assert_eq!(client.get(&url).unwrap().body, b"ok");
+        }
+        // a streamed body which is read to the end returns the connection too
+        let (_, mut body) = Request::get(&url).pool(&pool).send_streaming().unwrap();
+        body.read_to_end(&mut vec![]).unwrap();
+        drop(body);
+        assert_eq!(client.get(&url).unwrap().body, b"ok");
+
+        // the idle connection is closed after the idle timeout
+        let started = Instant::now();
+        assert_eq!(server.join().unwrap(), [5]);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn max_age() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = serve(listener, 2);
+        let pool = Pool::new().max_age(Duration::from_millis(200));
+        let client = Client::new().pool(pool);
+        assert_eq!(client.get(&url).unwrap().body, b"ok");
+        assert_eq!(client.get(&url).unwrap().body, b"ok");
+
+        // a connection which is too old is not reused, even though it has not been idle for long
+        thread::sleep(Duration::from_millis(250));
+        assert_eq!(client.get(&url).unwrap().body, b"ok");
+        // and is closed once it gets too old while idle
+        let started = Instant::now();
+        assert_eq!(server.join().unwrap(), [2, 1]);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn limits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = serve(listener, 3);
+
+        // a streamed body which is not read holds on to its connection, so the next request waits for it
+        let pool = Pool::new().max_connections(1);
+        let (_, body) = Request::get(&url).pool(&pool).send_streaming().unwrap();
+        let waiting = Request::get(&url)
+            .pool(&pool)
+            .timeout(Duration::from_millis(100));
+        assert!(waiting.send().unwrap_err().is_timeout());
+        drop(body);
+        assert_eq!(Request::get(&url).pool(&pool).send().unwrap().body, b"ok");
+        // the idle connection is closed with the pool
+        drop(pool);
+
+        // no connections are kept if no idle connections are allowed
+        let pool = Pool::new().max_idle_per_host(0);
+        assert_eq!(Request::get(&url).pool(&pool).send().unwrap().body, b"ok");
+        assert_eq!(server.join().unwrap(), [1, 1, 1]);
+    }
+
+    #[test]
+    fn per_host_limit() {
+        let url =
+            |listener: &TcpListener| format!("localhost:{}", listener.local_addr().unwrap().port());
+        let (first, second) = (
+            TcpListener::bind("127.0.0.1:0").unwrap(),
+            TcpListener::bind("127.0.0.1:0").unwrap(),
+        );
+        let (first_url, second_url) = (url(&first), url(&second));
+        let servers = [serve(first, 1), serve(second, 1)];
+
+        // a server which has as many connections as allowed makes its requests wait, but not those to other servers
+        let pool = Pool::new().max_connections_per_host(1);
+        let (_, mut body) = Request::get(&first_url)
+            .pool(&pool)
+            .send_streaming()
+            .unwrap();
+        let waiting = Request::get(&first_url)
+            .pool(&pool)
+            .timeout(Duration::from_millis(100));
+        assert!(waiting.send().unwrap_err().is_timeout());
+        assert_eq!(
+            Request::get(&second_url).pool(&pool).send().unwrap().body,
+            b"ok"
+        );
+        body.read_to_end(&mut vec![]).unwrap();
+        drop(body);
+        assert_eq!(
+            Request::get(&first_url).pool(&pool).send().unwrap().body,
+            b"ok"
+        );
+        drop(pool);
+        let served = servers.map(|server| server.join().unwrap());
+        assert_eq!(served, [[2], [1]]);
+    }
+}
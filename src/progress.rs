@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// A hook which is notified as the body of a request is sent and the body of its response is received.
+///
+/// Any closure taking a [`Progress`] can be used as a progress hook.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let report = |progress: Progress| println!("sent {} of {} bytes", progress.sent, progress.send_total);
+/// let request = Request::post("example.org", "hello").on_progress(&report);
+/// ```
+pub trait OnProgress: Send + Sync {
+    /// Handle an update of the progress of a transfer.
+    fn on_progress(&self, progress: Progress);
+}
+impl<F: Fn(Progress) + Send + Sync> OnProgress for F {
+    fn on_progress(&self, progress: Progress) {
+        self(progress);
+    }
+}
+impl fmt::Debug for dyn OnProgress + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnProgress")
+    }
+}
+
+/// The progress of a request, in bytes of the request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// Bytes of the request body which have been sent.
+    pub sent: u64,
+    /// Size of the request body.
+    pub send_total: u64,
+    /// Bytes of the response body which have been received.
+    pub received: u64,
+    /// Size of the response body, if the server announced it with a `Content-Length` header.
+    pub receive_total: Option<u64>,
+}
@@ -0,0 +1,116 @@
+use std::{env, net::IpAddr, sync::LazyLock};
+
+/// Proxy configuration read from the environment.
+#[derive(Debug, Default)]
+struct Environment {
+    http: Option<String>,
+    https: Option<String>,
+    all: Option<String>,
+    no_proxy: String,
+}
+
+/// Proxy configuration read from the environment, once.
+///
+/// Follows curl: `http_proxy` is only read in lowercase (the uppercase variant can be set by CGI servers from request headers),
+/// while the other variables are read in lowercase or uppercase.
+static ENVIRONMENT: LazyLock<Environment> = LazyLock::new(|| {
+    let var = |name: &str| {
+        env::var(name.to_lowercase())
+            .or_else(|_| env::var(name))
+            .ok()
+            .filter(|v| !v.is_empty())
+    };
+    Environment {
+        http: env::var("http_proxy").ok().filter(|v| !v.is_empty()),
+        https: var("HTTPS_PROXY"),
+        all: var("ALL_PROXY"),
+        no_proxy: var("NO_PROXY").unwrap_or_default(),
+    }
+});
+
+/// The proxy to use for a request to a host according to the `http_proxy`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` environment variables.
+pub(crate) fn from_env(scheme: &str, host: &str) -> Option<&'static str> {
+    let environment = &*ENVIRONMENT;
+    if bypass(&environment.no_proxy, host) {
+        return None;
+    }
+    let proxy = match scheme {
+        "https" => environment.https.as_ref(),
+        _ => environment.http.as_ref(),
+    };
+    proxy.or(environment.all.as_ref()).map(String::as_str)
+}
+
+/// Whether a host is excluded from proxying by a `NO_PROXY` list.
+///
+/// The list is comma-separated, and each entry is either `*` (matching every host), a domain (matching itself and its subdomains),
+/// an IP address, or an IP range in CIDR notation.
+fn bypass(no_proxy: &str, host: &str) -> bool {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    let address = host.parse::<IpAddr>().ok();
+    no_proxy
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            match (address, entry.split_once('/')) {
+                // ip range
+                (Some(address), Some((network, bits))) => {
+                    let (Ok(network), Ok(bits)) = (network.parse::<IpAddr>(), bits.parse::<u32>())
+                    else {
+                        return false;
+                    };
+                    in_network(address, network, bits)
+                }
+                // ip address
+                (Some(address), None) => entry.parse::<IpAddr>() == Ok(address),
+                // domain, with an optional leading dot
+                (None, _) => {
+                    let domain = entry.trim_start_matches('.').to_ascii_lowercase();
+                    host == domain || host.ends_with(&format!(".{domain}"))
+                }
+            }
+        })
+}
+
+/// Whether an address is in a network with a prefix of the given number of bits.
+fn in_network(address: IpAddr, network: IpAddr, bits: u32) -> bool {
+    let (address, network, width) = match (address, network) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => (u128::from(a.to_bits()), u128::from(n.to_bits()), 32),
+        (IpAddr::V6(a), IpAddr::V6(n)) => (a.to_bits(), n.to_bits(), 128),
+        _ => return false,
+    };
+    if bits > width {
+        return false;
+    }
+    let mask = u128::MAX.checked_shl(width - bits).unwrap_or(0) & (u128::MAX >> (128 - width));
+    address & mask == network & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bypass;
+
+    #[test]
+    fn no_proxy() {
+        let no_proxy = "example.org, .internal,192.168.1.10,10.0.0.0/8,fd00::/8";
+        assert!(bypass(no_proxy, "example.org"));
+        assert!(bypass(no_proxy, "www.Example.org"));
+        assert!(!bypass(no_proxy, "notexample.org"));
+        assert!(bypass(no_proxy, "backend.internal"));
+        assert!(bypass(no_proxy, "192.168.1.10"));
+        assert!(!bypass(no_proxy, "192.168.1.11"));
+        assert!(bypass(no_proxy, "10.20.30.40"));
+        assert!(!bypass(no_proxy, "11.0.0.1"));
+        assert!(bypass(no_proxy, "[fd12::1]"));
+        assert!(!bypass(no_proxy, "fe80::1"));
+        assert!(bypass("*", "anything"));
+        assert!(!bypass("", "anything"));
+    }
+}
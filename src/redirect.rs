@@ -0,0 +1,104 @@
+use crate::{uri, StatusCode};
+
+/// A policy for following redirects.
+///
+/// # Examples
+///
+/// Stop following redirects which lead to another host:
+/// ```rust
+/// # use request::*;
+/// let policy = RedirectPolicy::Custom(|redirect| redirect.hops < 4 && !redirect.is_cross_host());
+/// let request = Request::get("example.org").redirect(policy);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum RedirectPolicy {
+    /// Never follow redirects, returning the redirect response instead.
+    None,
+    /// Follow up to some number of redirects, erroring if more are issued.
+    Limited(usize),
+    /// Decide whether to follow each redirect.
+    ///
+    /// If the function returns `false`, the redirect response is returned.
+    Custom(fn(&Redirect) -> bool),
+}
+impl RedirectPolicy {
+    /// Decide whether to follow a redirect.
+    ///
+    /// Returns `None` if the redirect limit has been exceeded.
+    pub(crate) fn follow(&self, redirect: &Redirect) -> Option<bool> {
+        match self {
+            RedirectPolicy::None => Some(false),
+            RedirectPolicy::Limited(max) => (redirect.hops < *max).then_some(true),
+            RedirectPolicy::Custom(f) => Some(f(redirect)),
+        }
+    }
+}
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::Limited(4)
+    }
+}
+
+/// A redirect issued by a server.
+#[derive(Debug, Clone)]
+pub struct Redirect<'a> {
+    /// URL of the request which was redirected.
+    pub from: &'a str,
+    /// URL the server redirected to.
+    pub to: &'a str,
+    /// Status code of the redirect response.
+    pub status: StatusCode,
+    /// Number of redirects which have already been followed.
+    pub hops: usize,
+}
+impl Redirect<'_> {
+    /// Whether the redirect leads to another origin, which has another scheme, host or port.
+    ///
+    /// Hosts are compared without regard to case, and ports which are left out are the default port of the scheme,
+    /// so a redirect from `https://example.org` to `https://EXAMPLE.org:443/b` stays on the same origin,
+    /// while one to `http://example.org` or `https://example.org:8443` does not.
+    #[must_use]
+    pub fn is_cross_host(&self) -> bool {
+        origin(self.from).is_none() || origin(self.from) != origin(self.to)
+    }
+}
+
+/// The scheme, lowercase host and port of a URL, which make up its origin.
+fn origin(url: &str) -> Option<(String, String, u16)> {
+    let scheme = uri::scheme(url).unwrap_or("http").to_ascii_lowercase();
+    let host = uri::host(url)?.to_ascii_lowercase();
+    Some((scheme, host, uri::port_or_default(url)?))
+}
+
+/// A redirect which was followed on the way to a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hop {
+    /// URL of the request which was redirected.
+    pub url: String,
+    /// Status code of the redirect response.
+    pub status: StatusCode,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Redirect;
+    use crate::StatusCode;
+
+    #[test]
+    fn cross_host() {
+        let redirect = |to| Redirect {
+            from: "https://example.org/a",
+            to,
+            status: StatusCode::FOUND,
+            hops: 0,
+        };
+        assert!(!redirect("https://example.org/b").is_cross_host());
+        assert!(!redirect("HTTPS://EXAMPLE.org:443/b").is_cross_host());
+        assert!(redirect("https://example.com/b").is_cross_host());
+        // a downgrade to HTTP, or another port, leaves the origin too
+        assert!(redirect("http://example.org/b").is_cross_host());
+        assert!(redirect("https://example.org:8443/b").is_cross_host());
+        assert!(redirect("example.org/b").is_cross_host());
+    }
+}
@@ -0,0 +1,270 @@
+use super::{receive_response, Request};
+use crate::{Body, ConnectionInfo, Error, ErrorKind, Phase, ReadWrite, Response};
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, Write},
+};
+
+impl<'a> Request<'a> {
+    /// Open a connection and send the head of the request, returning separate handles for sending the body and receiving the response.
+    ///
+    /// The body is sent with the chunked transfer coding as it is written to the [`BodyWriter`], starting with the body of the request if it has one,
+    /// and the response can be received with the [`ResponseReader`] while the body is still being written, e.g. from another thread.
+    /// This allows exchanges where the server responds to the body as it arrives, such as streaming transcoding.
+    ///
+    /// Redirects are not followed, the request is not retried, and progress hooks and rate limits are not applied.
+    /// The connection must support being read from and written to at the same time, see [`ReadWrite::try_clone`],
+    /// which is the case for plain TCP connections and Unix domain sockets, but not for HTTPS.
+    ///
+    /// # Errors
+    ///
+    /// May error for the same reasons as [`Request::send`], or with [`io::ErrorKind::Unsupported`]
+    /// if the request uploads a file or the connection can not be shared.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// # use std::io::{Read, Write};
+    /// let (mut writer, reader) = Request::post("localhost:8000/transcode", "").send_duplex().unwrap();
+    /// std::thread::scope(|scope| {
+    ///     scope.spawn(move || {
+    ///         for _ in 0..10 {
+    ///             writer.write_all(b"a part of the input").unwrap();
+    ///         }
+    ///         writer.finish().unwrap();
+    ///     });
+    ///     let (response, mut body) = reader.receive().unwrap();
+    ///     let mut output = Vec::new();
+    ///     body.read_to_end(&mut output).unwrap();
+    /// });
+    /// ```
+    pub fn send_duplex(&self) -> Result<(BodyWriter, ResponseReader<'a>), Error> {
+        Ok(self.open_duplex().map_err(Error::at(&self.url))?)
+    }
+
+    /// Connect and send the head of a request with a chunked body, see [`Request::send_duplex`].
+    fn open_duplex(&self) -> Result<(BodyWriter, ResponseReader<'a>), io::Error> {
+        let prepare = Error::during(Phase::Prepare, ErrorKind::Io);
+        if self.file.is_some() {
+            let e = io::Error::new(
+                io::ErrorKind::Unsupported,
+                "files can not be uploaded in a duplex exchange",
+            );
+            return Err(prepare(e));
+        }
+        let mut request = self.clone();
+        request.body = Cow::Borrowed(&[]);
+        request.headers.remove("Content-Length");
+        request.headers.insert("Transfer-Encoding", "chunked");
+        let message = request.message(false).map_err(&prepare)?;
+
+        let deadline = self.total_deadline();
+        let (mut stream, _) = (self.open(self.connect_deadline(deadline)))
+            .map_err(Error::during(Phase::Connect, ErrorKind::Io))?;
+        let connection = ConnectionInfo {
+            remote_addr: stream.peer_addr(),
+            local_addr: stream.local_addr(),
+        };
+        let reader = (stream.try_clone()).map_err(Error::during(Phase::Connect, ErrorKind::Io))?;
+        let send = Error::during(Phase::Send, ErrorKind::Io);
+        stream
+            .set_write_timeout(self.timeouts.write)
+            .map_err(&send)?;
+        stream.write_all(&message).map_err(&send)?;
+        debug!("sent {} bytes to {}", message.len(), self.url);
+
+        let mut writer = BodyWriter {
+            stream,
+            finished: false,
+        };
+        writer.write_all(&self.body).map_err(&send)?;
+        let reader = ResponseReader {
+            request: self.clone(),
+            stream: reader,
+            connection,
+        };
+        Ok((writer, reader))
+    }
+}
+
+/// The sending half of a duplex exchange, which sends the body of the request as it is written, see [`Request::send_duplex`].
+///
+/// Each write is sent as a chunk. The body ends when the writer is finished or dropped.
+pub struct BodyWriter {
+    /// The connection to the server.
+    stream: Box<dyn ReadWrite>,
+    /// Whether the last chunk has been sent.
+    finished: bool,
+}
+impl BodyWriter {
+    /// Send the last chunk, which ends the body.
+    ///
+    /// # Errors
+    ///
+    /// May error if the connection fails.
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        self.end()
+    }
+
+    /// Send the last chunk, unless it was sent already.
+    fn end(&mut self) -> Result<(), io::Error> {
+        if !self.finished {
+            self.finished = true;
+            self.stream.write_all(b"0\r\n\r\n")?;
+            self.stream.flush()?;
+        }
+        Ok(())
+    }
+}
+impl Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // an empty chunk would end the body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "body is finished",
+            ));
+        }
+        let mut chunk = format!("{:x}\r\n", buf.len()).into_bytes();
+        chunk.extend(buf);
+        chunk.extend(b"\r\n");
+        self.stream.write_all(&chunk)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+impl Drop for BodyWriter {
+    fn drop(&mut self) {
+        let _ = self.end();
+    }
+}
+impl fmt::Debug for BodyWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyWriter")
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a duplex exchange, which receives the response, see [`Request::send_duplex`].
+pub struct ResponseReader<'a> {
+    /// The request, whose limits, timeouts and hooks apply to the response.
+    request: Request<'a>,
+    /// The connection to the server.
+    stream: Box<dyn ReadWrite>,
+    /// The addresses of the connection.
+    connection: ConnectionInfo,
+}
+impl ResponseReader<'_> {
+    /// Wait for the head of the response, returning it along with its body, which is read from the connection as it is consumed.
+    ///
+    /// # Errors
+    ///
+    /// May error if the connection fails, if the read timeout is exceeded, or if the response is invalid.
+    pub fn receive(self) -> Result<(Response, Body), Error> {
+        let Self {
+            request,
+            mut stream,
+            connection,
+        } = self;
+        let received =
+            receive_response(Vec::new(), request.informational, request.limits, |chunk| {
+                request.receive(stream.as_mut(), chunk, None)
+            })
+            .map_err(Error::during(Phase::Receive, ErrorKind::Io));
+        let result = received.and_then(|(mut response, rest)| {
+            response.connection = connection;
+            if let Some(jar) = request.cookie_jar {
+                jar.store(&request.url, &response);
+            }
+            let failed = Error::during(Phase::Receive, ErrorKind::Io);
+            let mut body = Body::new(
+                &mut response,
+                request.method,
+                rest,
+                stream,
+                request.decompress,
+            )
+            .map_err(&failed)?;
+            body.set_limit(request.limits.body).map_err(&failed)?;
+            body.set_read_timeout(request.timeouts.read)
+                .map_err(&failed)?;
+            Ok((response, body))
+        });
+        Ok(result.map_err(Error::at(&request.url))?)
+    }
+}
+impl fmt::Debug for ResponseReader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseReader")
+            .field("url", &self.request.url)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Request;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn duplex() {
+        // a server which responds right away, and echoes the chunks of the request body as they arrive
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = vec![];
+            let mut buffer = [0; 4096];
+            while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                received.extend(&buffer[..n]);
+            }
+            let end = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+            let head = String::from_utf8_lossy(&received[..end]).into_owned();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .unwrap();
+            let mut body = received[end..].to_vec();
+            stream.write_all(&body).unwrap();
+            while !body.ends_with(b"0\r\n\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                stream.write_all(&buffer[..n]).unwrap();
+                body.extend(&buffer[..n]);
+            }
+            head
+        });
+
+        let (mut writer, reader) = Request::post(&url, "hi").send_duplex().unwrap();
+        let (response, mut body) = reader.receive().unwrap();
+        assert_eq!(response.status, 200);
+        let mut buffer = [0; 2];
+        body.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"hi");
+        // the response is read while the body is still being written
+        writer.write_all(b"hello").unwrap();
+        let mut buffer = [0; 5];
+        body.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"hello");
+        writer.finish().unwrap();
+        let mut rest = String::new();
+        body.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "");
+
+        let head = server.join().unwrap();
+        assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+    }
+}
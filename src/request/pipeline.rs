@@ -0,0 +1,220 @@
+use super::{receive_response, Request};
+use crate::{
+    body::{self, Chunked},
+    encoding::Decoder,
+    response::has_body,
+    uri, ConnectionInfo, Error, ErrorKind, Method, Phase, Response,
+};
+use std::io::{self, prelude::*, BufReader, Cursor};
+
+/// Send a batch of idempotent requests to the same server over one connection, writing them all before reading the responses,
+/// which are returned in the order of the requests.
+///
+/// Pipelining saves a round trip for every request after the first, which makes many small requests such as bulk GETs much faster
+/// against servers which support it. If the server closes the connection before it has answered every request,
+/// e.g. because it limits how many requests are sent over a connection, the rest are pipelined again on a new connection.
+/// The timeouts and proxy of the first request are used for each connection.
+///
+/// Redirects are not followed, the requests are not retried on other failures, and progress hooks and rate limits are not applied.
+///
+/// # Errors
+///
+/// Errors with [`io::ErrorKind::InvalidInput`] if a request is not idempotent, uploads a file, or is sent to another server than the first,
+/// and otherwise for the same reasons as [`Request::send`], in which case the responses which were received are discarded.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let requests = ["a", "b", "c"].map(|page| Request::get(format!("example.org/{page}")));
+/// for response in send_pipelined(&requests).unwrap() {
+///     println!("{}", response.status);
+/// }
+/// ```
+pub fn send_pipelined(requests: &[Request]) -> Result<Vec<Response>, Error> {
+    let Some(first) = requests.first() else {
+        return Ok(Vec::new());
+    };
+    let origin = |request: &Request| {
+        let scheme = uri::scheme(&request.url).map(str::to_ascii_lowercase);
+        let host = uri::host(&request.url).map(str::to_ascii_lowercase);
+        (scheme, host, uri::port_or_default(&request.url))
+    };
+    for request in requests {
+        let message = if !request.method.is_idempotent() {
+            "only idempotent requests can be pipelined"
+        } else if request.file.is_some() {
+            "files can not be uploaded in a pipeline"
+        } else if origin(request) != origin(first) {
+            "pipelined requests must be sent to the same server"
+        } else {
+            continue;
+        };
+        let e = io::Error::new(io::ErrorKind::InvalidInput, message);
+        let e = Error::during(Phase::Prepare, ErrorKind::Io)(e);
+        return Err(Error::at(&request.url)(e).into());
+    }
+
+    let mut responses = Vec::with_capacity(requests.len());
+    while responses.len() < requests.len() {
+        let received = responses.len();
+        let pending = &requests[received..];
+        let result = pipeline(pending, &mut responses);
+        // a connection which was closed after some of the requests were answered is replaced
+        if let Err(e) = result {
+            if responses.len() == received {
+                return Err(Error::at(&pending[0].url)(e).into());
+            }
+            debug!("pipeline was interrupted: {e}, continuing on a new connection");
+        }
+    }
+    Ok(responses)
+}
+
+/// Write requests to a new connection, and receive their responses until the connection is closed.
+fn pipeline(requests: &[Request], responses: &mut Vec<Response>) -> Result<(), io::Error> {
+    let first = &requests[0];
+    let mut messages = Vec::new();
+    for request in requests {
+        let message =
+            (request.message(false)).map_err(Error::during(Phase::Prepare, ErrorKind::Io))?;
+        if let Some(hook) = request.outgoing {
+            hook.on_request(&message);
+        }
+        messages.extend(message);
+    }
+
+    let deadline = first.total_deadline();
+    let (mut stream, _) = (first.open(first.connect_deadline(deadline)))
+        .map_err(Error::during(Phase::Connect, ErrorKind::Io))?;
+    let connection = ConnectionInfo {
+        remote_addr: stream.peer_addr(),
+        local_addr: stream.local_addr(),
+    };
+    let send = Error::during(Phase::Send, ErrorKind::Io);
+    (stream.set_write_timeout(first.timeouts.write)).map_err(&send)?;
+    stream.write_all(&messages).map_err(&send)?;
+    stream.flush().map_err(&send)?;
+    debug!("pipelined {} requests to {}", requests.len(), first.url);
+
+    let failed = Error::during(Phase::Receive, ErrorKind::Io);
+    (stream.set_read_timeout(first.timeouts.read)).map_err(&failed)?;
+    let mut reader = BufReader::new(stream);
+    let mut received = Vec::new();
+    for request in requests {
+        let (mut response, rest) =
+            receive_response(received, request.informational, request.limits, |chunk| {
+                reader.read(chunk)
+            })
+            .map_err(&failed)?;
+        let mut remaining = Cursor::new(rest).chain(&mut reader);
+        let closes = receive_body(request, &mut response, &mut remaining).map_err(&failed)?;
+        let (rest, _) = remaining.into_inner();
+        let position = usize::try_from(rest.position()).unwrap_or(usize::MAX);
+        received = rest.into_inner().split_off(position);
+
+        response.connection = connection;
+        if let Some(jar) = request.cookie_jar {
+            jar.store(&request.url, &response);
+        }
+        if let Some(hook) = request.incoming {
+            hook.on_response(&response);
+        }
+        responses.push(response);
+        if closes {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read the body of a pipelined response, which must end exactly where the next response starts,
+/// returning whether the connection is closed after the response.
+fn receive_body(
+    request: &Request,
+    response: &mut Response,
+    reader: &mut impl BufRead,
+) -> Result<bool, io::Error> {
+    let mut closes = response.version == "HTTP/1.0"
+        || (response.headers.get_all("Connection"))
+            .flat_map(|options| options.split(','))
+            .any(|option| option.trim().eq_ignore_ascii_case("close"));
+    let mut encoded = Vec::new();
+    if request.method == Method::HEAD || !has_body(response.status) {
+        // the response has no body
+    } else if body::is_chunked(response) {
+        Chunked::new(&mut *reader).read_to_end(&mut encoded)?;
+    } else if let Some(length) = response.headers.content_length() {
+        reader.take(length).read_to_end(&mut encoded)?;
+        if (encoded.len() as u64) < length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "body ended early",
+            ));
+        }
+    } else {
+        // the body ends when the server closes the connection
+        reader.read_to_end(&mut encoded)?;
+        closes = true;
+    }
+
+    let limit = request.limits.body.unwrap_or(u64::MAX);
+    Decoder::new(response, &encoded[..], request.decompress)?
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut response.body)?;
+    if response.body.len() as u64 > limit {
+        return Err(body::too_large());
+    }
+    Ok(closes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::send_pipelined;
+    use crate::{Method, Request};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn pipelined() {
+        // a server which answers at most two requests per connection, all at once, after receiving them
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            let mut connections = vec![];
+            for (expected, responses) in [
+                (3, "HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\naHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n1\r\nb\r\n0\r\n\r\n"),
+                (1, "HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nc"),
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut received = String::new();
+                let mut buffer = [0; 4096];
+                while received.matches("\r\n\r\n").count() < expected {
+                    let n = stream.read(&mut buffer).unwrap();
+                    received.push_str(&String::from_utf8_lossy(&buffer[..n]));
+                }
+                stream.write_all(responses.as_bytes()).unwrap();
+                connections.push(received);
+            }
+            connections
+        });
+
+        let requests = ["a", "b", "c"].map(|page| Request::get(format!("{url}/{page}")));
+        let responses = send_pipelined(&requests).unwrap();
+        let bodies: Vec<_> = responses.iter().map(|r| r.body.as_slice()).collect();
+        assert_eq!(bodies, [b"a", b"b", b"c"]);
+        let connections = server.join().unwrap();
+        assert!(connections[0].starts_with("GET /a HTTP/1.1\r\n"));
+        assert!(connections[0].contains("GET /b HTTP/1.1\r\n"));
+        assert!(connections[1].starts_with("GET /c HTTP/1.1\r\n"));
+
+        // requests which are not idempotent, or to other servers, can not be pipelined
+        let requests = [Request::get(&url), Request::new(&url, Method::POST)];
+        assert!(send_pipelined(&requests).is_err());
+        let requests = [Request::get(&url), Request::get("example.org")];
+        assert!(send_pipelined(&requests).is_err());
+    }
+}
@@ -1,8 +1,20 @@
-use regex::Regex;
-use std::{collections::HashMap, sync::LazyLock};
+use crate::{
+    charset, date::DateTime, link, parser::Parser, CacheControl, Headers, Hop, MediaType, Method,
+    StatusCode, Version,
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error,
+    fmt::{self, Write},
+    io,
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
 
 /// An HTTP response.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
     /// HTTP version.
     ///
@@ -11,59 +23,631 @@ pub struct Response {
     /// Status code.
     ///
     /// 100-199: info, 200-299: success, 300-399: redir, 400-499: client error, 500-599: server error.
-    pub status: u16,
+    pub status: StatusCode,
     /// Message associated to the status code.
     pub reason: String,
-    /// Map of headers.
-    pub headers: HashMap<String, String>,
+    /// Response headers.
+    ///
+    /// Trailer fields sent after a chunked body are added after the headers.
+    pub headers: Headers,
     /// Message body.
-    pub body: String,
+    ///
+    /// Use [`Response::text`] to read it as text.
+    pub body: Vec<u8>,
+    /// Redirects which were followed to get to this response, in order.
+    ///
+    /// The URL of the final response is the location of the last redirect.
+    pub history: Vec<Hop>,
+    /// The connection the response was received over.
+    pub connection: ConnectionInfo,
+    /// How long each phase of the request took.
+    pub timings: Timings,
 }
 impl Response {
-    /// Parse the raw HTTP response into a structured [`Request`].
-    pub(crate) fn parse(message: &str) -> Result<Self, &'static str> {
-        // construct a regex: HTTP-Version Status-Code Reason-Phrase CRLF headers CRLF message-body
-        static MSG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-            Regex::new(r"(?P<version>HTTP\/\d\.\d) (?P<status>\d+) (?P<reason>[a-zA-Z ]+)(?:\r?\n(?P<headers>(?:.+\r?\n)+))?(?:\r?\n(?P<body>[\S\s]*))?").unwrap()
-        });
-
-        // parse the response
-        let Some(parts) = MSG_REGEX.captures(message) else {
-            Err("invalid message")?
-        };
-        let version = parts["version"].to_string();
-        let status = parts["status"].parse().unwrap();
-        let reason = parts["reason"].to_string();
-
-        // parse headers
-        let headers = parts.name("headers").map_or("", |m| m.as_str());
-        let headers = headers
-            .lines()
-            .filter_map(|l| l.split_once(": "))
-            .map(|(a, b)| (a.to_string(), b.to_string()))
-            .collect::<HashMap<String, String>>();
-
-        // parse body
-        let body = parts
-            .name("body")
-            .map_or(String::new(), |m| m.as_str().to_string());
-
-        // construct the response
-        let response = Response {
-            version,
-            status,
-            reason,
-            headers,
-            body,
-        };
+    /// Create a builder for a response, e.g. to test code which handles responses without sending requests.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let response = Response::builder()
+    ///     .status(404)
+    ///     .header("Content-Type", "text/plain")
+    ///     .body("not here")
+    ///     .build();
+    /// assert_eq!(response.status, StatusCode::NOT_FOUND);
+    /// assert_eq!(response.reason, "Not Found");
+    /// assert_eq!(response.text(), "not here");
+    /// ```
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder {
+            response: Response {
+                version: Version::Http11.to_string(),
+                status: StatusCode::OK,
+                reason: String::new(),
+                headers: Headers::new(),
+                body: Vec::new(),
+                history: Vec::new(),
+                connection: ConnectionInfo::default(),
+                timings: Timings::default(),
+            },
+        }
+    }
 
+    /// Parse the raw HTTP response into a structured [`Response`].
+    ///
+    /// Lines may end with either CRLF or LF, and empty lines before the status line are ignored.
+    /// Header values which are folded over multiple lines are joined with a space, and invalid header lines are skipped.
+    pub(crate) fn parse(message: impl AsRef<[u8]>) -> Result<Self, &'static str> {
+        let message = message.as_ref();
+        let mut parser = Parser::new();
+        let head = parser.feed(message)?;
+        let mut response = parser.finish()?;
+
+        // the rest is the body, which is never present in informational, 204 and 304 responses
+        if has_body(response.status) {
+            response.body = message[head..].to_vec();
+        }
         Ok(response)
     }
+
+    /// Parse a raw HTTP response without copying it, so the parts of the response borrow from the message.
+    ///
+    /// This avoids allocating for every header, e.g. for scrapers which parse many responses from a receive buffer.
+    /// The response is parsed leniently by the same parser as received responses, except that it must be valid UTF-8.
+    /// Only header values which are folded over multiple lines are copied, as their lines are joined with a space.
+    /// The body is the rest of the message, unless the status does not allow one.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [`io::ErrorKind::InvalidData`] if the message is not a valid response.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let message = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nhello";
+    /// let response = Response::parse_borrowed(message).unwrap();
+    /// assert_eq!(response.status, 200);
+    /// assert_eq!(response.header("content-type"), Some("text/plain"));
+    /// assert_eq!(response.body, b"hello");
+    /// ```
+    pub fn parse_borrowed(message: &[u8]) -> Result<BorrowedResponse<'_>, crate::Error> {
+        (Parser::new().parse_borrowed(message))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
+    }
+
+    /// The body as text, decoded with the charset of the `Content-Type` header.
+    ///
+    /// UTF-8, UTF-16 and Latin-1 (Windows-1252) are supported, and bodies in other charsets or without one are decoded as UTF-8.
+    /// Invalid sequences are replaced with U+FFFD.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\r\nhello"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org").unwrap();
+    /// assert_eq!(response.text(), "hello");
+    /// ```
+    #[must_use]
+    pub fn text(&self) -> Cow<'_, str> {
+        let media = self.content_type();
+        charset::decode(&self.body, media.as_ref().and_then(MediaType::charset))
+    }
+
+    /// The media type of the body, parsed from the `Content-Type` header.
+    ///
+    /// Returns `None` if the header is missing or is not a valid media type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org").unwrap();
+    /// let media = response.content_type().unwrap();
+    /// assert_eq!(media.essence(), "application/json");
+    /// assert_eq!(media.charset(), Some("utf-8"));
+    /// ```
+    #[must_use]
+    pub fn content_type(&self) -> Option<MediaType> {
+        self.headers.content_type()?.parse().ok()
+    }
+
+    /// Whether the `Content-Type` of the response is in one of the media ranges which were accepted, see [`Request::accept`](crate::Request::accept).
+    ///
+    /// Media ranges with a quality value of 0 are not acceptable. Responses without a valid `Content-Type` are not acceptable either.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let accept = [("application/json", 1.0), ("text/*", 0.5)];
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n"),
+    /// );
+    /// let response = Client::new().with(transport).send(Request::get("example.org").accept(&accept)).unwrap();
+    /// assert!(response.is_acceptable(&accept));
+    /// assert!(!response.is_acceptable(&[("application/json", 1.0), ("text/*", 0.0)]));
+    /// ```
+    #[must_use]
+    pub fn is_acceptable(&self, types: &[(&str, f32)]) -> bool {
+        self.content_type().is_some_and(|media| {
+            (types.iter()).any(|&(range, quality)| quality > 0.0 && media.matches(range))
+        })
+    }
+
+    /// Deserialize the JSON body into a value.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the body is not valid JSON for the type.
+    /// The error message includes the status and the start of the body, as error responses are often not JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// # use std::collections::HashMap;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\n\r\n{\"code\":123}"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org").unwrap();
+    /// let data: HashMap<String, u32> = response.json().unwrap();
+    /// assert_eq!(data["code"], 123);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, crate::Error> {
+        serde_json::from_slice(&self.body).map_err(|e| {
+            let text = self.text();
+            let body = match text.char_indices().nth(JSON_ERROR_BODY) {
+                Some((end, _)) => format!("{}...", &text[..end]),
+                None => text.into_owned(),
+            };
+            let message = format!(
+                "invalid JSON in {} {} response ({e}): {body}",
+                self.status, self.reason
+            );
+            let e = std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+            crate::Error::new(crate::ErrorKind::Protocol, e)
+        })
+    }
+
+    /// The entity tag of the resource, from the `ETag` header.
+    ///
+    /// It can be sent back with [`Request::if_none_match`](crate::Request::if_none_match) to revalidate a cached copy.
+    #[must_use]
+    pub fn etag(&self) -> Option<&str> {
+        self.headers.get("ETag")
+    }
+
+    /// Links to related resources from the `Link` headers, by relation type, e.g. `next` for the next page of results.
+    ///
+    /// Relation types are lowercased. If several links have the same relation type, the first one is kept.
+    /// The targets are returned as they were sent, so they may be relative to the URL of the request.
+    #[must_use]
+    pub fn links(&self) -> HashMap<String, &str> {
+        let mut links = HashMap::new();
+        for (relation, target) in self.headers.get_all("Link").flat_map(link::parse) {
+            links.entry(relation).or_insert(target);
+        }
+        links
+    }
+
+    /// The target of the link with a relation type from the `Link` headers, see [`Response::links`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org/items").respond("HTTP/1.1 200 OK\r\nLink: <https://example.org/items?page=2>; rel=\"next\"\r\n\r\n"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org/items").unwrap();
+    /// assert_eq!(response.link("next"), Some("https://example.org/items?page=2"));
+    /// assert_eq!(response.link("prev"), None);
+    /// ```
+    #[must_use]
+    pub fn link(&self, relation: &str) -> Option<&str> {
+        let relation = relation.to_ascii_lowercase();
+        (self.headers.get_all("Link"))
+            .flat_map(link::parse)
+            .find_map(|(r, target)| (r == relation).then_some(target))
+    }
+
+    /// The methods which the resource supports, from the `Allow` header, and the `Access-Control-Allow-Methods` header of CORS preflight responses.
+    ///
+    /// Methods are listed in the order they were sent, without duplicates. Unsupported methods and the `*` wildcard are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::OPTIONS, "example.org/items").respond("HTTP/1.1 204 No Content\r\nAllow: GET, HEAD, POST\r\n\r\n"),
+    /// );
+    /// let response = Client::new().with(transport).send(Request::new("example.org/items", Method::OPTIONS)).unwrap();
+    /// assert_eq!(response.allowed_methods(), [Method::GET, Method::HEAD, Method::POST]);
+    /// ```
+    #[must_use]
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        let mut methods = Vec::new();
+        let values = (self.headers.get_all("Allow"))
+            .chain(self.headers.get_all("Access-Control-Allow-Methods"));
+        for method in values.flat_map(|value| value.split(',')) {
+            if let Ok(method) = method.trim().parse() {
+                if !methods.contains(&method) {
+                    methods.push(method);
+                }
+            }
+        }
+        methods
+    }
+
+    /// When the resource was last modified, from the `Last-Modified` header.
+    ///
+    /// It can be sent back with [`Request::if_modified_since`](crate::Request::if_modified_since) to revalidate a cached copy.
+    #[must_use]
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        DateTime::parse(self.headers.get("Last-Modified")?).map(SystemTime::from)
+    }
+
+    /// The caching directives of the `Cache-Control` headers, which are all default if there are none.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// # use std::time::Duration;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\n\r\n"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org").unwrap();
+    /// assert_eq!(response.cache_control().max_age, Some(Duration::from_secs(60)));
+    /// ```
+    #[must_use]
+    pub fn cache_control(&self) -> CacheControl {
+        let directives = self.headers.get_all("Cache-Control").collect::<Vec<_>>();
+        CacheControl::parse(&directives.join(","))
+    }
+
+    /// How long the response has been stored in caches along the way, from the `Age` header.
+    #[must_use]
+    pub fn age(&self) -> Option<Duration> {
+        let age = self.headers.get("Age")?.trim().parse().ok()?;
+        Some(Duration::from_secs(age))
+    }
+
+    /// When the response becomes stale, from the `Expires` header.
+    ///
+    /// Invalid dates, such as `0`, mean that the response has already expired, so they are returned as the Unix epoch.
+    #[must_use]
+    pub fn expires(&self) -> Option<SystemTime> {
+        let expires = self.headers.get("Expires")?;
+        Some(DateTime::parse(expires).map_or(SystemTime::UNIX_EPOCH, SystemTime::from))
+    }
+
+    /// Whether the cached copy of the resource is still current, i.e. the status is `304 Not Modified`.
+    #[must_use]
+    pub fn is_not_modified(&self) -> bool {
+        self.status == 304
+    }
+
+    /// Whether the status is informational (100-199).
+    #[must_use]
+    pub fn is_informational(&self) -> bool {
+        self.status.is_informational()
+    }
+
+    /// Whether the request succeeded, i.e. the status is 200-299.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// Whether the status is a redirection (300-399).
+    #[must_use]
+    pub fn is_redirect(&self) -> bool {
+        self.status.is_redirect()
+    }
+
+    /// Whether the status is a client error (400-499).
+    #[must_use]
+    pub fn is_client_error(&self) -> bool {
+        self.status.is_client_error()
+    }
+
+    /// Whether the status is a server error (500-599).
+    #[must_use]
+    pub fn is_server_error(&self) -> bool {
+        self.status.is_server_error()
+    }
+
+    /// Turn a client or server error status into an error, or return the response otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [`ErrorKind::Status`](crate::ErrorKind::Status) if the status is 400-599.
+    /// The error wraps a [`StatusError`], and the response can be taken from it with [`Error::response`](crate::Error::response).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 404 Not Found\r\n\r\n"),
+    /// );
+    /// let error = Client::new().with(transport).get("example.org").unwrap().error_for_status().unwrap_err();
+    /// assert_eq!(error.to_string(), "404 Not Found");
+    ///
+    /// assert_eq!(error.kind(), ErrorKind::Status);
+    /// assert_eq!(error.response().unwrap().status, 404);
+    /// ```
+    pub fn error_for_status(self) -> Result<Self, crate::Error> {
+        if self.is_client_error() || self.is_server_error() {
+            let e = io::Error::other(StatusError {
+                response: Box::new(self),
+            });
+            return Err(crate::Error::new(crate::ErrorKind::Status, e));
+        }
+        Ok(self)
+    }
+
+    /// Format the response for logging, like it was received, but with at most `limit` characters of the body.
+    ///
+    /// Lines end with `\n` instead of CRLF, and bodies which are not text are summarized by their size.
+    /// The [`Display`](fmt::Display) implementation shows up to 1024 characters of the body.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use request::*;
+    /// let transport = MockTransport::new().mock(
+    ///     Mock::new(Method::GET, "example.org").respond("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nhello world"),
+    /// );
+    /// let response = Client::new().with(transport).get("example.org").unwrap();
+    /// assert_eq!(
+    ///     response.dump(5),
+    ///     "HTTP/1.1 200 OK\nContent-Type: text/plain\n\nhello... (11 bytes)"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn dump(&self, limit: usize) -> String {
+        let mut dump = format!("{} {} {}\n", self.version, self.status, self.reason);
+        for (name, value) in self.headers.iter() {
+            let _ = writeln!(dump, "{name}: {value}");
+        }
+        dump.push('\n');
+        let text = self.text();
+        if text.contains(char::REPLACEMENT_CHARACTER) {
+            let _ = write!(dump, "({} bytes of binary data)", self.body.len());
+        } else if let Some((end, _)) = text.char_indices().nth(limit) {
+            let _ = write!(dump, "{}... ({} bytes)", &text[..end], self.body.len());
+        } else {
+            dump.push_str(&text);
+        }
+        dump
+    }
+}
+impl fmt::Display for Response {
+    /// Format the response for logging, with up to 1024 characters of the body, see [`Response::dump`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.dump(DUMP_LIMIT))
+    }
+}
+
+/// An HTTP response which borrows its parts from the message it was parsed from, see [`Response::parse_borrowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowedResponse<'a> {
+    /// HTTP version.
+    pub version: &'a str,
+    /// Status code.
+    pub status: StatusCode,
+    /// Message associated to the status code.
+    pub reason: &'a str,
+    /// Names and values of the response headers, in order.
+    ///
+    /// Values are only copied if they are folded over multiple lines, as the lines are joined with a space.
+    pub headers: Vec<(&'a str, Cow<'a, str>)>,
+    /// Message body.
+    pub body: &'a [u8],
 }
+impl BorrowedResponse<'_> {
+    /// The value of the first header with a name, regardless of case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        (self.headers.iter())
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, value)| &**value)
+    }
+
+    /// Copy the response into an owned [`Response`].
+    #[must_use]
+    pub fn into_owned(self) -> Response {
+        Response {
+            version: self.version.to_string(),
+            status: self.status,
+            reason: self.reason.to_string(),
+            headers: self.headers.into_iter().collect(),
+            body: self.body.to_vec(),
+            history: Vec::new(),
+            connection: ConnectionInfo::default(),
+            timings: Timings::default(),
+        }
+    }
+}
+
+/// A builder for a [`Response`], which is created with [`Response::builder`].
+///
+/// By default, the response is `HTTP/1.1 200 OK` without headers or a body.
+/// Headers such as `Content-Length` are not added automatically.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct ResponseBuilder {
+    /// The response which is being built.
+    response: Response,
+}
+impl ResponseBuilder {
+    /// Set the status code, and the canonical reason phrase of the status unless a reason is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status code does not have three digits.
+    pub fn status(self, status: u16) -> Self {
+        let mut builder = self;
+        builder.response.status = StatusCode::new(status).expect("status code has three digits");
+        builder
+    }
+
+    /// Set the reason phrase of the status.
+    pub fn reason(self, reason: &str) -> Self {
+        let mut builder = self;
+        builder.response.reason = reason.to_string();
+        builder
+    }
+
+    /// Set the HTTP version, e.g. `HTTP/1.0`.
+    pub fn version(self, version: &str) -> Self {
+        let mut builder = self;
+        builder.response.version = version.to_string();
+        builder
+    }
+
+    /// Add a header, keeping any values the header already has.
+    pub fn header(self, key: &str, value: &str) -> Self {
+        let mut builder = self;
+        builder.response.headers.append(key, value);
+        builder
+    }
+
+    /// Set the body.
+    pub fn body(self, body: impl Into<Vec<u8>>) -> Self {
+        let mut builder = self;
+        builder.response.body = body.into();
+        builder
+    }
+
+    /// Build the response.
+    #[must_use]
+    pub fn build(self) -> Response {
+        let mut response = self.response;
+        if response.reason.is_empty() {
+            let reason = response.status.canonical_reason().unwrap_or_default();
+            response.reason = reason.to_string();
+        }
+        response
+    }
+}
+
+/// The number of characters of the body which are shown when a response is displayed.
+const DUMP_LIMIT: usize = 1024;
+
+/// Details of the connection a response was received over, e.g. to log which server handled a request.
+///
+/// The negotiated HTTP version is [`Response::version`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionInfo {
+    /// Address of the server, or of the proxy if the request was sent through one.
+    ///
+    /// This is `None` for connections which are not over TCP, and for responses which were not received over a connection.
+    pub remote_addr: Option<SocketAddr>,
+    /// Local address of the connection.
+    pub local_addr: Option<SocketAddr>,
+}
+
+/// How long each phase of a request took, e.g. to find out why a request is slow.
+///
+/// Only the last request is measured if redirects were followed or the request was retried.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// let response = Request::get("localhost:8000").send().unwrap();
+/// let timings = response.timings;
+/// println!("dns: {:?}, connect: {:?}, first byte after: {:?}", timings.dns, timings.connect, timings.wait);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timings {
+    /// Time spent resolving the host, unless the connection was opened by a custom connector.
+    pub dns: Option<Duration>,
+    /// Time spent opening the connection after resolving the host, including proxy tunnels and TLS handshakes.
+    pub connect: Duration,
+    /// Time spent sending the request, including waiting for the server to accept the body.
+    pub send: Duration,
+    /// Time spent waiting for the head of the response after sending the request, i.e. the time to first byte.
+    pub wait: Duration,
+    /// Time spent receiving the body, which is zero if the body is streamed.
+    pub receive: Duration,
+}
+impl Timings {
+    /// Time spent on all phases together.
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.dns.unwrap_or_default() + self.connect + self.send + self.wait + self.receive
+    }
+}
+
+/// Error for a response with a client or server error status, from [`Response::error_for_status`].
+#[derive(Debug)]
+pub struct StatusError {
+    /// The response with the error status.
+    pub response: Box<Response>,
+}
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.response.status, self.response.reason)
+    }
+}
+impl error::Error for StatusError {}
+
+/// A hook which is notified of informational (1xx) responses which come before the final response,
+/// such as `103 Early Hints`.
+///
+/// Any closure taking a [`Response`] can be used as a hook.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let hints = |response: &Response| println!("preload {:?}", response.headers.get("Link"));
+/// let request = Request::get("example.org").on_informational(&hints);
+/// ```
+pub trait OnInformational: Send + Sync {
+    /// Handle an informational response, which has no body.
+    fn on_informational(&self, response: &Response);
+}
+impl<F: Fn(&Response) + Send + Sync> OnInformational for F {
+    fn on_informational(&self, response: &Response) {
+        self(response);
+    }
+}
+impl fmt::Debug for dyn OnInformational + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnInformational")
+    }
+}
+
+/// Whether a response with the status can have a body, which informational, 204 and 304 responses never have.
+pub(crate) fn has_body(status: StatusCode) -> bool {
+    !(status.is_informational() || status == 204 || status == 304)
+}
+
+/// Number of characters of the body included in JSON errors.
+#[cfg(feature = "json")]
+const JSON_ERROR_BODY: usize = 100;
 
 #[cfg(test)]
 mod tests {
-    use crate::Response;
+    use crate::{parser::Parser, CacheControl, Method, Response};
+    use std::time::{Duration, SystemTime};
 
     #[test]
     fn https_redirect() {
@@ -73,17 +657,278 @@ Location: https://archlinux.org/
 ";
 
         let response = Response::parse(message).unwrap();
-        dbg!(&response);
         assert_eq!(response.version, "HTTP/1.1".to_string());
         assert_eq!(response.status, 301);
         assert_eq!(response.reason, "Moved Permanently");
+        assert_eq!(response.headers.location(), Some("https://archlinux.org/"));
+        assert_eq!(response.headers.len(), 1);
+        assert_eq!(response.body, b"");
+    }
+
+    #[test]
+    fn status_line() {
+        let response = Response::parse("HTTP/1.1 200 OK (cached, 2 hits)\r\n\r\n").unwrap();
+        assert_eq!(response.reason, "OK (cached, 2 hits)");
+        let response = Response::parse("HTTP/1.0 404\r\n\r\n").unwrap();
+        assert_eq!(
+            (response.version.as_str(), response.reason.as_str()),
+            ("HTTP/1.0", "")
+        );
+        let response = Response::parse("\r\n\r\nHTTP/1.1 204 No Content").unwrap();
+        assert_eq!(response.status, 204);
+        assert!(response.headers.is_empty());
+
+        for (message, error) in [
+            ("", "invalid message"),
+            ("HTTP/1.1", "invalid message"),
+            ("HTTP/11 200 OK\r\n\r\n", "invalid version"),
+            ("ICY 200 OK\r\n\r\n", "invalid version"),
+            ("HTTP/1.1 2000 OK\r\n\r\n", "invalid status"),
+            ("HTTP/1.1 OK\r\n\r\n", "invalid status"),
+        ] {
+            assert_eq!(Response::parse(message).unwrap_err(), error, "{message:?}");
+        }
+    }
+
+    #[test]
+    fn header_lines() {
+        let message = "HTTP/1.1 200 OK\nContent-Type:text/plain \nX-Folded: a\r\n  b\r\nSet-Cookie: a=1\nSet-Cookie: b=2\n\nbody\r\n\r\nmore";
+        let response = Response::parse(message).unwrap();
+        assert_eq!(response.headers.content_type(), Some("text/plain"));
+        assert_eq!(response.headers.get("x-folded"), Some("a b"));
+        assert_eq!(response.headers.get_all("Set-Cookie").count(), 2);
+        assert_eq!(response.body, b"body\r\n\r\nmore");
+
+        for message in [
+            "HTTP/1.1 200 OK\r\nno colon\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nBad Name: value\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n: value\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n folded: first\r\n\r\n",
+        ] {
+            // invalid header lines are skipped, unless the response is parsed strictly
+            assert!(Response::parse(message).unwrap().headers.is_empty());
+            let mut parser = Parser::new().strict(true);
+            assert_eq!(
+                parser.feed(message.as_bytes()).unwrap_err(),
+                "invalid header",
+                "{message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validators() {
+        let message = "HTTP/1.1 304 Not Modified\r\nEtag: \"33a64df5\"\r\nLast-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\nstale";
+        let response = Response::parse(message).unwrap();
+        assert!(response.is_not_modified());
+        assert_eq!(response.etag(), Some("\"33a64df5\""));
         assert_eq!(
-            response.headers,
-            std::collections::HashMap::from([(
-                "Location".to_string(),
-                "https://archlinux.org/".to_string()
-            )])
+            response.last_modified(),
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(784_111_777))
         );
-        assert_eq!(response.body, String::new());
+        assert_eq!(response.body, b"");
+    }
+
+    #[test]
+    fn allowed_methods() {
+        let message = "HTTP/1.1 204 No Content\r\nAllow: GET, HEAD\r\nAccess-Control-Allow-Methods: GET,PUT, PROPFIND, *\r\n\r\n";
+        let response = Response::parse(message).unwrap();
+        assert_eq!(
+            response.allowed_methods(),
+            [Method::GET, Method::HEAD, Method::PUT]
+        );
+        assert!(Response::parse("HTTP/1.1 200 OK\r\n\r\n")
+            .unwrap()
+            .allowed_methods()
+            .is_empty());
+    }
+
+    #[test]
+    fn borrowed() {
+        let message = b"\nHTTP/1.1  404 Not-Found (v2)\r\nContent-Type : text/plain\nno colon\r\nSet-Cookie: a=1\r\n\r\nbody";
+        let response = Response::parse_borrowed(message).unwrap();
+        assert_eq!(
+            (response.version, response.status.as_u16(), response.reason),
+            ("HTTP/1.1", 404, "Not-Found (v2)")
+        );
+        let headers: Vec<_> = (response.headers.iter()).map(|(k, v)| (*k, &**v)).collect();
+        assert_eq!(
+            headers,
+            [("Content-Type", "text/plain"), ("Set-Cookie", "a=1")]
+        );
+        assert_eq!(response.header("content-type"), Some("text/plain"));
+        // the body is a slice of the message
+        assert_eq!(response.body, b"body");
+        assert_eq!(
+            response.body.as_ptr(),
+            message[message.len() - 4..].as_ptr()
+        );
+        let owned = response.into_owned();
+        assert_eq!(owned.headers.get("Set-Cookie"), Some("a=1"));
+        assert_eq!(owned.body, b"body");
+
+        for message in [
+            &b""[..],
+            b"HTTP/1.1 200 \xff\r\n\r\n",
+            b"HTTP/1.1 2000 OK\r\n\r\n",
+        ] {
+            assert!(Response::parse_borrowed(message).is_err(), "{message:?}");
+        }
+        let response = Response::parse_borrowed(b"HTTP/1.1 304 Not Modified\r\n\r\nstale").unwrap();
+        assert_eq!(response.body, b"");
+    }
+
+    #[test]
+    fn borrowed_agrees() {
+        // bare line feeds, folded and invalid header lines, and heads without an empty line are parsed the same way
+        for message in [
+            &b"\n  HTTP/1.1  200  OK\nContent-Type : text/plain\nbroken line\nX-Folded: a\n\tb\n  c\n\nbody"[..],
+            b"HTTP/1.1 200 OK\r\n folded without header\r\nA: 1\r\n\r\n\r\nbody\r\n",
+            b"HTTP/1.0 404 Not Found\nA: 1",
+            b"HTTP/1.1 204 No Content\r\n\r\nignored",
+        ] {
+            let owned = Response::parse(message).unwrap();
+            let borrowed = Response::parse_borrowed(message).unwrap();
+            assert_eq!(borrowed.body, owned.body);
+            let borrowed = borrowed.into_owned();
+            assert_eq!(
+                (&borrowed.version, borrowed.status, &borrowed.reason),
+                (&owned.version, owned.status, &owned.reason)
+            );
+            let headers = |r: &Response| r.headers.iter().map(|(k, v)| format!("{k}: {v}")).collect::<Vec<_>>();
+            assert_eq!(headers(&borrowed), headers(&owned));
+        }
+        let response = Response::parse_borrowed(b"HTTP/1.1 200 OK\nX-Folded: a\n b\n\n").unwrap();
+        assert_eq!(response.header("X-Folded"), Some("a b"));
+
+        for message in [
+            &b""[..],
+            b"\r\n",
+            b"HTTP/1.1 2000 OK\r\n\r\n",
+            b"HTTP/x 200 OK\r\n",
+        ] {
+            assert_eq!(
+                Response::parse(message).is_err(),
+                Response::parse_borrowed(message).is_err(),
+                "{message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn status_classes() {
+        let response = Response::parse("HTTP/1.1 302 Found\r\n\r\n").unwrap();
+        assert!(response.is_redirect() && !response.is_success());
+        assert_eq!(response.error_for_status().unwrap().status, 302);
+
+        let response = Response::parse("HTTP/1.1 503 Service Unavailable\r\n\r\n").unwrap();
+        assert!(response.is_server_error() && !response.is_client_error());
+        let error = response.error_for_status().unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::Status);
+        assert_eq!(error.to_string(), "503 Service Unavailable");
+        assert_eq!(error.response().unwrap().reason, "Service Unavailable");
+    }
+
+    #[test]
+    fn binary_body() {
+        let mut message = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        message.extend([0x89, b'P', b'N', b'G', 0xff, 0x00]);
+        let response = Response::parse(message).unwrap();
+        assert_eq!(response.body, [0x89, b'P', b'N', b'G', 0xff, 0x00]);
+        assert_eq!(response.text(), "\u{fffd}PNG\u{fffd}\0");
+    }
+
+    #[test]
+    fn charset() {
+        let mut message =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=\"ISO-8859-1\"\r\n\r\n".to_vec();
+        message.extend(b"caf\xe9");
+        assert_eq!(Response::parse(message).unwrap().text(), "café");
+    }
+
+    #[test]
+    fn freshness() {
+        let response = Response::parse(
+            "HTTP/1.1 200 OK\r\nCache-Control: public\r\nAge: 30\r\nCache-Control: max-age=90\r\nExpires: 0\r\n\r\n",
+        )
+        .unwrap();
+        let cache = response.cache_control();
+        assert!(cache.public);
+        assert_eq!(cache.max_age, Some(Duration::from_secs(90)));
+        assert_eq!(response.age(), Some(Duration::from_secs(30)));
+        assert_eq!(response.expires(), Some(SystemTime::UNIX_EPOCH));
+
+        let response =
+            Response::parse("HTTP/1.1 200 OK\r\nExpires: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n")
+                .unwrap();
+        let expires = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(response.expires(), Some(expires));
+        assert_eq!(response.cache_control(), CacheControl::default());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json() {
+        let response = Response::parse("HTTP/1.1 200 OK\r\n\r\n[1, 2, 3]").unwrap();
+        assert_eq!(response.json::<Vec<u8>>().unwrap(), [1, 2, 3]);
+
+        let body = "<html>".repeat(100);
+        let response = Response::parse(format!("HTTP/1.1 502 Bad Gateway\r\n\r\n{body}")).unwrap();
+        let error = response.json::<Vec<u8>>().unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::Protocol);
+        assert_eq!(error.io_kind(), std::io::ErrorKind::InvalidData);
+        let message = error.to_string();
+        assert!(message.starts_with("invalid response: invalid JSON in 502 Bad Gateway response ("));
+        assert!(message.ends_with(&format!("): {}...", &body[..100])));
+    }
+
+    #[test]
+    fn dump() {
+        let response =
+            Response::parse("HTTP/1.1 404 Not Found\r\nX-A: 1\r\nX-A: 2\r\n\r\nnot here").unwrap();
+        assert_eq!(
+            response.to_string(),
+            "HTTP/1.1 404 Not Found\nX-A: 1\nX-A: 2\n\nnot here"
+        );
+        assert_eq!(
+            response.dump(3),
+            "HTTP/1.1 404 Not Found\nX-A: 1\nX-A: 2\n\nnot... (8 bytes)"
+        );
+        let mut response = Response::parse("HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        response.body = vec![0xff, 0xd8, 0xff, 0xe0];
+        assert_eq!(
+            response.dump(10),
+            "HTTP/1.1 200 OK\n\n(4 bytes of binary data)"
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "json"))]
+    fn serde() {
+        use crate::{Method, Request, Url};
+
+        let response =
+            Response::parse("HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\n\r\nmissing")
+                .unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["status"], 404);
+        assert_eq!(
+            json["headers"],
+            serde_json::json!([["Content-Type", "text/plain"]])
+        );
+        let restored: Response = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.status, 404);
+        assert_eq!(restored.headers, response.headers);
+        assert_eq!(restored.body, b"missing");
+
+        let request = Request::post("example.org/a", "hello").header("X-Test", "1");
+        let json = serde_json::to_string(&request).unwrap();
+        let restored: Request<'static> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.method, Method::POST);
+        assert_eq!(restored.to_string(), request.to_string());
+
+        let url: Url = serde_json::from_str("\"https://example.org/a?b\"").unwrap();
+        assert_eq!(url.path(), "/a");
+        assert!(serde_json::from_str::<Url>("\"http://[::1\"").is_err());
     }
 }
@@ -0,0 +1,153 @@
+use crate::{date::DateTime, random, Response};
+use std::{
+    io,
+    time::{Duration, SystemTime},
+};
+
+/// A policy for retrying failed requests.
+///
+/// Only idempotent requests are retried, when connecting fails, when the request times out, or when the server responds with a 429 or 5xx status.
+/// Attempts are spaced out by an exponentially increasing delay with random jitter,
+/// unless the server asks for a specific delay with the `Retry-After` header, which is also capped at the maximum delay.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::new(5).backoff(Duration::from_millis(200), Duration::from_secs(5));
+/// let request = Request::get("example.org").retry(policy);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    attempts: usize,
+    /// Delay before the first retry.
+    base: Duration,
+    /// Upper bound for the delay between attempts.
+    max: Duration,
+}
+impl RetryPolicy {
+    /// Create a policy which makes at most `attempts` attempts in total.
+    #[must_use]
+    pub fn new(attempts: usize) -> Self {
+        Self {
+            attempts,
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+        }
+    }
+
+    /// Set the initial delay and the maximum delay between attempts.
+    #[must_use]
+    pub fn backoff(self, base: Duration, max: Duration) -> Self {
+        let mut policy = self;
+        policy.base = base;
+        policy.max = max;
+        policy
+    }
+
+    /// Whether another attempt should be made after a number of attempts with the given outcome.
+    pub(crate) fn should_retry(
+        &self,
+        attempt: usize,
+        result: Result<&Response, &io::Error>,
+    ) -> bool {
+        attempt < self.attempts
+            && match result {
+                Ok(response) => response.status == 429 || response.is_server_error(),
+                Err(e) => is_transient(e.kind()),
+            }
+    }
+
+    /// Delay before the next attempt, after a number of attempts with the given outcome.
+    ///
+    /// Uses the `Retry-After` header of 429 and 503 responses if present, so the server can not make the client wait
+    /// longer than the maximum delay. Otherwise, half of the exponential delay is fixed and the other half is random.
+    pub(crate) fn delay(&self, attempt: usize, result: Result<&Response, &io::Error>) -> Duration {
+        if let Some(delay) = result.ok().and_then(retry_after) {
+            return delay.min(self.max);
+        }
+
+        let exponent = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let delay = self
+            .base
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max);
+        let jitter = u32::try_from(random::u64() % 1024).unwrap_or(0);
+        delay / 2 + delay / 2 * jitter / 1024
+    }
+}
+/// Whether an error may not happen again when the request is retried, e.g. because the connection was reset.
+pub(crate) fn is_transient(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::TimedOut
+    )
+}
+
+/// Parse the `Retry-After` header of a rate limited or unavailable response.
+///
+/// The header is either a number of seconds or an HTTP-date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(response.status.as_u16(), 429 | 503) {
+        return None;
+    }
+    let value = response.headers.get("Retry-After")?.trim();
+    value
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .ok()
+        .or_else(|| {
+            let date = SystemTime::from(DateTime::parse(value)?);
+            Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+        })
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_delay() {
+        let policy =
+            RetryPolicy::new(10).backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let error = io::Error::from(io::ErrorKind::TimedOut);
+        let result = Err(&error);
+        let first = policy.delay(1, result);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let third = policy.delay(3, result);
+        assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        let capped = policy.delay(9, result);
+        assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retry_after() {
+        let policy = RetryPolicy::new(3);
+        let response =
+            Response::parse("HTTP/1.1 429 Too Many Requests\r\nRetry-After: 7\r\n\r\n").unwrap();
+        assert_eq!(policy.delay(1, Ok(&response)), Duration::from_secs(7));
+        let response = Response::parse("HTTP/1.1 503 Service Unavailable\r\nRetry-After: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n").unwrap();
+        assert_eq!(policy.delay(1, Ok(&response)), Duration::ZERO);
+
+        // the server can not ask for a longer delay than the maximum
+        let response = Response::parse(
+            "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 18446744073709551615\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(policy.delay(1, Ok(&response)), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,148 @@
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{future::Future, io, net::SocketAddr, time::Duration};
+#[cfg(feature = "tokio")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// How requests which are sent asynchronously open connections and wait, so they can be sent on any async runtime.
+///
+/// Connections are [`futures-io`](futures_io) streams, and everything else, such as the HTTP exchange,
+/// redirects and timeouts, is handled by [`Request::send_with`](crate::Request::send_with).
+/// Transports for tokio ([`Tokio`], with the `tokio` feature) and smol ([`Smol`], with the `smol` feature) are provided.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// # #[cfg(feature = "smol")]
+/// # async fn example() -> Result<(), Error> {
+/// let response = Request::get("localhost:8000").send_with(&Smol).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait AsyncTransport: Send + Sync {
+    /// A connection to a server.
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send;
+
+    /// Open a TCP connection to an address.
+    fn connect(
+        &self,
+        address: SocketAddr,
+    ) -> impl Future<Output = Result<Self::Stream, io::Error>> + Send;
+
+    /// Wait for a duration, e.g. to time out an operation.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+
+    /// Run a blocking function without blocking the runtime, e.g. on a thread pool.
+    ///
+    /// This is used to resolve hosts with the built-in resolver.
+    fn unblock<T: Send + 'static>(
+        &self,
+        function: impl FnOnce() -> Result<T, io::Error> + Send + 'static,
+    ) -> impl Future<Output = Result<T, io::Error>> + Send;
+
+    /// The local address of a connection, if it is known.
+    fn local_addr(&self, stream: &Self::Stream) -> Option<SocketAddr> {
+        let _ = stream;
+        None
+    }
+}
+
+/// A transport which runs on the tokio runtime.
+///
+/// The runtime must have its I/O and time drivers enabled.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tokio;
+#[cfg(feature = "tokio")]
+impl AsyncTransport for Tokio {
+    type Stream = TokioStream;
+
+    async fn connect(&self, address: SocketAddr) -> Result<Self::Stream, io::Error> {
+        tokio::net::TcpStream::connect(address)
+            .await
+            .map(TokioStream)
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    async fn unblock<T: Send + 'static>(
+        &self,
+        function: impl FnOnce() -> Result<T, io::Error> + Send + 'static,
+    ) -> Result<T, io::Error> {
+        tokio::task::spawn_blocking(function)
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    fn local_addr(&self, stream: &Self::Stream) -> Option<SocketAddr> {
+        stream.0.local_addr().ok()
+    }
+}
+
+/// A tokio TCP connection, which implements the [`futures-io`](futures_io) traits.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioStream(pub tokio::net::TcpStream);
+#[cfg(feature = "tokio")]
+impl AsyncRead for TokioStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        let stream = Pin::new(&mut self.get_mut().0);
+        tokio::io::AsyncRead::poll_read(stream, cx, &mut buf).map_ok(|()| buf.filled().len())
+    }
+}
+#[cfg(feature = "tokio")]
+impl AsyncWrite for TokioStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(&mut self.get_mut().0), cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().0), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        tokio::io::AsyncWrite::poll_shutdown(Pin::new(&mut self.get_mut().0), cx)
+    }
+}
+
+/// A transport which runs on smol, or any other runtime which is based on `async-io`, such as async-std.
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Smol;
+#[cfg(feature = "smol")]
+impl AsyncTransport for Smol {
+    type Stream = smol::net::TcpStream;
+
+    async fn connect(&self, address: SocketAddr) -> Result<Self::Stream, io::Error> {
+        smol::net::TcpStream::connect(address).await
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        smol::Timer::after(duration).await;
+    }
+
+    async fn unblock<T: Send + 'static>(
+        &self,
+        function: impl FnOnce() -> Result<T, io::Error> + Send + 'static,
+    ) -> Result<T, io::Error> {
+        smol::unblock(function).await
+    }
+
+    fn local_addr(&self, stream: &Self::Stream) -> Option<SocketAddr> {
+        stream.local_addr().ok()
+    }
+}
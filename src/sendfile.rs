@@ -0,0 +1,28 @@
+// zero-copy file transfers, which is the only place where unsafe code is needed to call into libc
+#![allow(unsafe_code)]
+
+use std::{
+    fs::File,
+    io,
+    os::fd::{AsRawFd, RawFd},
+};
+
+/// Send up to `count` bytes from the current position of a file to a socket, advancing the position of the file.
+///
+/// Returns the number of bytes sent, which is zero at the end of the file,
+/// or if the kernel can not send this file with `sendfile(2)` and it has to be copied instead.
+pub(crate) fn send(socket: RawFd, file: &File, count: usize) -> Result<usize, io::Error> {
+    loop {
+        // safety: both file descriptors are open for the duration of the call, and a null offset uses the file position
+        let sent = unsafe { libc::sendfile(socket, file.as_raw_fd(), std::ptr::null_mut(), count) };
+        if let Ok(sent) = usize::try_from(sent) {
+            return Ok(sent);
+        }
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(libc::EINTR) => {}
+            Some(libc::EINVAL | libc::ENOSYS) => return Ok(0),
+            _ => return Err(error),
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use crate::Method;
+use std::{borrow::Cow, fmt, path::Path};
+
+/// A hook which signs a request right before it is sent.
+///
+/// The signer receives the canonical parts of the request and returns headers which are added to the outgoing message.
+/// Any closure taking a [`Canonical`] and returning a list of headers can be used as a signer.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let signer = |request: &Canonical| vec![("X-Signature".to_string(), request.body.len().to_string())];
+/// let request = Request::post("example.org", "hello").sign(&signer);
+/// ```
+pub trait Sign: Send + Sync {
+    /// Compute the signature headers for a request.
+    fn sign(&self, request: &Canonical) -> Vec<(String, String)>;
+}
+impl<F: Fn(&Canonical) -> Vec<(String, String)> + Send + Sync> Sign for F {
+    fn sign(&self, request: &Canonical) -> Vec<(String, String)> {
+        self(request)
+    }
+}
+impl fmt::Debug for dyn Sign + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sign")
+    }
+}
+
+/// The parts of a request which are covered by a signature.
+#[derive(Debug, Clone)]
+pub struct Canonical<'a> {
+    /// Request method.
+    pub method: Method,
+    /// Host the request is sent to, with the port if it is not the default port.
+    pub host: Cow<'a, str>,
+    /// Request target, including the query string.
+    pub path: Cow<'a, str>,
+    /// Request headers, excluding the generated `Host` header.
+    pub headers: Vec<(&'a str, &'a str)>,
+    /// Request body, which is empty if the body is uploaded from a file.
+    pub body: &'a [u8],
+    /// File the body is uploaded from, if any.
+    pub file: Option<&'a Path>,
+}
+
+/// AWS Signature Version 4 signer.
+///
+/// Signs requests for AWS and S3-compatible services.
+/// Adds the `X-Amz-Date`, `X-Amz-Content-Sha256` and `Authorization` headers (and `X-Amz-Security-Token` if a session token is set).
+/// Bodies uploaded from a file are not read ahead of time, and are signed as `UNSIGNED-PAYLOAD` instead.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let signer = SigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1", "s3");
+/// let request = Request::get("http://bucket.s3.amazonaws.com/key").sign(&signer);
+/// ```
+#[cfg(feature = "sigv4")]
+#[derive(Clone)]
+pub struct SigV4<'a> {
+    access_key: &'a str,
+    secret_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+    session_token: Option<&'a str>,
+}
+#[cfg(feature = "sigv4")]
+impl<'a> SigV4<'a> {
+    /// Create a new signer from a set of credentials, for the given region and service.
+    #[must_use]
+    pub fn new(
+        access_key: &'a str,
+        secret_key: &'a str,
+        region: &'a str,
+        service: &'a str,
+    ) -> Self {
+        Self {
+            access_key,
+            secret_key,
+            region,
+            service,
+            session_token: None,
+        }
+    }
+
+    /// Set the session token used with temporary credentials.
+    #[must_use]
+    pub fn session_token(self, token: &'a str) -> Self {
+        let mut signer = self;
+        signer.session_token = Some(token);
+        signer
+    }
+
+    /// Sign a request as if it was sent at the specified time.
+    fn sign_at(&self, request: &Canonical, time: std::time::SystemTime) -> Vec<(String, String)> {
+        use fmt::Write;
+
+        let t = crate::date::DateTime::from(time);
+        let date = format!("{:04}{:02}{:02}", t.year, t.month, t.day);
+        let timestamp = format!("{date}T{:02}{:02}{:02}Z", t.hour, t.minute, t.second);
+        let payload = match request.file {
+            Some(_) => "UNSIGNED-PAYLOAD".to_string(),
+            None => hex(&sha256(request.body)),
+        };
+
+        // headers added by the signer are signed too
+        let mut added = vec![
+            ("X-Amz-Date".to_string(), timestamp.clone()),
+            ("X-Amz-Content-Sha256".to_string(), payload.clone()),
+        ];
+        if let Some(token) = self.session_token {
+            added.push(("X-Amz-Security-Token".to_string(), token.to_string()));
+        }
+        let mut headers = iter_headers(request, &added);
+        headers.sort();
+        let canonical_headers = headers.iter().fold(String::new(), |mut s, (k, v)| {
+            let _ = writeln!(s, "{k}:{v}");
+            s
+        });
+        let signed_headers = headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        // canonical request: method, uri, query, headers, signed headers, payload hash
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        let query = canonical_query(query);
+        let canonical = format!(
+            "{}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload}",
+            request.method
+        );
+
+        // string to sign and signing key derivation
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, self.service);
+        let string = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            hex(&sha256(canonical.as_bytes()))
+        );
+        let key = [date.as_str(), self.region, self.service, "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.secret_key).into_bytes(),
+                |key, part| hmac(&key, part.as_bytes()).to_vec(),
+            );
+        let signature = hex(&hmac(&key, string.as_bytes()));
+
+        added.push((
+            "Authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                self.access_key
+            ),
+        ));
+        added
+    }
+}
+#[cfg(feature = "sigv4")]
+impl fmt::Debug for SigV4<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the secret key and the session token are hidden, so the signer can be logged without leaking them
+        let redacted = "[redacted]";
+        f.debug_struct("SigV4")
+            .field("access_key", &self.access_key)
+            .field("secret_key", &redacted)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .field("session_token", &self.session_token.map(|_| redacted))
+            .finish()
+    }
+}
+#[cfg(feature = "sigv4")]
+impl Sign for SigV4<'_> {
+    fn sign(&self, request: &Canonical) -> Vec<(String, String)> {
+        self.sign_at(request, std::time::SystemTime::now())
+    }
+}
+
+/// Query parameters sorted by name and value, each encoded so that only unreserved characters are left as-is.
+///
+/// Parameters are decoded first, so they are only encoded once whether or not they were encoded in the URL.
+#[cfg(feature = "sigv4")]
+fn canonical_query(query: &str) -> String {
+    use crate::percent::{decode, encode_unreserved};
+    let mut query = query
+        .split('&')
+        .filter(|q| !q.is_empty())
+        .map(|q| q.split_once('=').unwrap_or((q, "")))
+        .map(|(k, v)| (encode_unreserved(&decode(k)), encode_unreserved(&decode(v))))
+        .collect::<Vec<_>>();
+    query.sort_unstable();
+    query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Lowercased and trimmed headers of a request, including the host and any additional headers.
+#[cfg(feature = "sigv4")]
+fn iter_headers(request: &Canonical, added: &[(String, String)]) -> Vec<(String, String)> {
+    std::iter::once(("host", &*request.host))
+        .chain(request.headers.iter().copied())
+        .chain(added.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        .map(|(k, v)| {
+            (
+                k.to_ascii_lowercase(),
+                v.split_whitespace().collect::<Vec<_>>().join(" "),
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "sigv4")]
+fn hex(bytes: &[u8]) -> String {
+    use fmt::Write;
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// HMAC-SHA256 (RFC 2104).
+#[cfg(feature = "sigv4")]
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = block
+        .iter()
+        .map(|b| b ^ 0x36)
+        .chain(message.iter().copied())
+        .collect::<Vec<u8>>();
+    let outer = block
+        .iter()
+        .map(|b| b ^ 0x5c)
+        .chain(sha256(&inner))
+        .collect::<Vec<u8>>();
+    sha256(&outer)
+}
+
+/// SHA-256 round constants.
+#[cfg(feature = "sigv4")]
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+/// SHA-256 digest (FIPS 180-4).
+#[cfg(feature = "sigv4")]
+#[allow(clippy::many_single_char_names)]
+fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09_e667,
+        0xbb67_ae85,
+        0x3c6e_f372,
+        0xa54f_f53a,
+        0x510e_527f,
+        0x9b05_688c,
+        0x1f83_d9ab,
+        0x5be0_cd19,
+    ];
+
+    // pad the message: 0x80, zeroes, then the bit length as a big endian u64
+    let mut data = message.to_vec();
+    data.push(0x80);
+    data.resize((data.len() + 8).next_multiple_of(64) - 8, 0);
+    data.extend((message.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    for chunk in data.chunks_exact(64) {
+        let mut w = [0u32; 64]; // message schedule
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, s) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(all(test, feature = "sigv4"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn sha256_digest() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&hmac(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn sigv4_signature() {
+        let signer = SigV4::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "service",
+        );
+        let request = Canonical {
+            method: Method::GET,
+            host: "example.amazonaws.com".into(),
+            path: "/".into(),
+            headers: vec![],
+            body: b"",
+            file: None,
+        };
+        let time = UNIX_EPOCH + Duration::from_mins(24_015_636); // 2015-08-30T12:36:00Z
+        let headers = signer.sign_at(&request, time);
+        assert_eq!(
+            headers[0],
+            ("X-Amz-Date".to_string(), "20150830T123600Z".to_string())
+        );
+        assert!(headers[2].1.ends_with(
+            "Signature=726c5c4879a6b4ccbbd3b24edbd6b8826d34f87450fbbf4e85546fc7ba9c1642"
+        ));
+    }
+
+    #[test]
+    fn sigv4_debug() {
+        let signer = SigV4::new("AKIDEXAMPLE", "secret", "us-east-1", "s3").session_token("token");
+        let debug = format!("{signer:?}");
+        assert!(debug.contains("AKIDEXAMPLE") && debug.contains("us-east-1"));
+        assert!(!debug.contains("secret\"") && !debug.contains("token\""));
+    }
+
+    #[test]
+    fn sigv4_query() {
+        assert_eq!(
+            canonical_query("b=2&a=x y&c=%2f&a%20b=1&d&e=~*"),
+            "a=x%20y&a%20b=1&b=2&c=%2F&d=&e=~%2A"
+        );
+
+        // the same parameters are signed the same whether or not they were encoded in the URL
+        let signer = SigV4::new("id", "secret", "us-east-1", "s3");
+        let time = UNIX_EPOCH + Duration::from_mins(24_015_636);
+        let sign = |path: &'static str| {
+            let request = Canonical {
+                method: Method::GET,
+                host: "example.amazonaws.com".into(),
+                path: path.into(),
+                headers: vec![],
+                body: b"",
+                file: None,
+            };
+            signer.sign_at(&request, time)
+        };
+        assert_eq!(sign("/?key=a b&x=1"), sign("/?x=1&key=a%20b"));
+        assert_ne!(sign("/?key=a b"), sign("/?key=a+b"));
+
+        // files are not hashed
+        let request = Canonical {
+            method: Method::PUT,
+            host: "example.amazonaws.com".into(),
+            path: "/".into(),
+            headers: vec![],
+            body: b"",
+            file: Some(Path::new("upload.bin")),
+        };
+        let headers = signer.sign_at(&request, time);
+        assert_eq!(headers[1].1, "UNSIGNED-PAYLOAD");
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{Body, Error, ErrorKind, Request};
+use std::{
+    io::{self, BufRead, BufReader},
+    thread,
+    time::Duration,
+};
+
+/// A client for server-sent events (the `text/event-stream` format of HTML), which yields events as they are received.
+///
+/// The request is sent with an `Accept: text/event-stream` header, and the connection is kept open while events are read from it.
+/// If the connection is closed or fails, the request is sent again after the retry delay, which the server may change,
+/// with a `Last-Event-ID` header with the ID of the last event which was received, so the server can resume the stream.
+/// Errors while connecting are yielded, and connecting is tried again when the next event is asked for.
+/// Responses which are not successful or not an event stream end the iteration after their error, and so does a `204 No Content` response.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use request::*;
+/// for event in EventSource::new(Request::get("localhost:8000/events")) {
+///     let event = event.unwrap();
+///     println!("{}: {}", event.event, event.data);
+/// }
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct EventSource<'a> {
+    /// The request which opens the stream.
+    request: Request<'a>,
+    /// The body of the open stream, if any.
+    stream: Option<BufReader<Body>>,
+    /// ID of the last event which was received, which is sent when reconnecting.
+    last_event_id: Option<String>,
+    /// How long to wait before reconnecting.
+    retry: Duration,
+    /// Whether the stream has been opened before, so opening it again waits for the retry delay.
+    opened: bool,
+    /// Whether the stream is closed for good.
+    closed: bool,
+}
+
+/// An event which was received from an [`EventSource`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Event {
+    /// The type of the event, which is `message` unless the server set it.
+    pub event: String,
+    /// The data of the event, with the lines of multiple `data` fields joined by line breaks.
+    pub data: String,
+    /// The ID of the last event, which is kept until the server sets another one.
+    pub id: Option<String>,
+}
+
+impl<'a> EventSource<'a> {
+    /// Create an event source, which sends the request when the first event is asked for.
+    pub fn new(request: Request<'a>) -> Self {
+        let mut request = request;
+        request.headers.insert("Accept", "text/event-stream");
+        Self {
+            request,
+            stream: None,
+            last_event_id: None,
+            retry: DEFAULT_RETRY,
+            opened: false,
+            closed: false,
+        }
+    }
+
+    /// The ID of the last event which was received, which is sent when reconnecting.
+    #[must_use]
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Send the request, waiting for the retry delay first if the stream was open before.
+    fn open(&mut self) -> Result<BufReader<Body>, Error> {
+        if self.opened {
+            thread::sleep(self.retry);
+        }
+        self.opened = true;
+        let mut request = self.request.clone();
+        if let Some(id) = &self.last_event_id {
+            request.headers.insert("Last-Event-ID", id.as_str());
+        }
+        let (response, body) = request.send_streaming()?;
+        let event_stream = (response.headers.get("Content-Type"))
+            .and_then(|t| t.split(';').next())
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case("text/event-stream"));
+        if response.status == 204 || !response.is_success() || !event_stream {
+            self.closed = true;
+        }
+        if response.status == 204 {
+            let e = io::Error::new(io::ErrorKind::NotFound, "server ended the event stream");
+            return Err(e.into());
+        }
+        if !response.is_success() {
+            let status = format!("{} {}", response.status, response.reason);
+            return Err(response
+                .error_for_status()
+                .err()
+                .unwrap_or_else(|| io::Error::other(status).into()));
+        }
+        if !event_stream {
+            let e = io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response is not an event stream",
+            );
+            return Err(Error::new(ErrorKind::Protocol, e));
+        }
+        debug!("opened event stream {}", self.request.url);
+        Ok(BufReader::new(body))
+    }
+
+    /// Read lines until an event is complete, returning `None` if the stream ends first.
+    fn read_event(&mut self, stream: &mut BufReader<Body>) -> Result<Option<Event>, io::Error> {
+        let mut event = Event::default();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+
+            // events end at an empty line, and are only dispatched if they have data
+            if line.is_empty() {
+                if event.data.is_empty() {
+                    event = Event::default();
+                    continue;
+                }
+                event.data.pop();
+                if event.event.is_empty() {
+                    event.event = "message".to_string();
+                }
+                event.id.clone_from(&self.last_event_id);
+                return Ok(Some(event));
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => event.event = value.to_string(),
+                "data" => {
+                    event.data.push_str(value);
+                    event.data.push('\n');
+                }
+                "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+                "retry" => {
+                    if let Ok(milliseconds) = value.parse() {
+                        self.retry = Duration::from_millis(milliseconds);
+                    }
+                }
+                // comments start with a colon, and unknown fields are ignored
+                _ => {}
+            }
+        }
+    }
+}
+impl Iterator for EventSource<'_> {
+    type Item = Result<Event, Error>;
+
+    /// Wait for the next event, reconnecting if the connection was closed.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.closed {
+                return None;
+            }
+            let mut stream = match self.stream.take() {
+                Some(stream) => stream,
+                None => match self.open() {
+                    Ok(stream) => stream,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            match self.read_event(&mut stream) {
+                Ok(Some(event)) => {
+                    self.stream = Some(stream);
+                    return Some(Ok(event));
+                }
+                // the connection was closed or failed, so it is opened again
+                Ok(None) => debug!("event stream {} ended", self.request.url),
+                Err(e) => debug!("event stream {} failed: {e}", self.request.url),
+            }
+        }
+    }
+}
+
+/// How long to wait before reconnecting, unless the server sets another delay.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+#[cfg(test)]
+mod tests {
+    use crate::{Event, EventSource, Request};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn event_source() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        let server = thread::spawn(move || {
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n";
+            let bodies = [
+                ": comment\nretry: 10\n\ndata: first\ndata: line\r\nid: 1\n\nevent: update\ndata:second\n\ndata: unfinished",
+                "data: third\n\n",
+            ];
+            let mut requests = vec![];
+            for body in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0; 4096];
+                let n = stream.read(&mut buffer).unwrap();
+                requests.push(String::from_utf8_lossy(&buffer[..n]).into_owned());
+                stream
+                    .write_all(format!("{head}{body}").as_bytes())
+                    .unwrap();
+            }
+            requests
+        });
+
+        let mut source = EventSource::new(Request::get(&url));
+        let events: Vec<_> = (&mut source).take(3).map(Result::unwrap).collect();
+        let event = |event: &str, data: &str| Event {
+            event: event.to_string(),
+            data: data.to_string(),
+            id: Some("1".to_string()),
+        };
+        assert_eq!(
+            events,
+            [
+                event("message", "first\nline"),
+                event("update", "second"),
+                event("message", "third")
+            ]
+        );
+        assert_eq!(source.last_event_id(), Some("1"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("Accept: text/event-stream\r\n"));
+        assert!(!requests[0].contains("Last-Event-ID"));
+        assert!(requests[1].contains("Last-Event-ID: 1\r\n"));
+
+        // responses which are not event streams end the iteration
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("localhost:{}", listener.local_addr().unwrap().port());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi")
+                .unwrap();
+        });
+        let mut source = EventSource::new(Request::get(&url));
+        assert!(source.next().unwrap().is_err());
+        assert!(source.next().is_none());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fmt, io, str::FromStr};
+
+/// The status code of a response, e.g. `200` or `404`.
+///
+/// It can be compared with integers, and knows the class of the status and its canonical reason phrase.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let status: StatusCode = "404".parse().unwrap();
+/// assert_eq!(status, 404);
+/// assert_eq!(status, StatusCode::NOT_FOUND);
+/// assert!(status.is_client_error());
+/// assert_eq!(status.canonical_reason(), Some("Not Found"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct StatusCode(u16);
+impl StatusCode {
+    /// `200 OK`
+    pub const OK: Self = Self(200);
+    /// `201 Created`
+    pub const CREATED: Self = Self(201);
+    /// `204 No Content`
+    pub const NO_CONTENT: Self = Self(204);
+    /// `206 Partial Content`
+    pub const PARTIAL_CONTENT: Self = Self(206);
+    /// `301 Moved Permanently`
+    pub const MOVED_PERMANENTLY: Self = Self(301);
+    /// `302 Found`
+    pub const FOUND: Self = Self(302);
+    /// `303 See Other`
+    pub const SEE_OTHER: Self = Self(303);
+    /// `304 Not Modified`
+    pub const NOT_MODIFIED: Self = Self(304);
+    /// `307 Temporary Redirect`
+    pub const TEMPORARY_REDIRECT: Self = Self(307);
+    /// `308 Permanent Redirect`
+    pub const PERMANENT_REDIRECT: Self = Self(308);
+    /// `400 Bad Request`
+    pub const BAD_REQUEST: Self = Self(400);
+    /// `401 Unauthorized`
+    pub const UNAUTHORIZED: Self = Self(401);
+    /// `403 Forbidden`
+    pub const FORBIDDEN: Self = Self(403);
+    /// `404 Not Found`
+    pub const NOT_FOUND: Self = Self(404);
+    /// `407 Proxy Authentication Required`
+    pub const PROXY_AUTHENTICATION_REQUIRED: Self = Self(407);
+    /// `429 Too Many Requests`
+    pub const TOO_MANY_REQUESTS: Self = Self(429);
+    /// `500 Internal Server Error`
+    pub const INTERNAL_SERVER_ERROR: Self = Self(500);
+    /// `502 Bad Gateway`
+    pub const BAD_GATEWAY: Self = Self(502);
+    /// `503 Service Unavailable`
+    pub const SERVICE_UNAVAILABLE: Self = Self(503);
+
+    /// Create a status code, which must have three digits.
+    #[must_use]
+    pub fn new(code: u16) -> Option<Self> {
+        (100..1000).contains(&code).then_some(Self(code))
+    }
+
+    /// The status code as an integer.
+    #[must_use]
+    pub fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// The reason phrase which is usually sent with the status, e.g. `Not Found` for 404.
+    #[must_use]
+    pub fn canonical_reason(self) -> Option<&'static str> {
+        Some(match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Content Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Content",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            _ => return None,
+        })
+    }
+
+    /// Whether the status is informational (100-199).
+    #[must_use]
+    pub fn is_informational(self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    /// Whether the request succeeded, i.e. the status is 200-299.
+    #[must_use]
+    pub fn is_success(self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    /// Whether the status is a redirection (300-399).
+    #[must_use]
+    pub fn is_redirect(self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    /// Whether the status is a client error (400-499).
+    #[must_use]
+    pub fn is_client_error(self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    /// Whether the status is a server error (500-599).
+    #[must_use]
+    pub fn is_server_error(self) -> bool {
+        (500..600).contains(&self.0)
+    }
+}
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+impl PartialEq<StatusCode> for u16 {
+    fn eq(&self, other: &StatusCode) -> bool {
+        *self == other.0
+    }
+}
+impl From<StatusCode> for u16 {
+    fn from(status: StatusCode) -> Self {
+        status.0
+    }
+}
+impl TryFrom<u16> for StatusCode {
+    type Error = io::Error;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        Self::new(code).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid status"))
+    }
+}
+impl FromStr for StatusCode {
+    type Err = io::Error;
+
+    /// Parse a status code of three digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid status");
+        if s.len() != 3 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        s.parse::<u16>().map_err(|_| invalid())?.try_into()
+    }
+}
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StatusCode;
+
+    #[test]
+    fn status_code() {
+        let status = StatusCode::new(503).unwrap();
+        assert!(status.is_server_error() && !status.is_success());
+        assert_eq!(status, 503);
+        assert_eq!(u16::from(status), 503);
+        assert_eq!(status.to_string(), "503");
+        assert_eq!(status.canonical_reason(), Some("Service Unavailable"));
+        assert_eq!(StatusCode::new(299).unwrap().canonical_reason(), None);
+
+        assert_eq!(StatusCode::new(99), None);
+        assert!("20".parse::<StatusCode>().is_err());
+        assert!("+20".parse::<StatusCode>().is_err());
+        assert_eq!(
+            "301".parse::<StatusCode>().unwrap(),
+            StatusCode::MOVED_PERMANENTLY
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Utilities for testing code which sends requests, see [`Server`].
+
+use crate::{
+    body,
+    parser::{Parser, State},
+    uri, Method, Request,
+};
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// A server on a local port which responds to requests with canned responses, and records the requests it receives.
+///
+/// Unlike a [`MockTransport`](crate::MockTransport), requests are sent over real connections,
+/// so the whole request is tested, including how it is sent.
+/// Requests are matched against the routes in the order they were added, by method and path, ignoring the query.
+/// Requests without a matching route receive a `404 Not Found` response, and invalid requests a `400 Bad Request` response.
+/// Each connection is closed after one request, and the server is stopped when it is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::{*, testing::Server};
+/// let server = Server::new().route(Method::GET, "/hello", "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+///
+/// let response = Request::get(server.url("/hello?name=world")).send().unwrap();
+/// assert_eq!(response.text(), "hello");
+/// assert_eq!(Request::get(server.url("/missing")).send().unwrap().status, 404);
+///
+/// let requests = server.requests();
+/// assert_eq!(requests.len(), 2);
+/// assert_eq!(requests[0].get_method(), Method::GET);
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct Server {
+    /// The address the server is listening on.
+    address: SocketAddr,
+    /// Routes and received requests, shared with the thread which serves connections.
+    state: Arc<Mutex<Routes>>,
+    /// Whether the server keeps accepting connections.
+    running: Arc<AtomicBool>,
+    /// The thread which serves connections.
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The routes of a server, and the requests it received.
+#[derive(Debug, Default)]
+struct Routes {
+    /// Method, path and raw response message of each route, in order.
+    routes: Vec<(Method, String, String)>,
+    /// Raw messages of the valid requests which have been received, in order.
+    requests: Vec<Vec<u8>>,
+}
+
+impl Server {
+    /// Start a server on a free port of the loopback interface, without any routes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no port can be bound.
+    pub fn new() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+        let address = listener.local_addr().expect("failed to bind test server");
+        let state = Arc::new(Mutex::new(Routes::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let (state, running) = (Arc::clone(&state), Arc::clone(&running));
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(mut stream) = stream {
+                        // failed connections only affect the client which opened them
+                        let _ = serve(&mut stream, &state);
+                    }
+                }
+            })
+        };
+        Self {
+            address,
+            state,
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    /// Respond to requests with a method and path with a raw HTTP response message.
+    ///
+    /// Routes can also be added while the server is being used.
+    pub fn route(self, method: Method, path: &str, response: &str) -> Self {
+        let mut routes = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        (routes.routes).push((method, path.to_string(), response.to_string()));
+        drop(routes);
+        self
+    }
+
+    /// The address the server is listening on.
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The URL of a path on the server, e.g. `http://127.0.0.1:12345/hello` for `/hello`.
+    #[must_use]
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    /// The requests which have been received so far, in order.
+    ///
+    /// The URL of each request is built from its `Host` header and target, see [`Request::parse`].
+    #[must_use]
+    pub fn requests(&self) -> Vec<Request<'static>> {
+        let routes = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        (routes.requests.iter())
+            .filter_map(|message| Request::parse(message).ok())
+            .map(owned)
+            .collect()
+    }
+}
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for Server {
+    fn drop(&mut self) {
+        // the thread is waiting for a connection, so it is woken up by connecting to it
+        self.running.store(false, Ordering::SeqCst);
+        if TcpStream::connect(self.address).is_ok() {
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Receive a request on a connection, record it and send the response of its route.
+fn serve(stream: &mut TcpStream, state: &Mutex<Routes>) -> Result<(), io::Error> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let message = receive(stream)?;
+    let Ok(request) = Request::parse(&message) else {
+        return stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n");
+    };
+    let path = uri::path(&request.url).unwrap_or("/");
+    let mut routes = state.lock().unwrap_or_else(PoisonError::into_inner);
+    let response = (routes.routes.iter())
+        .find(|(method, p, _)| *method == request.method && p == path)
+        .map_or(NOT_FOUND, |(_, _, response)| response.as_str())
+        .to_string();
+    routes.requests.push(message.clone());
+    drop(routes);
+    stream.write_all(response.as_bytes())
+}
+
+/// Receive the head and body of a request, answering `Expect: 100-continue` before the body is sent.
+fn receive(stream: &mut TcpStream) -> Result<Vec<u8>, io::Error> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    let (mut message, mut buffer) = (Vec::new(), [0; 4096]);
+    let mut parser = Parser::request();
+    let mut length = 0;
+    while parser.state() != State::Body {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(message);
+        }
+        message.extend(&buffer[..n]);
+        length += parser.feed(&buffer[..n]).map_err(invalid)?;
+    }
+    let head = parser.finish_request().map_err(invalid)?;
+    if (head.headers.get("Expect")).is_some_and(|e| e.eq_ignore_ascii_case("100-continue")) {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+
+    // the body is delimited by its length or by the last chunk, and is empty otherwise
+    let chunked = body::is_chunked_message(&head.version, &head.headers);
+    let end = (head.headers.content_length()).map(|l| length + usize::try_from(l).unwrap_or(0));
+    loop {
+        let complete = if chunked {
+            message[length..].ends_with(b"0\r\n\r\n")
+        } else {
+            end.is_none_or(|end| message.len() >= end)
+        };
+        if complete {
+            return Ok(message);
+        }
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(message);
+        }
+        message.extend(&buffer[..n]);
+    }
+}
+
+/// A parsed request which owns its body, so it outlives the received message.
+fn owned(request: Request<'_>) -> Request<'static> {
+    let mut owned =
+        Request::new(request.url.into_owned(), request.method).http_version(request.version);
+    owned.headers = request.headers;
+    owned.body = Cow::Owned(request.body.into_owned());
+    owned
+}
+
+/// The response to requests without a matching route.
+const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+
+/// How long to wait for the rest of a request before giving up on the connection.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(test)]
+mod tests {
+    use super::Server;
+    use crate::{Method, Request};
+
+    #[test]
+    fn routes() {
+        let server = Server::new().route(
+            Method::POST,
+            "/echo",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n",
+        );
+        let response = Request::post(server.url("/echo"), "hello")
+            .header("Content-Length", "5")
+            .expect_continue(true)
+            .send()
+            .unwrap();
+        assert_eq!(response.status, 201);
+        // routes match the method, and can be added later
+        assert_eq!(
+            Request::get(server.url("/echo")).send().unwrap().status,
+            404
+        );
+        let server = server.route(
+            Method::GET,
+            "/echo",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        );
+        assert_eq!(
+            Request::get(server.url("/echo")).send().unwrap().status,
+            200
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].get_body(), b"hello");
+        assert_eq!(
+            requests[0].get_headers().get("Expect"),
+            Some("100-continue")
+        );
+        assert_eq!(requests[0].get_url(), format!("{}/echo", server.address()));
+    }
+}
@@ -0,0 +1,233 @@
+#[cfg(feature = "async")]
+use futures_io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "async")]
+use futures_lite::{future, ready};
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::{
+    io::{self, prelude::*},
+    sync::{Arc, LazyLock},
+};
+#[cfg(feature = "async")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Client configuration trusting the Mozilla root certificates.
+static CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("default protocol versions are supported")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Arc::new(config)
+});
+
+/// Run TLS over a stream, verifying that the server is the given host.
+///
+/// The handshake is performed on the first read or write.
+pub(crate) fn wrap<S: Read + Write>(
+    stream: S,
+    host: &str,
+) -> Result<StreamOwned<ClientConnection, S>, io::Error> {
+    Ok(StreamOwned::new(connection(host)?, stream))
+}
+
+/// A client connection which verifies that the server is the given host.
+fn connection(host: &str) -> Result<ClientConnection, io::Error> {
+    // addresses are verified without the brackets around IPv6 addresses
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid tls server name"))?;
+    ClientConnection::new(CONFIG.clone(), name).map_err(io::Error::other)
+}
+
+/// TLS over an asynchronous stream, which is driven as the stream is polled.
+#[cfg(feature = "async")]
+pub(crate) struct AsyncStream<S> {
+    /// The TLS state, which encrypts what is written and decrypts what is read.
+    connection: ClientConnection,
+    /// The stream the encrypted data is sent over.
+    stream: S,
+}
+#[cfg(feature = "async")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncStream<S> {
+    /// Run TLS over a stream, verifying that the server is the given host once the handshake is performed.
+    pub(crate) fn new(stream: S, host: &str) -> Result<Self, io::Error> {
+        Ok(Self {
+            connection: connection(host)?,
+            stream,
+        })
+    }
+
+    /// Perform the handshake, so that errors during it are told apart from those of the request.
+    pub(crate) async fn handshake(&mut self) -> Result<(), io::Error> {
+        future::poll_fn(|cx| self.poll_handshake(cx)).await
+    }
+
+    /// Exchange handshake messages until the handshake is complete.
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        while self.connection.is_handshaking() {
+            while self.connection.wants_write() {
+                ready!(self.poll_write_tls(cx))?;
+            }
+            if self.connection.is_handshaking() && self.connection.wants_read() {
+                if ready!(self.poll_read_tls(cx))? == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed during the tls handshake",
+                    )));
+                }
+                self.process()?;
+            }
+        }
+        while self.connection.wants_write() {
+            ready!(self.poll_write_tls(cx))?;
+        }
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    /// Receive encrypted data from the stream.
+    fn poll_read_tls(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, io::Error>> {
+        let mut stream = NonBlocking {
+            stream: &mut self.stream,
+            cx,
+        };
+        match self.connection.read_tls(&mut stream) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+
+    /// Send encrypted data to the stream, erroring if the stream accepts none of it.
+    fn poll_write_tls(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, io::Error>> {
+        let mut stream = NonBlocking {
+            stream: &mut self.stream,
+            cx,
+        };
+        match self.connection.write_tls(&mut stream) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Ok(0) => Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+            result => Poll::Ready(result),
+        }
+    }
+
+    /// Decrypt the data which was received, erroring if it is invalid.
+    fn process(&mut self) -> Result<(), io::Error> {
+        match self.connection.process_new_packets() {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                // the alert which tells the server about the error is sent if it can be without waiting
+                let mut stream = NonBlocking {
+                    stream: &mut self.stream,
+                    cx: &mut Context::from_waker(Waker::noop()),
+                };
+                let _ = self.connection.write_tls(&mut stream);
+                Err(io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+
+    /// Send the encrypted data which is buffered.
+    fn poll_flush_tls(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.connection.writer().flush()?;
+        while self.connection.wants_write() {
+            ready!(self.poll_write_tls(cx))?;
+        }
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+}
+#[cfg(feature = "async")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for AsyncStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        loop {
+            // a closed connection is read as the end of the stream, or as an error if it was cut off
+            match this.connection.reader().read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return Poll::Ready(result),
+            }
+            ready!(this.poll_read_tls(cx))?;
+            this.process()?;
+        }
+    }
+}
+#[cfg(feature = "async")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for AsyncStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        loop {
+            // the data is buffered, and sent as far as the stream accepts it
+            let written = this.connection.writer().write(buf)?;
+            while this.connection.wants_write() {
+                match this.poll_write_tls(cx) {
+                    Poll::Ready(result) => result?,
+                    Poll::Pending if written > 0 => return Poll::Ready(Ok(written)),
+                    Poll::Pending => return Poll::Pending,
+                };
+            }
+            if written > 0 || buf.is_empty() {
+                return Poll::Ready(Ok(written));
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.get_mut().poll_flush_tls(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        this.connection.send_close_notify();
+        ready!(this.poll_flush_tls(cx))?;
+        Pin::new(&mut this.stream).poll_close(cx)
+    }
+}
+
+/// An asynchronous stream which is read and written like a non-blocking one, so rustls can drive it.
+///
+/// Operations which would wait fail with [`io::ErrorKind::WouldBlock`], after the task is registered to be woken.
+#[cfg(feature = "async")]
+struct NonBlocking<'a, 'b, S> {
+    /// The stream which is driven.
+    stream: &'a mut S,
+    /// The context of the task which polls the stream.
+    cx: &'a mut Context<'b>,
+}
+#[cfg(feature = "async")]
+impl<S: AsyncRead + Unpin> Read for NonBlocking<'_, '_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match Pin::new(&mut *self.stream).poll_read(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+#[cfg(feature = "async")]
+impl<S: AsyncWrite + Unpin> Write for NonBlocking<'_, '_, S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        match Pin::new(&mut *self.stream).poll_write(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match Pin::new(&mut *self.stream).poll_flush(self.cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
@@ -0,0 +1,881 @@
+use crate::{dns, Response};
+#[cfg(not(target_arch = "wasm32"))]
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, RawFd};
+use std::{
+    fmt,
+    io::{self, prelude::*},
+    iter,
+    net::{IpAddr, SocketAddr, TcpStream},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::Path};
+
+/// A connection to a server, which can be read from and written to.
+///
+/// Timeouts are only enforced for streams which support them, which includes [`TcpStream`].
+pub trait ReadWrite: Read + Write + Send {
+    /// Set the timeout for each read, or remove it with `None`.
+    ///
+    /// Streams which do not support timeouts ignore this.
+    ///
+    /// # Errors
+    ///
+    /// May error if the timeout is zero, or if the stream is closed.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    /// Set the timeout for each write, or remove it with `None`.
+    ///
+    /// Streams which do not support timeouts ignore this.
+    ///
+    /// # Errors
+    ///
+    /// May error if the timeout is zero, or if the stream is closed.
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    /// Address of the other end of the connection, if it is a network connection.
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Address of this end of the connection, if it is a network connection.
+    fn local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// The socket which writes go to directly, if any, which allows files to be sent without copying them through userspace.
+    ///
+    /// Streams which transform or buffer their writes must return `None`, which is the default.
+    #[cfg(target_os = "linux")]
+    fn raw_socket(&self) -> Option<RawFd> {
+        None
+    }
+
+    /// Whether the connection can no longer be used, because the other end closed it or sent data which was not asked for.
+    ///
+    /// This is checked without blocking before a request is sent over a connection from a custom [`Connector`],
+    /// which may hand out connections that were idle for a while. Streams which can not tell return `false`, which is the default.
+    fn is_closed(&self) -> bool {
+        false
+    }
+
+    /// Another handle to the same connection, so it can be read from and written to at the same time, e.g. from different threads.
+    ///
+    /// # Errors
+    ///
+    /// Errors with [`io::ErrorKind::Unsupported`] by default, for streams which can not be shared, such as TLS streams.
+    fn try_clone(&self) -> Result<Box<dyn ReadWrite>, io::Error> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "connection can not be cloned",
+        ))
+    }
+}
+impl ReadWrite for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        TcpStream::local_addr(self).ok()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_socket(&self) -> Option<RawFd> {
+        Some(self.as_raw_fd())
+    }
+
+    fn is_closed(&self) -> bool {
+        // peek without blocking: nothing to read means the connection is idle and open
+        let peeked = (self.set_nonblocking(true)).and_then(|()| self.peek(&mut [0]));
+        let _ = self.set_nonblocking(false);
+        !matches!(peeked, Err(e) if e.kind() == io::ErrorKind::WouldBlock)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn ReadWrite>, io::Error> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+}
+#[cfg(feature = "tls")]
+impl ReadWrite for rustls::StreamOwned<rustls::ClientConnection, Box<dyn ReadWrite>> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.sock.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.sock.set_write_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.sock.peer_addr()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.sock.local_addr()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sock.is_closed()
+    }
+}
+
+#[cfg(unix)]
+impl ReadWrite for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_socket(&self) -> Option<RawFd> {
+        Some(self.as_raw_fd())
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn ReadWrite>, io::Error> {
+        Ok(Box::new(UnixStream::try_clone(self)?))
+    }
+}
+
+/// A stream which limits the rate of reads and writes, by sleeping when data is transferred too quickly.
+pub(crate) struct Throttled {
+    inner: Box<dyn ReadWrite>,
+    read: Option<Limiter>,
+    write: Option<Limiter>,
+}
+impl Throttled {
+    /// Limit the rates of a stream, in bytes per second.
+    pub(crate) fn new(inner: Box<dyn ReadWrite>, read: Option<u64>, write: Option<u64>) -> Self {
+        Self {
+            inner,
+            read: read.map(Limiter::new),
+            write: write.map(Limiter::new),
+        }
+    }
+}
+impl Read for Throttled {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(limiter) = &mut self.read {
+            limiter.transferred(n);
+        }
+        Ok(n)
+    }
+}
+impl Write for Throttled {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(limiter) = &mut self.write else {
+            return self.inner.write(buf);
+        };
+        // write in small parts, so that the rate is even during large writes
+        let n = self.inner.write(&buf[..limiter.chunk(buf.len())])?;
+        limiter.transferred(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+impl ReadWrite for Throttled {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.inner.set_write_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+/// A connection which is taken over after the head of a response has been received, e.g. by a WebSocket or a tunnel.
+///
+/// Bytes which were received along with the head of the response are read first, and then the connection itself.
+/// The read and write timeouts of the request stay in place.
+pub struct Upgraded {
+    /// Bytes which were received after the head of the response.
+    received: io::Cursor<Vec<u8>>,
+    /// The connection to the server.
+    stream: Box<dyn ReadWrite>,
+}
+impl Upgraded {
+    /// Take over a connection, starting with the bytes which were received after the head of the response.
+    pub(crate) fn new(received: Vec<u8>, stream: Box<dyn ReadWrite>) -> Self {
+        Self {
+            received: io::Cursor::new(received),
+            stream,
+        }
+    }
+}
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.received.read(buf)? {
+            0 => self.stream.read(buf),
+            n => Ok(n),
+        }
+    }
+}
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+impl ReadWrite for Upgraded {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.stream.set_write_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.stream.local_addr()
+    }
+}
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded").finish_non_exhaustive()
+    }
+}
+
+/// Tracks the average rate of a transfer.
+struct Limiter {
+    /// Maximum rate in bytes per second.
+    rate: u64,
+    start: Instant,
+    bytes: u64,
+}
+impl Limiter {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// How many bytes to transfer at once: about a tenth of a second's worth.
+    fn chunk(&self, len: usize) -> usize {
+        usize::try_from(self.rate / 10).map_or(len, |n| n.clamp(1, len.max(1)))
+    }
+
+    /// Record a transfer, sleeping until the average rate is within the limit.
+    fn transferred(&mut self, n: usize) {
+        self.bytes += n as u64;
+        #[allow(clippy::cast_precision_loss)]
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.rate as f64);
+        if let Some(wait) = expected.checked_sub(self.start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// A way of opening connections to servers (or proxies), over which requests are sent.
+///
+/// Proxy tunnels and TLS are established on top of the returned stream, so a connector only needs to provide the raw connection.
+/// Connectors may hand out connections which were used before: if the server has closed one while it was idle
+/// (see [`ReadWrite::is_closed`]), the connector is asked for another connection once, before the request is sent.
+/// Requests with a connector do not use a [`Pool`](crate::Pool), so how long idle connections are kept,
+/// and how old they may get, is up to the connector rather than [`Pool::idle_timeout`](crate::Pool::idle_timeout)
+/// and [`Pool::max_age`](crate::Pool::max_age).
+/// Any closure with the same signature as [`Connector::connect`] can be used as a connector.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// // connect to a fixed address, regardless of the host
+/// let connector = |_: &str, port: u16, _| {
+///     let stream = std::net::TcpStream::connect(("127.0.0.1", port))?;
+///     Ok(Box::new(stream) as Box<dyn ReadWrite>)
+/// };
+/// let request = Request::get("example.org").connector(&connector);
+/// ```
+pub trait Connector: Send + Sync {
+    /// Open a connection to a host and port, giving up after the timeout if there is one.
+    ///
+    /// # Errors
+    ///
+    /// May error if the host can not be resolved or reached, or if the timeout is exceeded.
+    fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error>;
+}
+impl<F> Connector for F
+where
+    F: Fn(&str, u16, Option<Duration>) -> Result<Box<dyn ReadWrite>, io::Error> + Send + Sync,
+{
+    fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        self(host, port, timeout)
+    }
+}
+impl fmt::Debug for dyn Connector + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Connector")
+    }
+}
+
+/// The default connector, which resolves the host with DNS and opens a TCP connection.
+///
+/// # Examples
+///
+/// ```rust
+/// # use request::*;
+/// let connector = TcpConnector::new().local_address("192.0.2.10:0".parse().unwrap());
+/// let request = Request::get("example.org").connector(&connector);
+/// ```
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct TcpConnector {
+    /// Local address the socket is bound to before connecting.
+    pub(crate) local_address: Option<SocketAddr>,
+    /// Network interface the socket is bound to (`SO_BINDTODEVICE`).
+    pub(crate) interface: Option<String>,
+    /// Whether small writes are sent immediately (`TCP_NODELAY`).
+    nodelay: Option<bool>,
+    /// Idle time before keepalive probes are sent, and the interval between them.
+    keepalive: Option<(Duration, Option<Duration>)>,
+    /// Size of the send buffer (`SO_SNDBUF`).
+    send_buffer_size: Option<usize>,
+    /// Size of the receive buffer (`SO_RCVBUF`).
+    recv_buffer_size: Option<usize>,
+}
+impl TcpConnector {
+    /// Create a new connector with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind the socket to a local address before connecting, e.g. to choose the network on a multi-homed host.
+    ///
+    /// A port of 0 lets the operating system choose the port.
+    pub fn local_address(self, address: SocketAddr) -> Self {
+        let mut connector = self;
+        connector.local_address = Some(address);
+        connector
+    }
+
+    /// Send the connection through a specific network interface, such as `eth1` or a VPN interface (`SO_BINDTODEVICE`).
+    ///
+    /// This is only supported on Linux and Android, connecting fails with [`io::ErrorKind::Unsupported`] elsewhere.
+    pub fn interface(self, name: &str) -> Self {
+        let mut connector = self;
+        connector.interface = Some(name.to_string());
+        connector
+    }
+
+    /// Set whether small writes are sent immediately, instead of being buffered (`TCP_NODELAY`).
+    ///
+    /// This lowers latency for requests which are written in multiple parts.
+    pub fn nodelay(self, enabled: bool) -> Self {
+        let mut connector = self;
+        connector.nodelay = Some(enabled);
+        connector
+    }
+
+    /// Enable TCP keepalive (`SO_KEEPALIVE`), sending probes after the connection has been idle for some time.
+    ///
+    /// The interval between probes is only set on platforms which support it.
+    pub fn keepalive(self, idle: Duration, interval: Option<Duration>) -> Self {
+        let mut connector = self;
+        connector.keepalive = Some((idle, interval));
+        connector
+    }
+
+    /// Set the size of the socket send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(self, size: usize) -> Self {
+        let mut connector = self;
+        connector.send_buffer_size = Some(size);
+        connector
+    }
+
+    /// Set the size of the socket receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(self, size: usize) -> Self {
+        let mut connector = self;
+        connector.recv_buffer_size = Some(size);
+        connector
+    }
+
+    /// Apply the socket options, before connecting.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn configure(&self, socket: &Socket) -> Result<(), io::Error> {
+        if let Some(local) = self.local_address {
+            socket.bind(&local.into())?;
+        }
+        if let Some(interface) = &self.interface {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.bind_device(Some(interface.as_bytes()))?;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("binding to interface {interface} is not supported on this platform"),
+            ));
+        }
+        if let Some(nodelay) = self.nodelay {
+            socket.set_tcp_nodelay(nodelay)?;
+        }
+        if let Some((idle, interval)) = self.keepalive {
+            let keepalive = TcpKeepalive::new().with_time(idle);
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "freebsd",
+                target_os = "windows"
+            ))]
+            let keepalive = match interval {
+                Some(interval) => keepalive.with_interval(interval),
+                None => keepalive,
+            };
+            let _ = interval; // not supported on every platform
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+
+    /// Open a connection to an address which has already been resolved, giving up after the timeout if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn connect_to(
+        &self,
+        address: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let socket = Socket::new(
+            Domain::for_address(address),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        self.configure(&socket)?;
+        match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out"));
+                }
+                socket.connect_timeout(&address.into(), left)?;
+            }
+            None => socket.connect(&address.into())?,
+        }
+        debug!("connected to {address}");
+        Ok(Box::new(TcpStream::from(socket)))
+    }
+
+    /// Browsers can not open TCP connections, so requests are sent with the fetch API instead.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn connect_to(
+        &self,
+        address: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        let _ = (address, timeout);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "TCP connections are not supported on wasm32",
+        ))
+    }
+
+    /// Open a connection to whichever of the resolved addresses accepts it first (Happy Eyeballs, RFC 8305).
+    ///
+    /// IPv6 and IPv4 addresses are tried alternately, starting with IPv6. A new attempt is started whenever the
+    /// previous one fails or has not succeeded within a short delay, so a broken network does not stall the connection.
+    pub(crate) fn connect_any(
+        &self,
+        addresses: &[IpAddr],
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut error = io::Error::new(io::ErrorKind::NotFound, "no address to connect to");
+
+        // a socket bound to a local address can only connect to addresses of the same family
+        let family = |address: &IpAddr| {
+            (self.local_address).is_none_or(|local| local.is_ipv6() == address.is_ipv6())
+        };
+        let addresses = (addresses.iter().copied())
+            .filter(family)
+            .collect::<Vec<_>>();
+        if let [address] = addresses[..] {
+            return self.connect_to(SocketAddr::new(address, port), timeout);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let mut pending = 0;
+        let mut candidates = interleave(&addresses).peekable();
+        loop {
+            // start the next attempt, in the background
+            if let Some(address) = candidates.next() {
+                let connector = self.clone();
+                let sender = sender.clone();
+                let timeout = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                thread::spawn(move || {
+                    let result = connector.connect_to(SocketAddr::new(address, port), timeout);
+                    if let Err(e) = &result {
+                        debug!("failed to connect to {address}: {e}");
+                    }
+                    let _ = sender.send(result);
+                });
+                pending += 1;
+            } else if pending == 0 {
+                return Err(error);
+            }
+
+            // wait for an attempt to finish, or until the next attempt should be started
+            let wait = match (candidates.peek(), deadline) {
+                (Some(_), Some(d)) => {
+                    CONNECTION_ATTEMPT_DELAY.min(d.saturating_duration_since(Instant::now()))
+                }
+                (Some(_), None) => CONNECTION_ATTEMPT_DELAY,
+                (None, Some(d)) => d.saturating_duration_since(Instant::now()),
+                (None, None) => Duration::MAX,
+            };
+            match receiver.recv_timeout(wait) {
+                Ok(Ok(stream)) => return Ok(stream),
+                Ok(Err(e)) => {
+                    error = e;
+                    pending -= 1;
+                }
+                Err(_) if candidates.peek().is_none() => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out"));
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+impl Connector for TcpConnector {
+    fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let addresses = dns::resolve(host, timeout)?;
+        debug!("resolved {host} to {addresses:?}");
+        let timeout = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        self.connect_any(&addresses, port, timeout)
+    }
+}
+
+/// How long to wait for a connection attempt before starting the next one in parallel (RFC 8305).
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Order addresses for connection attempts, alternating between IPv6 and IPv4 addresses and starting with IPv6.
+fn interleave(addresses: &[IpAddr]) -> impl Iterator<Item = IpAddr> + '_ {
+    let mut ipv6 = addresses.iter().copied().filter(IpAddr::is_ipv6);
+    let mut ipv4 = addresses.iter().copied().filter(IpAddr::is_ipv4);
+    let mut ipv6_next = false;
+    iter::from_fn(move || {
+        ipv6_next = !ipv6_next;
+        if ipv6_next {
+            ipv6.next().or_else(|| ipv4.next())
+        } else {
+            ipv4.next().or_else(|| ipv6.next())
+        }
+    })
+}
+
+/// A connector which connects to a Unix domain socket, regardless of the host and port.
+///
+/// See [`Request::unix_socket`](crate::Request::unix_socket).
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy)]
+pub struct UnixConnector<'a>(pub &'a Path);
+#[cfg(unix)]
+impl Connector for UnixConnector<'_> {
+    fn connect(
+        &self,
+        _: &str,
+        _: u16,
+        _: Option<Duration>,
+    ) -> Result<Box<dyn ReadWrite>, io::Error> {
+        let stream = UnixStream::connect(self.0)?;
+        debug!("connected to {}", self.0.display());
+        Ok(Box::new(stream))
+    }
+}
+
+/// Ask a proxy to establish a tunnel to `authority` (host and port), using the CONNECT method.
+///
+/// Returns the response of the proxy. If it is successful (2xx), the stream is connected to the target, and can be used for any protocol.
+pub(crate) fn tunnel(
+    stream: &mut (impl Read + Write),
+    authority: &str,
+    headers: &[(String, String)],
+) -> Result<Response, io::Error> {
+    stream.write_all(tunnel_request(authority, headers).as_bytes())?;
+
+    // read the response head, one byte at a time to avoid consuming data sent through the tunnel
+    let mut head = Vec::new();
+    let mut byte = [0u8];
+    while !is_tunnel_head(&head)? {
+        match stream.read(&mut byte)? {
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "proxy closed the connection",
+                ))
+            }
+            _ => head.push(byte[0]),
+        }
+    }
+    tunnel_response(head)
+}
+
+/// The CONNECT request which asks a proxy to establish a tunnel to `authority`.
+pub(crate) fn tunnel_request(authority: &str, headers: &[(String, String)]) -> String {
+    let headers = iter::once(format!("Host: {authority}"))
+        .chain(headers.iter().map(|(k, v)| format!("{k}: {v}")))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    format!("CONNECT {authority} HTTP/1.1\r\n{headers}\r\n\r\n")
+}
+
+/// Whether the head of the response of a proxy to a CONNECT request has been received, erroring if it is too large.
+pub(crate) fn is_tunnel_head(head: &[u8]) -> Result<bool, io::Error> {
+    if head.len() > 8192 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "proxy response too large",
+        ));
+    }
+    Ok(head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n"))
+}
+
+/// Parse the head of the response of a proxy to a CONNECT request.
+pub(crate) fn tunnel_response(head: Vec<u8>) -> Result<Response, io::Error> {
+    let head = String::from_utf8(head)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "received invalid data"))?;
+    Response::parse(head).map_err(|s| io::Error::new(io::ErrorKind::InvalidData, s))
+}
+
+/// Establish a tunnel to a host through a SOCKS5 proxy (RFC 1928), optionally authenticating with a username and password (RFC 1929).
+///
+/// If an address is given, the proxy connects to it directly, otherwise the proxy resolves the host name.
+pub(crate) fn socks5(
+    stream: &mut (impl Read + Write),
+    host: &str,
+    address: Option<IpAddr>,
+    port: u16,
+    auth: Option<(&str, &str)>,
+) -> Result<(), io::Error> {
+    // negotiate the authentication method: none or username/password
+    let greeting = socks5::greeting(auth);
+    stream.write_all(&greeting)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    socks5::check_method(reply, greeting)?;
+    if let Some((username, password)) = auth {
+        stream.write_all(&socks5::credentials(username, password)?)?;
+        stream.read_exact(&mut reply)?;
+        socks5::check_authenticated(reply)?;
+    }
+
+    // connect to the target, and read the reply, including the bound address which is not used
+    stream.write_all(&socks5::connect(host, address, port)?)?;
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    let bound = if let Some(length) = socks5::check_reply(reply)? {
+        length
+    } else {
+        let mut n = [0u8];
+        stream.read_exact(&mut n)?;
+        usize::from(n[0])
+    };
+    stream.read_exact(&mut vec![0u8; bound + 2])?;
+
+    Ok(())
+}
+
+/// The messages of a SOCKS5 handshake, which are exchanged by [`socks5`] and its asynchronous counterpart.
+pub(crate) mod socks5 {
+    use std::{io, net::IpAddr};
+
+    /// The greeting which offers an authentication method: none, or username and password if there are credentials.
+    pub(crate) fn greeting(auth: Option<(&str, &str)>) -> [u8; 3] {
+        let method = if auth.is_some() { 2 } else { 0 };
+        [5, 1, method]
+    }
+
+    /// Check that the proxy chose the offered authentication method.
+    pub(crate) fn check_method(reply: [u8; 2], greeting: [u8; 3]) -> Result<(), io::Error> {
+        if reply == [5, greeting[2]] {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "socks proxy rejected authentication method",
+        ))
+    }
+
+    /// The username and password to authenticate with.
+    pub(crate) fn credentials(username: &str, password: &str) -> Result<Vec<u8>, io::Error> {
+        let mut message = vec![1, length(username)?];
+        message.extend(username.as_bytes());
+        message.push(length(password)?);
+        message.extend(password.as_bytes());
+        Ok(message)
+    }
+
+    /// Check that the proxy accepted the credentials.
+    pub(crate) fn check_authenticated(reply: [u8; 2]) -> Result<(), io::Error> {
+        if reply[1] == 0 {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "socks proxy authentication failed",
+        ))
+    }
+
+    /// The request to connect to the target: [version, command, reserved, address type, address, port].
+    pub(crate) fn connect(
+        host: &str,
+        address: Option<IpAddr>,
+        port: u16,
+    ) -> Result<Vec<u8>, io::Error> {
+        let mut message = vec![5, 1, 0];
+        match address {
+            Some(IpAddr::V4(ip)) => {
+                message.push(1);
+                message.extend(ip.octets());
+            }
+            Some(IpAddr::V6(ip)) => {
+                message.push(4);
+                message.extend(ip.octets());
+            }
+            None => {
+                message.extend([3, length(host)?]);
+                message.extend(host.as_bytes());
+            }
+        }
+        message.extend(port.to_be_bytes());
+        Ok(message)
+    }
+
+    /// Check the start of the reply to the connect request, returning the length of the bound address which follows,
+    /// or `None` if it is a domain name, whose length is in the next byte.
+    pub(crate) fn check_reply(reply: [u8; 4]) -> Result<Option<usize>, io::Error> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if reply[0] != 5 {
+            return Err(invalid("invalid socks reply"));
+        }
+        if reply[1] != 0 {
+            let reason = match reply[1] {
+                2 => "connection not allowed by ruleset",
+                3 => "network unreachable",
+                4 => "host unreachable",
+                5 => "connection refused",
+                6 => "ttl expired",
+                _ => "general failure",
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("socks proxy error: {reason}"),
+            ));
+        }
+        match reply[3] {
+            1 => Ok(Some(4)),
+            4 => Ok(Some(16)),
+            3 => Ok(None),
+            _ => Err(invalid("invalid socks address type")),
+        }
+    }
+
+    /// The length of a value, which is at most 255 bytes.
+    fn length(s: &str) -> Result<u8, io::Error> {
+        u8::try_from(s.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "value too long for socks"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interleave, TcpConnector};
+    use std::{
+        net::{IpAddr, TcpListener},
+        time::Duration,
+    };
+
+    #[test]
+    fn happy_eyeballs() {
+        let addresses = ["192.0.2.1", "192.0.2.2", "2001:db8::1", "192.0.2.3"]
+            .map(|a| a.parse::<IpAddr>().unwrap());
+        let ordered = interleave(&addresses)
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordered,
+            ["2001:db8::1", "192.0.2.1", "192.0.2.2", "192.0.2.3"]
+        );
+
+        // the IPv6 address refuses the connection, so the IPv4 address is used
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let addresses = ["::1", "127.0.0.1"].map(|a| a.parse::<IpAddr>().unwrap());
+        let connector = TcpConnector::new();
+        assert!(connector.connect_any(&addresses, port, None).is_ok());
+        assert!(connector.connect_any(&addresses[..1], port, None).is_err());
+
+        // unreachable addresses of the same family are skipped in order
+        let addresses =
+            ["127.0.0.2", "127.0.0.3", "127.0.0.1"].map(|a| a.parse::<IpAddr>().unwrap());
+        let timeout = Some(Duration::from_secs(5));
+        assert!(connector.connect_any(&addresses, port, timeout).is_ok());
+        assert!(connector
+            .connect_any(&addresses[..2], port, timeout)
+            .is_err());
+    }
+}
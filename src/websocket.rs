@@ -0,0 +1,456 @@
+use crate::{base64, random, uri, Error, ErrorKind, Method, Request, Response, Upgraded};
+use std::{
+    borrow::Cow,
+    io::{self, prelude::*},
+};
+
+impl Request<'_> {
+    /// Open a WebSocket (RFC 6455) to the URL of the request, which may have a `ws` or `wss` scheme.
+    ///
+    /// The opening handshake is sent as a GET request with the headers of the request, whatever its method,
+    /// and the `Sec-WebSocket-Accept` header of the response is checked against the key which was sent.
+    /// Redirects are not followed. The response is returned along with the WebSocket, e.g. to check the negotiated protocol.
+    ///
+    /// # Errors
+    ///
+    /// May error for the same reasons as [`Request::send`], or with [`ErrorKind::Protocol`]
+    /// if the server does not switch protocols or sends an invalid handshake.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use request::*;
+    /// let (_, mut socket) = Request::get("ws://localhost:8000/chat").websocket().unwrap();
+    /// socket.send(&Message::Text("hello".to_string())).unwrap();
+    /// while let Ok(message) = socket.receive() {
+    ///     match message {
+    ///         Message::Text(text) => println!("received {text}"),
+    ///         Message::Close(_) => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn websocket(&self) -> Result<(Response, WebSocket), Error> {
+        Ok(self.handshake().map_err(Error::at(&self.url))?)
+    }
+
+    /// Send the opening handshake of a WebSocket and check the response.
+    fn handshake(&self) -> Result<(Response, WebSocket), io::Error> {
+        let mut request = self.clone();
+        request.url = match uri::scheme(&self.url) {
+            Some("ws") => Cow::Owned(format!("http{}", &self.url[2..])),
+            Some("wss") => Cow::Owned(format!("https{}", &self.url[3..])),
+            _ => request.url,
+        };
+        request.method = Method::GET;
+        request.body = Cow::Borrowed(&[]);
+        let key =
+            base64::encode(&[random::u64().to_be_bytes(), random::u64().to_be_bytes()].concat());
+        request.headers.insert("Connection", "Upgrade");
+        request.headers.insert("Upgrade", "websocket");
+        request.headers.insert("Sec-WebSocket-Version", "13");
+        request.headers.insert("Sec-WebSocket-Key", key.as_str());
+
+        let (response, stream) = request.exchange_upgrade()?;
+        let upgrade = response.headers.get("Upgrade");
+        if response.status != 101 || !upgrade.is_some_and(|u| u.eq_ignore_ascii_case("websocket")) {
+            let message = format!(
+                "server refused to open websocket: {} {}",
+                response.status, response.reason
+            );
+            return Err(protocol(message));
+        }
+        if response.headers.get("Sec-WebSocket-Accept") != Some(&accept(&key)) {
+            return Err(protocol("invalid websocket accept key".to_string()));
+        }
+        debug!("opened websocket to {}", self.url);
+        Ok((response, WebSocket::new(stream)))
+    }
+}
+
+/// A WebSocket connection, which sends and receives messages, see [`Request::websocket`].
+///
+/// Fragmented messages are reassembled, pings are answered with pongs, and a close frame from the server is answered with a close frame.
+/// Messages are sent in a single frame. Extensions, such as compression, are not supported.
+pub struct WebSocket {
+    /// The connection to the server.
+    stream: Upgraded,
+    /// Opcode and payload of a fragmented message which has not been received completely.
+    partial: Option<(u8, Vec<u8>)>,
+    /// Whether a close frame has been sent.
+    closed: bool,
+}
+impl WebSocket {
+    /// Start exchanging frames over a connection on which the handshake is complete.
+    fn new(stream: Upgraded) -> Self {
+        Self {
+            stream,
+            partial: None,
+            closed: false,
+        }
+    }
+
+    /// Send a message in a single frame.
+    ///
+    /// Sending a [`Message::Close`] closes the WebSocket, after which the server should respond with a close frame.
+    ///
+    /// # Errors
+    ///
+    /// May error if the connection fails, or if the WebSocket has been closed.
+    pub fn send(&mut self, message: &Message) -> Result<(), io::Error> {
+        if self.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "websocket is closed",
+            ));
+        }
+        match message {
+            Message::Text(text) => self.write_frame(TEXT, text.as_bytes()),
+            Message::Binary(data) => self.write_frame(BINARY, data),
+            Message::Ping(data) => self.write_frame(PING, data),
+            Message::Pong(data) => self.write_frame(PONG, data),
+            Message::Close(reason) => {
+                let payload = (reason.iter())
+                    .flat_map(|(code, reason)| {
+                        [&code.to_be_bytes()[..], reason.as_bytes()].concat()
+                    })
+                    .collect::<Vec<_>>();
+                self.closed = true;
+                self.write_frame(CLOSE, &payload)
+            }
+        }
+    }
+
+    /// Receive the next message, waiting for it if necessary.
+    ///
+    /// Pings are answered before they are returned, and so is a close frame, unless the WebSocket was closed first.
+    ///
+    /// # Errors
+    ///
+    /// May error if the connection fails, or if the server sends an invalid frame or a message which is too large.
+    pub fn receive(&mut self) -> Result<Message, io::Error> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                CLOSE => {
+                    let reason = (payload.len() >= 2).then(|| {
+                        let code = u16::from_be_bytes([payload[0], payload[1]]);
+                        (code, String::from_utf8_lossy(&payload[2..]).into_owned())
+                    });
+                    if !self.closed {
+                        self.closed = true;
+                        self.write_frame(CLOSE, &payload[..payload.len().min(2)])?;
+                    }
+                    return Ok(Message::Close(reason));
+                }
+                PING => {
+                    if !self.closed {
+                        self.write_frame(PONG, &payload)?;
+                    }
+                    return Ok(Message::Ping(payload));
+                }
+                PONG => return Ok(Message::Pong(payload)),
+                CONTINUATION => match &mut self.partial {
+                    Some((_, data)) if data.len() + payload.len() <= MAX_MESSAGE => {
+                        data.extend(payload);
+                    }
+                    Some(_) => return Err(invalid("websocket message too large")),
+                    None => return Err(invalid("unexpected websocket continuation frame")),
+                },
+                TEXT | BINARY if self.partial.is_none() => self.partial = Some((opcode, payload)),
+                _ => return Err(invalid("unexpected websocket frame")),
+            }
+            if !fin {
+                continue;
+            }
+            match self.partial.take() {
+                Some((TEXT, data)) => {
+                    let text = String::from_utf8(data)
+                        .map_err(|_| invalid("websocket text is not utf-8"))?;
+                    return Ok(Message::Text(text));
+                }
+                Some((_, data)) => return Ok(Message::Binary(data)),
+                None => {}
+            }
+        }
+    }
+
+    /// The connection to the server, e.g. to set its timeouts.
+    #[must_use]
+    pub fn get_ref(&self) -> &Upgraded {
+        &self.stream
+    }
+
+    /// Take the connection to the server, to read and write frames yourself.
+    #[must_use]
+    pub fn into_inner(self) -> Upgraded {
+        self.stream
+    }
+
+    /// Write a final frame, masked with a random key as frames from clients must be.
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), io::Error> {
+        let mut frame = vec![0x80 | opcode];
+        let length = payload.len();
+        match (u8::try_from(length), u16::try_from(length)) {
+            (Ok(length), _) if length < 0x7e => frame.push(0x80 | length),
+            (_, Ok(length)) => {
+                frame.push(0x80 | 0x7e);
+                frame.extend(length.to_be_bytes());
+            }
+            _ => {
+                frame.push(0x80 | 0x7f);
+                frame.extend((length as u64).to_be_bytes());
+            }
+        }
+        let mask = random::u64().to_be_bytes();
+        frame.extend(&mask[..4]);
+        frame.extend((payload.iter().enumerate()).map(|(i, byte)| byte ^ mask[i % 4]));
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    /// Read a frame, returning whether it is final, its opcode and its unmasked payload.
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), io::Error> {
+        let mut head = [0u8; 2];
+        self.stream.read_exact(&mut head)?;
+        let (fin, opcode, masked) = (head[0] & 0x80 != 0, head[0] & 0x0f, head[1] & 0x80 != 0);
+        let length = match head[1] & 0x7f {
+            0x7e => {
+                let mut length = [0u8; 2];
+                self.stream.read_exact(&mut length)?;
+                u64::from(u16::from_be_bytes(length))
+            }
+            0x7f => {
+                let mut length = [0u8; 8];
+                self.stream.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => u64::from(length),
+        };
+        let length = (usize::try_from(length).ok())
+            .filter(|&length| length <= MAX_MESSAGE)
+            .ok_or_else(|| invalid("websocket message too large"))?;
+        let mut mask = [0u8; 4];
+        if masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; length];
+        self.stream.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+impl std::fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocket")
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message which is sent or received over a [`WebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// A text message.
+    Text(String),
+    /// A binary message.
+    Binary(Vec<u8>),
+    /// A ping, which is answered with a pong with the same data.
+    Ping(Vec<u8>),
+    /// A pong, in response to a ping.
+    Pong(Vec<u8>),
+    /// A close frame, with a status code and a reason if one was given.
+    Close(Option<(u16, String)>),
+}
+
+/// Opcodes of the frames.
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// Maximum size of a received message.
+const MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+/// The `Sec-WebSocket-Accept` value which the server must respond with to a key.
+fn accept(key: &str) -> String {
+    base64::encode(&sha1(
+        format!("{key}258EAFA5-E914-47DA-95CA-C5AB0DC85B11").as_bytes(),
+    ))
+}
+
+/// The error for an invalid frame.
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The error for an invalid handshake.
+fn protocol(message: String) -> io::Error {
+    let e = io::Error::new(io::ErrorKind::InvalidData, message);
+    Error::new(ErrorKind::Protocol, e).into()
+}
+
+/// SHA-1 digest (FIPS 180-4), which is only used for the handshake.
+#[allow(clippy::many_single_char_names)]
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+
+    // pad the message: 0x80, zeroes, then the bit length as a big endian u64
+    let mut data = message.to_vec();
+    data.push(0x80);
+    data.resize((data.len() + 8).next_multiple_of(64) - 8, 0);
+    data.extend((message.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    for chunk in data.chunks_exact(64) {
+        let mut w = [0u32; 80]; // message schedule
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..40 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = (a.rotate_left(5))
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, s) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::accept;
+    use crate::{ErrorKind, Message, Request};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn accept_key() {
+        // the example of RFC 6455, section 1.3
+        assert_eq!(
+            accept("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "ws://localhost:{}/chat",
+            listener.local_addr().unwrap().port()
+        );
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0; 4096];
+            let n = stream.read(&mut buffer).unwrap();
+            let request = String::from_utf8_lossy(&buffer[..n]).into_owned();
+            let key = (request.lines())
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+                .unwrap();
+            let head = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept(key)
+            );
+            // a fragmented text message, with a ping in between, sent along with the head
+            let frames = [&b"\x01\x03hel"[..], b"\x89\x01!", b"\x80\x02lo"].concat();
+            stream
+                .write_all(&[head.as_bytes(), &frames].concat())
+                .unwrap();
+
+            // the pong, a masked binary message and a close frame
+            let mut received = vec![];
+            while received.len() < 7 + 11 + 8 {
+                let n = stream.read(&mut buffer).unwrap();
+                if n == 0 {
+                    break;
+                }
+                received.extend(&buffer[..n]);
+            }
+            stream.write_all(b"\x88\x02\x03\xe8").unwrap();
+            (request, received)
+        });
+
+        let (response, mut socket) = Request::post(&url, "ignored").websocket().unwrap();
+        assert_eq!(response.status, 101);
+        assert_eq!(socket.receive().unwrap(), Message::Ping(b"!".to_vec()));
+        assert_eq!(
+            socket.receive().unwrap(),
+            Message::Text("hello".to_string())
+        );
+        socket.send(&Message::Binary(vec![1, 2, 3, 4, 5])).unwrap();
+        socket
+            .send(&Message::Close(Some((1000, String::new()))))
+            .unwrap();
+        assert_eq!(
+            socket.receive().unwrap(),
+            Message::Close(Some((1000, String::new())))
+        );
+        assert!(socket.send(&Message::Text("late".to_string())).is_err());
+
+        let (request, received) = server.join().unwrap();
+        assert!(request.starts_with("GET /chat HTTP/1.1\r\n"));
+        assert!(request.contains("Upgrade: websocket\r\n"));
+        // frames from the client are masked, so the payloads are unmasked with the key after the length
+        let unmask = |frame: &[u8]| -> Vec<u8> {
+            let (mask, payload) = frame[2..].split_at(4);
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b ^ mask[i % 4])
+                .collect()
+        };
+        assert_eq!(&received[..2], [0x8a, 0x81]);
+        assert_eq!(unmask(&received[..7]), b"!");
+        assert_eq!(&received[7..9], [0x82, 0x85]);
+        assert_eq!(unmask(&received[7..18]), [1, 2, 3, 4, 5]);
+        assert_eq!(&received[18..20], [0x88, 0x82]);
+        assert_eq!(unmask(&received[18..]), [0x03, 0xe8]);
+
+        // servers which do not switch protocols are refused
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://localhost:{}", listener.local_addr().unwrap().port());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        let error = Request::get(&url).websocket().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Protocol);
+    }
+}